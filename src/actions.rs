@@ -0,0 +1,375 @@
+use bevy::input::InputSystem;
+use bevy::input::gamepad::{GamepadAxisType, GamepadButtonType};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Bindings are read from (and saved to) this file in the working directory
+pub const BINDINGS_FILE: &str = "bindings.cfg";
+
+// Stick values below this are treated as centred
+const STICK_DEADZONE: f32 = 0.15;
+
+/// Everything the player can do, independent of the device that triggers it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    StrafeLeft,
+    StrafeRight,
+    Jump,
+    /// Fly down in creative and spectator modes
+    Descend,
+    Sprint,
+    /// Crouch; also keeps the player from walking off ledges
+    Crouch,
+    Break,
+    /// Place the selected `.vox` structure
+    Place,
+    CycleStructure,
+    /// Eat a carried apple
+    Eat,
+    ExportCornerA,
+    ExportCornerB,
+    ExportSelection,
+    CyclePov,
+    ToggleSpectator,
+    /// Climb full-block ledges without jumping
+    ToggleStepAssist,
+    ZoomIn,
+    ZoomOut,
+    ToggleMinimap,
+    GrabCursor,
+    ReleaseCursor,
+    OpenBindings,
+    /// Open the chat and command console
+    OpenConsole,
+    /// Accept the focused menu option (respawn, ...)
+    Confirm,
+}
+
+impl Action {
+    pub const ALL: [Action; 26] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Jump,
+        Action::Descend,
+        Action::Sprint,
+        Action::Crouch,
+        Action::Break,
+        Action::Place,
+        Action::CycleStructure,
+        Action::Eat,
+        Action::ExportCornerA,
+        Action::ExportCornerB,
+        Action::ExportSelection,
+        Action::CyclePov,
+        Action::ToggleSpectator,
+        Action::ToggleStepAssist,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ToggleMinimap,
+        Action::GrabCursor,
+        Action::ReleaseCursor,
+        Action::OpenBindings,
+        Action::OpenConsole,
+        Action::Confirm,
+    ];
+
+    pub fn name(self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(GamepadButtonType),
+}
+
+// Keys that can be written to and read back from the bindings file
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE,
+    KeyCode::KeyF, KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ,
+    KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO,
+    KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT,
+    KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Escape, KeyCode::Tab, KeyCode::Backspace,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight, KeyCode::CapsLock,
+    KeyCode::Backquote, KeyCode::Minus, KeyCode::Equal, KeyCode::BracketLeft,
+    KeyCode::BracketRight, KeyCode::Semicolon, KeyCode::Quote, KeyCode::Comma,
+    KeyCode::Period, KeyCode::Slash, KeyCode::Backslash,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+];
+
+const BINDABLE_MOUSE: &[MouseButton] = &[
+    MouseButton::Left,
+    MouseButton::Right,
+    MouseButton::Middle,
+    MouseButton::Back,
+    MouseButton::Forward,
+];
+
+const BINDABLE_PAD: &[GamepadButtonType] = &[
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::LeftThumb,
+    GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+impl Binding {
+    /// Whether this binding can be saved to the bindings file.
+    pub fn is_bindable(self) -> bool {
+        match self {
+            Binding::Key(key) => BINDABLE_KEYS.contains(&key),
+            Binding::Mouse(button) => BINDABLE_MOUSE.contains(&button),
+            Binding::Pad(button) => BINDABLE_PAD.contains(&button),
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Binding::Key(key) => format!("Key:{:?}", key),
+            Binding::Mouse(button) => format!("Mouse:{:?}", button),
+            Binding::Pad(button) => format!("Pad:{:?}", button),
+        }
+    }
+
+    fn parse(text: &str) -> Option<Binding> {
+        let (device, name) = text.trim().split_once(':')?;
+        let matches = |debug: String| debug == name.trim();
+        match device.trim() {
+            "Key" => BINDABLE_KEYS.iter().copied().find(|k| matches(format!("{:?}", k))).map(Binding::Key),
+            "Mouse" => BINDABLE_MOUSE.iter().copied().find(|b| matches(format!("{:?}", b))).map(Binding::Mouse),
+            "Pad" => BINDABLE_PAD.iter().copied().find(|b| matches(format!("{:?}", b))).map(Binding::Pad),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct InputBindings {
+    pub map: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use Binding::*;
+        let defaults: [(Action, Vec<Binding>); 26] = [
+            (Action::MoveForward, vec![Key(KeyCode::KeyW)]),
+            (Action::MoveBack, vec![Key(KeyCode::KeyS)]),
+            (Action::StrafeLeft, vec![Key(KeyCode::KeyA)]),
+            (Action::StrafeRight, vec![Key(KeyCode::KeyD)]),
+            (Action::Jump, vec![Key(KeyCode::Space), Pad(GamepadButtonType::South)]),
+            (Action::Descend, vec![Key(KeyCode::KeyC), Pad(GamepadButtonType::East)]),
+            (Action::Sprint, vec![Key(KeyCode::ShiftLeft), Pad(GamepadButtonType::LeftThumb)]),
+            (Action::Crouch, vec![Key(KeyCode::ControlLeft), Pad(GamepadButtonType::RightThumb)]),
+            (Action::Break, vec![Mouse(MouseButton::Left), Pad(GamepadButtonType::RightTrigger2)]),
+            (Action::Place, vec![Key(KeyCode::KeyP), Pad(GamepadButtonType::LeftTrigger2)]),
+            (Action::CycleStructure, vec![Key(KeyCode::KeyO), Pad(GamepadButtonType::West)]),
+            (Action::Eat, vec![Key(KeyCode::KeyF), Pad(GamepadButtonType::DPadRight)]),
+            (Action::ExportCornerA, vec![Key(KeyCode::KeyZ)]),
+            (Action::ExportCornerB, vec![Key(KeyCode::KeyX)]),
+            (Action::ExportSelection, vec![Key(KeyCode::F9)]),
+            (Action::CyclePov, vec![Key(KeyCode::F5), Pad(GamepadButtonType::North)]),
+            (Action::ToggleSpectator, vec![Key(KeyCode::KeyN)]),
+            (Action::ToggleStepAssist, vec![Key(KeyCode::KeyK)]),
+            (Action::ZoomIn, vec![Pad(GamepadButtonType::DPadUp)]),
+            (Action::ZoomOut, vec![Pad(GamepadButtonType::DPadDown)]),
+            (Action::ToggleMinimap, vec![Key(KeyCode::KeyM), Pad(GamepadButtonType::Select)]),
+            (Action::GrabCursor, vec![Mouse(MouseButton::Left)]),
+            (Action::ReleaseCursor, vec![Key(KeyCode::Escape)]),
+            (Action::OpenBindings, vec![Key(KeyCode::F1), Pad(GamepadButtonType::Start)]),
+            (Action::OpenConsole, vec![Key(KeyCode::KeyT), Key(KeyCode::Slash)]),
+            (Action::Confirm, vec![Key(KeyCode::Enter), Pad(GamepadButtonType::South)]),
+        ];
+        Self {
+            map: defaults.into_iter().collect(),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.map.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// One `Action = Device:Button, Device:Button` line per action.
+    pub fn to_text(&self) -> String {
+        let mut out = String::from("# Input bindings: Action = Key:<KeyCode>, Mouse:<Button>, Pad:<Button>\n");
+        for action in Action::ALL {
+            let bindings: Vec<String> = self.get(action).iter().map(|b| b.label()).collect();
+            out.push_str(&format!("{} = {}\n", action.name(), bindings.join(", ")));
+        }
+        out
+    }
+
+    /// Starts from the defaults so actions missing from the file keep working.
+    pub fn from_text(text: &str) -> Self {
+        let mut bindings = Self::default();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, rest)) = line.split_once('=') else {
+                warn!("{}:{}: expected `Action = bindings`", BINDINGS_FILE, line_no + 1);
+                continue;
+            };
+            let Some(action) = Action::from_name(name.trim()) else {
+                warn!("{}:{}: unknown action '{}'", BINDINGS_FILE, line_no + 1, name.trim());
+                continue;
+            };
+            let mut list = Vec::new();
+            for part in rest.split(',').filter(|p| !p.trim().is_empty()) {
+                match Binding::parse(part) {
+                    Some(binding) => list.push(binding),
+                    None => warn!("{}:{}: unknown binding '{}'", BINDINGS_FILE, line_no + 1, part.trim()),
+                }
+            }
+            bindings.map.insert(action, list);
+        }
+        bindings
+    }
+
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_text(&text),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        if let Err(e) = fs::write(path, self.to_text()) {
+            warn!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Per-frame action state, resolved from the bindings before `Update` runs.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    /// Strafe (x, right positive) and forward (y) input in -1..=1
+    pub movement: Vec2,
+    /// Right-stick look input in -1..=1
+    pub look: Vec2,
+    /// Set while a menu owns the input; only `OpenBindings`,
+    /// `ReleaseCursor` and `Confirm` still come through.
+    pub suppressed: bool,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputBindings::load(Path::new(BINDINGS_FILE)))
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+fn stick(axes: &Axis<GamepadAxis>, gamepads: &Gamepads, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+    let mut value = Vec2::ZERO;
+    for gamepad in gamepads.iter() {
+        value.x += axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0);
+        value.y += axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0);
+    }
+    if value.length() < STICK_DEADZONE {
+        Vec2::ZERO
+    } else {
+        value.clamp_length_max(1.0)
+    }
+}
+
+fn update_action_state(
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut state: ResMut<ActionState>,
+) {
+    let is_down = |binding: &Binding, just: bool| match *binding {
+        Binding::Key(key) => {
+            if just { keyboard.just_pressed(key) } else { keyboard.pressed(key) }
+        }
+        Binding::Mouse(button) => {
+            if just { mouse.just_pressed(button) } else { mouse.pressed(button) }
+        }
+        Binding::Pad(button) => gamepads.iter().any(|gamepad| {
+            let button = GamepadButton::new(gamepad, button);
+            if just { pad_buttons.just_pressed(button) } else { pad_buttons.pressed(button) }
+        }),
+    };
+
+    let suppressed = state.suppressed;
+    state.pressed.clear();
+    state.just_pressed.clear();
+    for action in Action::ALL {
+        if suppressed && !matches!(action, Action::OpenBindings | Action::ReleaseCursor | Action::Confirm) {
+            continue;
+        }
+        let list = bindings.get(action);
+        if list.iter().any(|b| is_down(b, false)) {
+            state.pressed.insert(action);
+        }
+        if list.iter().any(|b| is_down(b, true)) {
+            state.just_pressed.insert(action);
+        }
+    }
+
+    if suppressed {
+        state.movement = Vec2::ZERO;
+        state.look = Vec2::ZERO;
+        return;
+    }
+
+    let digital = Vec2::new(
+        state.pressed(Action::StrafeRight) as i32 as f32 - state.pressed(Action::StrafeLeft) as i32 as f32,
+        state.pressed(Action::MoveForward) as i32 as f32 - state.pressed(Action::MoveBack) as i32 as f32,
+    );
+    let left = stick(&pad_axes, &gamepads, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    state.movement = (digital + left).clamp_length_max(1.0);
+    state.look = stick(&pad_axes, &gamepads, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+}
@@ -0,0 +1,217 @@
+use crate::actions::{Action, ActionState, BINDINGS_FILE, Binding, InputBindings};
+use bevy::prelude::*;
+use bevy::window::CursorGrabMode;
+use std::path::Path;
+
+const ROW_IDLE: Color = Color::srgba(0.15, 0.15, 0.15, 0.9);
+const ROW_HOVER: Color = Color::srgba(0.25, 0.25, 0.25, 0.9);
+const ROW_LISTENING: Color = Color::srgba(0.45, 0.35, 0.1, 0.9);
+
+#[derive(Resource, Default)]
+pub struct BindingsMenu {
+    pub open: bool,
+    listening: Option<Action>,
+    // The click that starts listening must not be captured as the new binding
+    armed: bool,
+    root: Option<Entity>,
+}
+
+#[derive(Component)]
+struct BindingRow(Action);
+
+#[derive(Component)]
+struct BindingRowText(Action);
+
+pub struct BindingsMenuPlugin;
+
+impl Plugin for BindingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BindingsMenu>().add_systems(
+            Update,
+            (toggle_menu, click_rows, capture_binding, refresh_rows).chain(),
+        );
+    }
+}
+
+fn toggle_menu(
+    mut commands: Commands,
+    mut menu: ResMut<BindingsMenu>,
+    mut actions: ResMut<ActionState>,
+    mut windows: Query<&mut Window>,
+) {
+    let close_requested = menu.open
+        && menu.listening.is_none()
+        && actions.just_pressed(Action::ReleaseCursor);
+    if !actions.just_pressed(Action::OpenBindings) && !close_requested {
+        return;
+    }
+    // F1 while listening is captured as a binding instead
+    if menu.listening.is_some() {
+        return;
+    }
+
+    menu.open = !menu.open;
+    actions.suppressed = menu.open;
+
+    if let Some(root) = menu.root.take() {
+        commands.entity(root).despawn_recursive();
+    }
+    if !menu.open {
+        return;
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+
+    let root = commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.0),
+                top: Val::Px(40.0),
+                margin: UiRect::left(Val::Px(-220.0)),
+                width: Val::Px(440.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(3.0),
+                ..default()
+            },
+            background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+            ..default()
+        })
+        .with_children(|panel| {
+            panel.spawn(TextBundle::from_section(
+                "Controls — click an action, then press a key, mouse or gamepad button\n\
+                 Esc cancels, Backspace clears, F1 closes",
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            for action in Action::ALL {
+                panel
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                                ..default()
+                            },
+                            background_color: ROW_IDLE.into(),
+                            ..default()
+                        },
+                        BindingRow(action),
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font_size: 13.0,
+                                    color: Color::srgb(0.9, 0.9, 0.9),
+                                    ..default()
+                                },
+                            ),
+                            BindingRowText(action),
+                        ));
+                    });
+            }
+        })
+        .id();
+    menu.root = Some(root);
+}
+
+fn click_rows(
+    mut menu: ResMut<BindingsMenu>,
+    rows: Query<(&Interaction, &BindingRow), Changed<Interaction>>,
+) {
+    if !menu.open || menu.listening.is_some() {
+        return;
+    }
+    for (interaction, row) in rows.iter() {
+        if *interaction == Interaction::Pressed {
+            menu.listening = Some(row.0);
+            menu.armed = false;
+        }
+    }
+}
+
+fn capture_binding(
+    mut menu: ResMut<BindingsMenu>,
+    mut bindings: ResMut<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let Some(action) = menu.listening else { return };
+    if !menu.armed {
+        menu.armed = true;
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        menu.listening = None;
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        bindings.map.insert(action, Vec::new());
+        bindings.save(Path::new(BINDINGS_FILE));
+        menu.listening = None;
+        return;
+    }
+
+    let pressed = keyboard
+        .get_just_pressed()
+        .map(|k| Binding::Key(*k))
+        .chain(mouse.get_just_pressed().map(|b| Binding::Mouse(*b)))
+        .chain(pad_buttons.get_just_pressed().map(|b| Binding::Pad(b.button_type)))
+        .find(|b| b.is_bindable());
+    let Some(binding) = pressed else { return };
+
+    // Replace the binding for the same device, keep the others
+    let list = bindings.map.entry(action).or_default();
+    list.retain(|b| std::mem::discriminant(b) != std::mem::discriminant(&binding));
+    list.push(binding);
+    bindings.save(Path::new(BINDINGS_FILE));
+    info!("Bound {:?} to {}", action, binding.label());
+    menu.listening = None;
+}
+
+fn refresh_rows(
+    menu: Res<BindingsMenu>,
+    bindings: Res<InputBindings>,
+    mut rows: Query<(&Interaction, &BindingRow, &mut BackgroundColor)>,
+    mut texts: Query<(&BindingRowText, &mut Text)>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    for (interaction, row, mut background) in rows.iter_mut() {
+        let color = if menu.listening == Some(row.0) {
+            ROW_LISTENING
+        } else if *interaction == Interaction::Hovered {
+            ROW_HOVER
+        } else {
+            ROW_IDLE
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+
+    for (row, mut text) in texts.iter_mut() {
+        let value = if menu.listening == Some(row.0) {
+            format!("{:<16} press a button...", row.0.name())
+        } else {
+            let labels: Vec<String> = bindings.get(row.0).iter().map(|b| b.label()).collect();
+            let labels = if labels.is_empty() { "(unbound)".to_string() } else { labels.join(", ") };
+            format!("{:<16} {}", row.0.name(), labels)
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -11,10 +11,40 @@ pub enum BlockType {
 }
 
 impl BlockType {
+    /// Every block type, indexed by its wire/save id
+    pub const ALL: [BlockType; 8] = [
+        BlockType::Air,
+        BlockType::Grass,
+        BlockType::Dirt,
+        BlockType::Stone,
+        BlockType::Sand,
+        BlockType::Wood,
+        BlockType::Leaves,
+        BlockType::Water,
+    ];
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<BlockType> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Looks a block up by its name, ignoring case (`stone`, `Leaves`)
+    pub fn from_name(name: &str) -> Option<BlockType> {
+        Self::ALL.into_iter().find(|b| format!("{:?}", b).eq_ignore_ascii_case(name))
+    }
+
     pub fn is_solid(&self) -> bool {
         !matches!(self, BlockType::Air)
     }
 
+    /// Bodies collide with these; water is swum through
+    pub fn is_collidable(&self) -> bool {
+        self.is_solid() && !matches!(self, BlockType::Water)
+    }
+
     pub fn is_transparent(&self) -> bool {
         matches!(self, BlockType::Air | BlockType::Leaves | BlockType::Water)
     }
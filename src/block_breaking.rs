@@ -1,7 +1,11 @@
+use crate::actions::{Action, ActionState};
 use crate::block::BlockType;
 use crate::camera::{MainCamera, Player};
-use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
-use crate::world::World as GameWorld;
+use crate::hunger::LeavesBroken;
+use crate::journal::BlockEditor;
+use crate::mobs::Mob;
+use crate::physics::{PhysicsPosition, VoxelBody, VoxelBodyBundle, ray_box_distance};
+use crate::voxel_world::VoxelWorld;
 use bevy::prelude::*;
 
 const BREAK_TIME: f32 = 1.0;
@@ -12,11 +16,12 @@ pub struct BreakingState {
     pub target: Option<IVec3>,
     pub progress: f32,
     pub crack_entity: Option<Entity>,
+    /// A mob in front of the targeted block; clicking attacks it instead
+    pub mob_target: Option<Entity>,
 }
 
 #[derive(Component)]
 pub struct BreakParticle {
-    pub velocity: Vec3,
     pub lifetime: f32,
     pub age: f32,
 }
@@ -24,7 +29,6 @@ pub struct BreakParticle {
 #[derive(Component)]
 pub struct BlockDrop {
     pub _block_type: BlockType,
-    pub origin_y: f32,
     pub age: f32,
 }
 
@@ -51,52 +55,20 @@ impl Plugin for BlockBreakingPlugin {
     }
 }
 
-fn raycast_block(
-    world: &GameWorld,
-    chunks: &Query<&crate::chunk::Chunk>,
-    origin: Vec3,
-    direction: Vec3,
-) -> Option<IVec3> {
+fn raycast_block(voxels: &VoxelWorld, origin: Vec3, direction: Vec3) -> Option<IVec3> {
     let steps = (REACH * 10.0) as usize;
     let step_size = REACH / steps as f32;
 
-    for i in 1..=steps {
-        let pos = origin + direction * (i as f32 * step_size);
-        let bx = pos.x.floor() as i32;
-        let by = pos.y.floor() as i32;
-        let bz = pos.z.floor() as i32;
-
-        if by < 0 || by >= CHUNK_HEIGHT as i32 {
-            continue;
-        }
-
-        let chunk_x = bx.div_euclid(CHUNK_SIZE as i32);
-        let chunk_z = bz.div_euclid(CHUNK_SIZE as i32);
-        let chunk_pos = IVec3::new(chunk_x, 0, chunk_z);
-
-        let Some(&entity) = world.chunks.get(&chunk_pos) else {
-            continue;
-        };
-        let Ok(chunk) = chunks.get(entity) else {
-            continue;
-        };
-
-        let lx = bx.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let lz = bz.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let block = chunk.get_block(lx, by as usize, lz as usize);
-
-        if block.is_solid() && !matches!(block, BlockType::Water) {
-            return Some(IVec3::new(bx, by, bz));
-        }
-    }
-    None
+    (1..=steps)
+        .map(|i| (origin + direction * (i as f32 * step_size)).floor().as_ivec3())
+        .find(|&pos| voxels.is_solid(pos))
 }
 
 fn raycast_target(
-    world: Res<GameWorld>,
-    chunks: Query<&crate::chunk::Chunk>,
+    voxels: VoxelWorld,
     mut state: ResMut<BreakingState>,
     camera_query: Query<&Transform, With<MainCamera>>,
+    mobs: Query<(Entity, &PhysicsPosition, &VoxelBody), With<Mob>>,
     mut commands: Commands,
 ) {
     let Ok(cam_transform) = camera_query.get_single() else {
@@ -104,8 +76,26 @@ fn raycast_target(
     };
 
     let origin = cam_transform.translation;
-    let direction = cam_transform.forward().into();
-    let hit = raycast_block(&world, &chunks, origin, direction);
+    let direction: Vec3 = cam_transform.forward().into();
+    let mut hit = raycast_block(&voxels, origin, direction);
+
+    // The nearest mob within reach wins over any block behind it
+    let block_distance = hit.map_or(REACH, |b| {
+        let min = b.as_vec3();
+        ray_box_distance(origin, direction, min, min + Vec3::ONE).unwrap_or(REACH)
+    });
+    state.mob_target = mobs
+        .iter()
+        .filter_map(|(entity, position, body)| {
+            let center = position.current;
+            let distance = ray_box_distance(origin, direction, center - body.half_extents, center + body.half_extents)?;
+            (distance < block_distance).then_some((entity, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity);
+    if state.mob_target.is_some() {
+        hit = None;
+    }
 
     if hit != state.target {
         state.progress = 0.0;
@@ -117,23 +107,24 @@ fn raycast_target(
     state.target = hit;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_breaking(
     mut commands: Commands,
     time: Res<Time>,
-    mouse: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
     mut state: ResMut<BreakingState>,
-    world: Res<GameWorld>,
-    mut chunks: Query<&mut crate::chunk::Chunk>,
+    mut editor: BlockEditor,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
+    mut leaves_broken: EventWriter<LeavesBroken>,
 ) {
     let Some(target) = state.target else {
         state.progress = 0.0;
         return;
     };
 
-    if !mouse.pressed(MouseButton::Left) {
+    if !actions.pressed(Action::Break) {
         state.progress = 0.0;
         if let Some(e) = state.crack_entity.take() {
             commands.entity(e).despawn_recursive();
@@ -155,31 +146,8 @@ fn handle_breaking(
             commands.entity(e).despawn_recursive();
         }
 
-        let chunk_x = target.x.div_euclid(CHUNK_SIZE as i32);
-        let chunk_z = target.z.div_euclid(CHUNK_SIZE as i32);
-        let chunk_pos = IVec3::new(chunk_x, 0, chunk_z);
-
-        // Get block type before removing
-        let block_type = if let Some(&entity) = world.chunks.get(&chunk_pos) {
-            if let Ok(chunk) = chunks.get(entity) {
-                let lx = target.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-                let lz = target.z.rem_euclid(CHUNK_SIZE as i32) as usize;
-                chunk.get_block(lx, target.y as usize, lz as usize)
-            } else {
-                BlockType::Air
-            }
-        } else {
-            BlockType::Air
-        };
-
-        // Set block to Air — sync_block_visuals will despawn the GLB
-        if let Some(&entity) = world.chunks.get(&chunk_pos) {
-            if let Ok(mut chunk) = chunks.get_mut(entity) {
-                let lx = target.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-                let lz = target.z.rem_euclid(CHUNK_SIZE as i32) as usize;
-                chunk.set_block(lx, target.y as usize, lz as usize, BlockType::Air);
-            }
-        }
+        // Journaled, so the broken block can be undone
+        let block_type = editor.set_block(target, BlockType::Air).map_or(BlockType::Air, |c| c.old);
 
         let center = Vec3::new(
             target.x as f32 + 0.5,
@@ -195,6 +163,9 @@ fn handle_breaking(
             block_type,
         );
         spawn_block_drop(&mut commands, &asset_server, center, block_type);
+        if block_type == BlockType::Leaves {
+            leaves_broken.send(LeavesBroken { position: center, count: 1 });
+        }
     }
 }
 
@@ -303,10 +274,17 @@ fn spawn_break_particles(
                 ..default()
             },
             BreakParticle {
-                velocity: vel,
                 lifetime: 0.6,
                 age: 0.0,
             },
+            VoxelBodyBundle::new(
+                VoxelBody::new(Vec3::splat(0.09))
+                    .with_gravity_scale(0.45)
+                    .with_bounciness(0.35)
+                    .with_friction(4.0),
+                center,
+                vel,
+            ),
         ));
     }
 }
@@ -325,8 +303,6 @@ fn update_break_particles(
         }
 
         let t = particle.age / particle.lifetime;
-        transform.translation += particle.velocity * dt;
-        particle.velocity.y -= 12.0 * dt;
         let scale = (1.0 - t).max(0.01);
         transform.scale = Vec3::splat(scale * 0.18);
         transform.rotate_x(dt * 5.0);
@@ -351,18 +327,19 @@ fn spawn_block_drop(
         BlockType::Air => "block.glb#Scene0",
     };
 
+    let spawn_pos = Vec3::new(center.x, center.y + 0.3, center.z);
     commands.spawn((
         SceneBundle {
             scene: asset_server.load(scene_path),
-            transform: Transform::from_translation(Vec3::new(center.x, center.y + 0.3, center.z))
-                .with_scale(Vec3::splat(0.4)),
+            transform: Transform::from_translation(spawn_pos).with_scale(Vec3::splat(0.4)),
             ..default()
         },
         BlockDrop {
             _block_type: block_type,
-            origin_y: center.y + 0.3,
             age: 0.0,
         },
+        // Small pop upwards, then it falls and settles on the ground
+        VoxelBodyBundle::new(VoxelBody::new(Vec3::splat(0.2)), spawn_pos, Vec3::Y * 3.0),
     ));
 }
 
@@ -382,7 +359,6 @@ fn update_block_drops(
     for (entity, mut transform, mut drop) in query.iter_mut() {
         drop.age += dt;
 
-        transform.translation.y = drop.origin_y + (drop.age * 2.5).sin() * 0.15;
         transform.rotate_y(dt * 1.2);
 
         let dist = (transform.translation - player_pos).length();
@@ -1,5 +1,12 @@
-use crate::physics::{Grounded, Velocity, PLAYER_HEIGHT};
+use crate::actions::{Action, ActionState};
+use crate::health::Health;
+use crate::hunger::{FoodBag, Hunger, Stamina};
+use crate::physics::{
+    CROUCH_HEIGHT, FallImpact, Grounded, PLAYER_HEIGHT, PhysicsPosition, STEP_ASSIST_HEIGHT, STEP_HEIGHT, Velocity,
+};
+use crate::voxel_world::VoxelWorld;
 use crate::world::World as GameWorld;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::window::CursorGrabMode;
 
@@ -11,6 +18,17 @@ pub enum PovMode {
     FrontView,
 }
 
+impl PovMode {
+    pub fn next(self) -> PovMode {
+        match self {
+            PovMode::FirstPerson => PovMode::ThirdPerson,
+            PovMode::ThirdPerson => PovMode::TopDown,
+            PovMode::TopDown => PovMode::FrontView,
+            PovMode::FrontView => PovMode::FirstPerson,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct PlayerCamera {
     pub sensitivity: f32,
@@ -19,6 +37,9 @@ pub struct PlayerCamera {
     pub pov: PovMode,
     pub tp_distance: f32,
     pub tp_height: f32,
+    /// Current boom length along the camera offset, smoothed toward the
+    /// collision-free length each frame
+    pub boom_length: f32,
 }
 
 impl Default for PlayerCamera {
@@ -30,15 +51,55 @@ impl Default for PlayerCamera {
             pov: PovMode::FirstPerson,
             tp_distance: 3.0,
             tp_height: 2.0,
+            boom_length: 0.0,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovementMode {
+    /// Walking with gravity and collision
+    Survival,
+    /// Free flight with collision, toggled by double-tapping jump
+    Creative,
+    /// Free flight through blocks
+    Spectator,
+}
+
 // The player body — holds physics, collision, and the capsule mesh
 #[derive(Component)]
 pub struct Player {
     pub speed: f32,
     pub sprint_multiplier: f32,
+    pub mode: MovementMode,
+    pub fly_speed: f32,
+    pub spectator_speed: f32,
+    pub crouch_multiplier: f32,
+    pub crouching: bool,
+    /// Set by movement input while actually sprinting this frame
+    pub sprinting: bool,
+    /// Climb full-block ledges without jumping instead of only low ones
+    pub step_assist: bool,
+}
+
+impl Player {
+    /// Current body height; the transform sits this far above the feet.
+    pub fn height(&self) -> f32 {
+        if self.crouching { CROUCH_HEIGHT } else { PLAYER_HEIGHT }
+    }
+
+    /// Walking, flying or spectating speed, before sprinting or crouching.
+    pub fn mode_speed(&self) -> f32 {
+        match self.mode {
+            MovementMode::Survival => self.speed,
+            MovementMode::Creative => self.fly_speed,
+            MovementMode::Spectator => self.spectator_speed,
+        }
+    }
+
+    pub fn step_height(&self) -> f32 {
+        if self.step_assist { STEP_ASSIST_HEIGHT } else { STEP_HEIGHT }
+    }
 }
 
 impl Default for Player {
@@ -46,6 +107,13 @@ impl Default for Player {
         Self {
             speed: 5.0,
             sprint_multiplier: 2.0,
+            mode: MovementMode::Survival,
+            fly_speed: 10.0,
+            spectator_speed: 15.0,
+            crouch_multiplier: 0.3,
+            crouching: false,
+            sprinting: false,
+            step_assist: false,
         }
     }
 }
@@ -58,12 +126,30 @@ pub struct PlayerMesh;
 #[derive(Component)]
 pub struct MainCamera;
 
+// Scroll-wheel zoom limits for the third-person views
+const TP_MIN_DISTANCE: f32 = 1.5;
+const TP_MAX_DISTANCE: f32 = 12.0;
+const TP_SCROLL_STEP: f32 = 0.5;
+// Pixel-based scroll deltas (trackpads) per line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 40.0;
+// Zoom speed while a ZoomIn/ZoomOut button is held, in scroll lines per second
+const ZOOM_HOLD_RATE: f32 = 8.0;
+// Right-stick look speed, in mouse pixels per second at full tilt
+const STICK_LOOK_RATE: f32 = 900.0;
+
+// The boom samples the world this often along its length
+const BOOM_STEP: f32 = 0.05;
+// Keeps the near plane from poking into the block the boom hit
+const BOOM_PADDING: f32 = 0.25;
+// How fast the boom extends back out once an obstruction clears
+const BOOM_EXTEND_SPEED: f32 = 6.0;
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_player_and_camera)
-            .add_systems(Update, (mouse_look, grab_cursor, switch_pov));
+            .add_systems(Update, (zoom_camera, mouse_look, grab_cursor, switch_pov, fit_player_mesh));
     }
 }
 
@@ -90,8 +176,14 @@ fn setup_player_and_camera(
             ..default()
         },
         Player::default(),
+        PhysicsPosition::new(Vec3::new(0.0, eye_y, 0.0)),
         Velocity(Vec3::ZERO),
         Grounded(false),
+        FallImpact::default(),
+        Health::default(),
+        Hunger::default(),
+        Stamina::default(),
+        FoodBag::default(),
         PlayerCamera::default(),
     )).with_children(|parent| {
         // Capsule mesh centered on the player body
@@ -119,36 +211,36 @@ fn setup_player_and_camera(
     ));
 }
 
+/// Squashes the capsule to the current body height so crouching shows.
+fn fit_player_mesh(
+    player_query: Query<&Player, Changed<Player>>,
+    mut mesh_query: Query<&mut Transform, With<PlayerMesh>>,
+) {
+    let Ok(player) = player_query.get_single() else { return };
+    let height = player.height();
+    for mut transform in mesh_query.iter_mut() {
+        transform.translation.y = -height / 2.0;
+        transform.scale.y = height / PLAYER_HEIGHT;
+    }
+}
+
 fn switch_pov(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut player_query: Query<&mut PlayerCamera, With<Player>>,
     mut mesh_query: Query<&mut Visibility, With<PlayerMesh>>,
 ) {
-    let new_pov =
-        if keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight) {
-            if keyboard.just_pressed(KeyCode::Digit1) {
-                Some(PovMode::FirstPerson)
-            } else if keyboard.just_pressed(KeyCode::Digit2) {
-                Some(PovMode::ThirdPerson)
-            } else if keyboard.just_pressed(KeyCode::Digit3) {
-                Some(PovMode::TopDown)
-            } else if keyboard.just_pressed(KeyCode::Digit4) {
-                Some(PovMode::FrontView)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-    let Some(pov) = new_pov else { return };
-
-    for mut cam in player_query.iter_mut() {
-        cam.pov = pov;
-        if pov == PovMode::TopDown {
-            cam.pitch = -std::f32::consts::FRAC_PI_2;
-        }
+    if !actions.just_pressed(Action::CyclePov) {
+        return;
     }
+    let Ok(mut cam) = player_query.get_single_mut() else { return };
+
+    let pov = cam.pov.next();
+    cam.pov = pov;
+    cam.pitch = match pov {
+        PovMode::TopDown => -std::f32::consts::FRAC_PI_2,
+        PovMode::ThirdPerson | PovMode::FrontView => cam.pitch.clamp(-0.8, 0.6),
+        PovMode::FirstPerson => cam.pitch,
+    };
 
     for mut vis in mesh_query.iter_mut() {
         *vis = match pov {
@@ -158,7 +250,112 @@ fn switch_pov(
     }
 }
 
+fn zoom_camera(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    mut wheel: EventReader<MouseWheel>,
+    mut player_query: Query<&mut PlayerCamera, With<Player>>,
+) {
+    let mut lines = 0.0;
+    for event in wheel.read() {
+        lines += match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+    }
+    // Menus own the wheel while input is suppressed
+    if actions.suppressed {
+        return;
+    }
+    if actions.pressed(Action::ZoomIn) {
+        lines += ZOOM_HOLD_RATE * time.delta_seconds();
+    }
+    if actions.pressed(Action::ZoomOut) {
+        lines -= ZOOM_HOLD_RATE * time.delta_seconds();
+    }
+    if lines == 0.0 {
+        return;
+    }
+
+    for mut cam in player_query.iter_mut() {
+        if cam.pov == PovMode::FirstPerson {
+            continue;
+        }
+        // Scrolling up pulls the camera in
+        cam.tp_distance =
+            (cam.tp_distance - lines * TP_SCROLL_STEP).clamp(TP_MIN_DISTANCE, TP_MAX_DISTANCE);
+    }
+}
+
+/// How far the camera can travel from `origin` along `offset` before it
+/// would end up within `BOOM_PADDING` of a solid block.
+fn boom_clearance(
+    voxels: &VoxelWorld,
+    origin: Vec3,
+    offset: Vec3,
+) -> f32 {
+    let length = offset.length();
+    if length <= f32::EPSILON {
+        return 0.0;
+    }
+    let dir = offset / length;
+    let probes = [
+        Vec3::ZERO,
+        Vec3::X * BOOM_PADDING,
+        Vec3::NEG_X * BOOM_PADDING,
+        Vec3::Y * BOOM_PADDING,
+        Vec3::NEG_Y * BOOM_PADDING,
+        Vec3::Z * BOOM_PADDING,
+        Vec3::NEG_Z * BOOM_PADDING,
+    ];
+
+    let steps = (length / BOOM_STEP).ceil() as usize;
+    for i in 1..=steps {
+        let distance = (i as f32 * BOOM_STEP).min(length);
+        let pos = origin + dir * distance;
+        let blocked = probes.iter().any(|probe| {
+            let p = pos + *probe;
+            voxels.is_solid(p.floor().as_ivec3())
+        });
+        if blocked {
+            return (distance - BOOM_STEP).max(0.0);
+        }
+    }
+    length
+}
+
+/// Places the camera at `eye_pos + offset`, pulled in along the boom when
+/// terrain is in the way. Obstructions snap the camera in immediately so it
+/// never sits inside a block; it eases back out once they clear.
+fn place_on_boom(
+    cam: &mut PlayerCamera,
+    voxels: &VoxelWorld,
+    eye_pos: Vec3,
+    offset: Vec3,
+    dt: f32,
+) -> Vec3 {
+    let length = offset.length();
+    let clearance = boom_clearance(voxels, eye_pos, offset);
+
+    if clearance < cam.boom_length {
+        cam.boom_length = clearance;
+    } else {
+        let t = 1.0 - (-BOOM_EXTEND_SPEED * dt).exp();
+        cam.boom_length += (clearance - cam.boom_length) * t;
+    }
+    cam.boom_length = cam.boom_length.min(length);
+
+    if length <= f32::EPSILON {
+        eye_pos
+    } else {
+        eye_pos + offset * (cam.boom_length / length)
+    }
+}
+
 fn mouse_look(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    voxels: VoxelWorld,
     mut mouse_motion: EventReader<bevy::input::mouse::MouseMotion>,
     // Player has the camera settings and the authoritative position
     mut player_query: Query<(&mut PlayerCamera, &Transform), With<Player>>,
@@ -169,6 +366,11 @@ fn mouse_look(
     for event in mouse_motion.read() {
         delta += event.delta;
     }
+    if actions.suppressed {
+        delta = Vec2::ZERO;
+    }
+    // Stick up looks up, mouse up is negative y
+    delta += Vec2::new(actions.look.x, -actions.look.y) * STICK_LOOK_RATE * time.delta_seconds();
 
     let Ok((mut cam, player_transform)) = player_query.get_single_mut() else { return };
     let Ok(mut cam_transform) = camera_transform_query.get_single_mut() else { return };
@@ -207,7 +409,8 @@ fn mouse_look(
                 cam.tp_height + cam.pitch * cam.tp_distance,
                 -cam.yaw.cos() * cam.tp_distance,
             );
-            cam_transform.translation = eye_pos + offset;
+            cam_transform.translation =
+                place_on_boom(&mut cam, &voxels, eye_pos, offset, time.delta_seconds());
             cam_transform.look_at(eye_pos + Vec3::Y * 0.5, Vec3::Y);
         }
 
@@ -225,7 +428,8 @@ fn mouse_look(
                 cam.tp_height + cam.pitch * cam.tp_distance,
                 cam.yaw.cos() * cam.tp_distance,
             );
-            cam_transform.translation = eye_pos + offset;
+            cam_transform.translation =
+                place_on_boom(&mut cam, &voxels, eye_pos, offset, time.delta_seconds());
             cam_transform.look_at(eye_pos + Vec3::Y * 0.5, Vec3::Y);
         }
     }
@@ -233,17 +437,16 @@ fn mouse_look(
 
 fn grab_cursor(
     mut windows: Query<&mut Window>,
-    mouse_button: Res<ButtonInput<MouseButton>>,
-    key_input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
 ) {
     let mut window = windows.single_mut();
 
-    if mouse_button.just_pressed(MouseButton::Left) {
+    if actions.just_pressed(Action::GrabCursor) {
         window.cursor.grab_mode = CursorGrabMode::Locked;
         window.cursor.visible = false;
     }
 
-    if key_input.just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(Action::ReleaseCursor) {
         window.cursor.grab_mode = CursorGrabMode::None;
         window.cursor.visible = true;
     }
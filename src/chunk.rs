@@ -1,42 +1,147 @@
 use crate::block::BlockType;
 use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const CHUNK_HEIGHT: usize = 64;
+pub const SECTION_HEIGHT: usize = 16;
+pub const SECTIONS: usize = CHUNK_HEIGHT / SECTION_HEIGHT;
+const SECTION_VOLUME: usize = CHUNK_SIZE * SECTION_HEIGHT * CHUNK_SIZE;
 
-#[derive(Component)]
+/// Block ids are stored as `u16` so the palette does not limit how many
+/// block types there can be.
+fn block_id(block: BlockType) -> u16 {
+    u16::from(block.id())
+}
+
+fn block_of(id: u16) -> BlockType {
+    u8::try_from(id).ok().and_then(BlockType::from_id).unwrap_or(BlockType::Air)
+}
+
+/// One 16-block-high slice of a chunk: the distinct block ids in it, and
+/// for every block an index into them packed at `bits` bits. A section of
+/// a single block (all air, all stone) stores no indices at all.
+#[derive(Clone)]
+struct Section {
+    palette: Vec<u16>,
+    /// Indices packed LSB first, never straddling two words
+    indices: Vec<u64>,
+    bits: u32,
+}
+
+impl Section {
+    fn uniform(block: BlockType) -> Self {
+        Self {
+            palette: vec![block_id(block)],
+            indices: Vec::new(),
+            bits: 0,
+        }
+    }
+
+    fn per_word(bits: u32) -> usize {
+        (u64::BITS / bits) as usize
+    }
+
+    fn index(&self, i: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = Self::per_word(self.bits);
+        let word = self.indices[i / per_word];
+        (word >> ((i % per_word) as u32 * self.bits) & ((1 << self.bits) - 1)) as usize
+    }
+
+    fn get(&self, i: usize) -> u16 {
+        self.palette[self.index(i)]
+    }
+
+    fn set(&mut self, i: usize, id: u16) {
+        if self.get(i) == id {
+            return;
+        }
+        let index = match self.palette.iter().position(|&p| p == id) {
+            Some(index) => index,
+            None => {
+                self.palette.push(id);
+                if self.palette.len() > 1 << self.bits {
+                    self.repack(u32::BITS - (self.palette.len() as u32 - 1).leading_zeros());
+                }
+                self.palette.len() - 1
+            }
+        };
+
+        let per_word = Self::per_word(self.bits);
+        let shift = (i % per_word) as u32 * self.bits;
+        let word = &mut self.indices[i / per_word];
+        *word = *word & !(((1 << self.bits) - 1) << shift) | (index as u64) << shift;
+    }
+
+    /// Rewrites every index at `bits` bits, once the palette outgrows the
+    /// current width.
+    fn repack(&mut self, bits: u32) {
+        let per_word = Self::per_word(bits);
+        let mut indices = vec![0u64; SECTION_VOLUME.div_ceil(per_word)];
+        for i in 0..SECTION_VOLUME {
+            indices[i / per_word] |= (self.index(i) as u64) << ((i % per_word) as u32 * bits);
+        }
+        self.indices = indices;
+        self.bits = bits;
+    }
+
+    fn heap_bytes(&self) -> usize {
+        self.palette.capacity() * size_of::<u16>() + self.indices.capacity() * size_of::<u64>()
+    }
+}
+
+/// The blocks of one chunk column. Plain data, not a component: loaded
+/// chunks live in [`ChunkMap`], and their entities only hold visuals.
+#[derive(Clone)]
 pub struct Chunk {
     pub position: IVec3,
-    pub blocks: [[[BlockType; CHUNK_SIZE]; CHUNK_HEIGHT]; CHUNK_SIZE],
+    sections: [Section; SECTIONS],
 }
 
 impl Chunk {
     pub fn new(position: IVec3) -> Self {
         Self {
             position,
-            blocks: [[[BlockType::Air; CHUNK_SIZE]; CHUNK_HEIGHT]; CHUNK_SIZE],
+            sections: std::array::from_fn(|_| Section::uniform(BlockType::Air)),
         }
     }
 
+    // Blocks within a section are ordered x, then y, then z
+    fn slot(x: usize, y: usize, z: usize) -> (usize, usize) {
+        (y / SECTION_HEIGHT, (x * SECTION_HEIGHT + y % SECTION_HEIGHT) * CHUNK_SIZE + z)
+    }
+
     pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockType {
         if x >= CHUNK_SIZE || y >= CHUNK_HEIGHT || z >= CHUNK_SIZE {
             return BlockType::Air;
         }
-        self.blocks[x][y][z]
+        let (section, i) = Self::slot(x, y, z);
+        block_of(self.sections[section].get(i))
     }
 
     pub fn set_block(&mut self, x: usize, y: usize, z: usize, block_type: BlockType) {
         if x < CHUNK_SIZE && y < CHUNK_HEIGHT && z < CHUNK_SIZE {
-            self.blocks[x][y][z] = block_type;
+            let (section, i) = Self::slot(x, y, z);
+            self.sections[section].set(i, block_id(block_type));
         }
     }
+
+    /// Bytes this chunk takes up, including its palettes and indices.
+    pub fn memory_bytes(&self) -> usize {
+        size_of::<Self>() + self.sections.iter().map(Section::heap_bytes).sum::<usize>()
+    }
+
     pub fn get_surface_blocks(&self) -> Vec<(usize, usize, usize, BlockType)> {
         let mut surface = Vec::new();
 
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_HEIGHT {
                 for z in 0..CHUNK_SIZE {
-                    let b = self.blocks[x][y][z];
+                    let b = self.get_block(x, y, z);
                     if b == BlockType::Air || b == BlockType::Stone {
                         continue;
                     }
@@ -52,12 +157,12 @@ impl Chunk {
                     {
                         exposed = true;
                     } else {
-                        if self.blocks[x][y + 1][z] == BlockType::Air
-                            || self.blocks[x][y - 1][z] == BlockType::Air
-                            || self.blocks[x + 1][y][z] == BlockType::Air
-                            || self.blocks[x - 1][y][z] == BlockType::Air
-                            || self.blocks[x][y][z + 1] == BlockType::Air
-                            || self.blocks[x][y][z - 1] == BlockType::Air
+                        if self.get_block(x, y + 1, z) == BlockType::Air
+                            || self.get_block(x, y - 1, z) == BlockType::Air
+                            || self.get_block(x + 1, y, z) == BlockType::Air
+                            || self.get_block(x - 1, y, z) == BlockType::Air
+                            || self.get_block(x, y, z + 1) == BlockType::Air
+                            || self.get_block(x, y, z - 1) == BlockType::Air
                         {
                             exposed = true;
                         }
@@ -74,8 +179,96 @@ impl Chunk {
     }
 }
 
+/// The chunk column holding the block at `pos`.
+pub fn chunk_of(pos: IVec3) -> IVec3 {
+    IVec3::new(pos.x.div_euclid(CHUNK_SIZE as i32), 0, pos.z.div_euclid(CHUNK_SIZE as i32))
+}
+
+/// Where the block at `pos` sits inside its chunk.
+pub fn local_of(pos: IVec3) -> (usize, usize, usize) {
+    (
+        pos.x.rem_euclid(CHUNK_SIZE as i32) as usize,
+        pos.y as usize,
+        pos.z.rem_euclid(CHUNK_SIZE as i32) as usize,
+    )
+}
+
+/// Block data of every loaded chunk, kept outside the ECS. Chunks are
+/// shared: a background task can hold a clone of the `Arc` and read it
+/// while the game goes on, and a chunk is only copied if it is written
+/// while such a clone is alive.
+#[derive(Resource, Default)]
+pub struct ChunkMap {
+    chunks: HashMap<IVec3, Arc<Chunk>>,
+}
+
+impl ChunkMap {
+    pub fn get(&self, chunk_pos: IVec3) -> Option<&Arc<Chunk>> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    pub fn get_mut(&mut self, chunk_pos: IVec3) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk_pos).map(Arc::make_mut)
+    }
+
+    pub fn get_or_insert_with(&mut self, chunk_pos: IVec3, make: impl FnOnce() -> Chunk) -> &Arc<Chunk> {
+        self.chunks.entry(chunk_pos).or_insert_with(|| Arc::new(make()))
+    }
+
+    pub fn insert(&mut self, chunk: impl Into<Arc<Chunk>>) {
+        let chunk = chunk.into();
+        self.chunks.insert(chunk.position, chunk);
+    }
+
+    pub fn remove(&mut self, chunk_pos: IVec3) -> Option<Arc<Chunk>> {
+        self.chunks.remove(&chunk_pos)
+    }
+
+    pub fn contains(&self, chunk_pos: IVec3) -> bool {
+        self.chunks.contains_key(&chunk_pos)
+    }
+
+    /// The block at world position `pos`, or `None` above or below the
+    /// world and in chunks that are not loaded.
+    pub fn get_block(&self, pos: IVec3) -> Option<BlockType> {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+        let (x, y, z) = local_of(pos);
+        Some(self.get(chunk_of(pos))?.get_block(x, y, z))
+    }
+
+    /// Sets the block at world position `pos` and returns the one it
+    /// replaced, or `None` if the chunk is not loaded.
+    pub fn set_block(&mut self, pos: IVec3, block: BlockType) -> Option<BlockType> {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+        let (x, y, z) = local_of(pos);
+        let chunk = self.get_mut(chunk_of(pos))?;
+        let old = chunk.get_block(x, y, z);
+        chunk.set_block(x, y, z, block);
+        Some(old)
+    }
+
+    /// Drops every chunk `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(IVec3) -> bool) {
+        self.chunks.retain(|&chunk_pos, _| keep(chunk_pos));
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.memory_bytes()).sum()
+    }
+}
+
 pub struct ChunkPlugin;
 
 impl Plugin for ChunkPlugin {
-    fn build(&self, _app: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkMap>();
+    }
 }
@@ -0,0 +1,93 @@
+use crate::chunk::Chunk;
+use crate::chunk_codec::{decode_chunk, encode_chunk};
+use crate::save::{chunk_dir, load_chunk, save_chunk};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+// Unloaded chunks kept in memory; older ones are written to disk
+const MAX_CACHED_CHUNKS: usize = 64;
+
+/// Edited chunks that were unloaded, so they come back as they were left
+/// instead of being generated again. The most recently unloaded are kept
+/// in memory, encoded with [`encode_chunk`]; the rest are spilled to the
+/// save directory's chunk files.
+#[derive(Resource)]
+pub struct ChunkCache {
+    /// Least recently unloaded first
+    memory: VecDeque<(IVec3, Vec<u8>)>,
+    dir: PathBuf,
+}
+
+impl Default for ChunkCache {
+    fn default() -> Self {
+        Self::new(chunk_dir())
+    }
+}
+
+impl ChunkCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            memory: VecDeque::new(),
+            dir,
+        }
+    }
+
+    pub fn store(&mut self, chunk: &Chunk) {
+        self.memory.retain(|(pos, _)| *pos != chunk.position);
+        self.memory.push_back((chunk.position, encode_chunk(chunk)));
+        while self.memory.len() > MAX_CACHED_CHUNKS {
+            let Some((pos, bytes)) = self.memory.pop_front() else { break };
+            match decode_chunk(&bytes) {
+                Ok(chunk) => save_chunk(&self.dir, &chunk),
+                Err(e) => warn!("Dropping cached chunk {}: {}", pos, e),
+            }
+        }
+    }
+
+    /// Takes a chunk out of memory, or reads its saved file. A saved file
+    /// stays behind and is overwritten when the chunk is saved again.
+    pub fn take(&mut self, chunk_pos: IVec3) -> Option<Chunk> {
+        let Some(i) = self.memory.iter().position(|(pos, _)| *pos == chunk_pos) else {
+            return load_chunk(&self.dir, chunk_pos);
+        };
+        let (_, bytes) = self.memory.remove(i)?;
+        decode_chunk(&bytes)
+            .inspect_err(|e| warn!("Ignoring unreadable cached chunk {}: {}", chunk_pos, e))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockType;
+
+    fn edited_chunk(x: i32) -> Chunk {
+        let mut chunk = Chunk::new(IVec3::new(x, 0, -x));
+        chunk.set_block(1, 2, 3, BlockType::Sand);
+        chunk
+    }
+
+    #[test]
+    fn unloaded_chunks_come_back() {
+        let dir = std::env::temp_dir().join(format!("voxelverse-cache-{}", std::process::id()));
+        let mut cache = ChunkCache::new(dir.clone());
+        for x in 0..MAX_CACHED_CHUNKS as i32 + 2 {
+            cache.store(&edited_chunk(x));
+        }
+        // The two oldest were spilled to disk, the rest stayed in memory
+        assert_eq!(cache.memory.len(), MAX_CACHED_CHUNKS);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        for x in [0, 1, MAX_CACHED_CHUNKS as i32 + 1] {
+            let chunk = cache.take(IVec3::new(x, 0, -x)).unwrap();
+            assert_eq!(chunk.position, IVec3::new(x, 0, -x));
+            assert_eq!(chunk.get_block(1, 2, 3), BlockType::Sand);
+        }
+        assert_eq!(cache.memory.len(), MAX_CACHED_CHUNKS - 1);
+        assert!(cache.take(IVec3::new(-50, 0, 50)).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+use crate::block::BlockType;
+use crate::chunk::{CHUNK_SIZE, Chunk, SECTION_HEIGHT, SECTIONS};
+use bevy::prelude::*;
+use std::fmt;
+
+// Binary chunk encoding shared by the network protocol and save files.
+//
+//   u8       format version
+//   i32 × 3  chunk position
+//   per 16-block-high section, bottom up:
+//     u8 0, u8 block           — the whole section is one block (all air, all stone)
+//     u8 1, u8 n-1, n × u8     — palette of n block ids, then 4096 indices
+//           packed bytes         bit-packed LSB first at ceil(log2 n) bits each
+//
+// Blocks within a section are ordered x, then y, then z, like in `Chunk`.
+
+pub const CODEC_VERSION: u8 = 1;
+
+const SECTION_VOLUME: usize = CHUNK_SIZE * SECTION_HEIGHT * CHUNK_SIZE;
+
+const TAG_UNIFORM: u8 = 0;
+const TAG_PALETTE: u8 = 1;
+
+#[derive(Debug)]
+pub enum ChunkCodecError {
+    Version(u8),
+    Format(&'static str),
+}
+
+impl fmt::Display for ChunkCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkCodecError::Version(v) => write!(f, "unsupported chunk format version {}", v),
+            ChunkCodecError::Format(msg) => write!(f, "invalid chunk data: {}", msg),
+        }
+    }
+}
+
+fn section_blocks(chunk: &Chunk, section: usize) -> impl Iterator<Item = BlockType> + '_ {
+    let y0 = section * SECTION_HEIGHT;
+    (0..CHUNK_SIZE).flat_map(move |x| {
+        (y0..y0 + SECTION_HEIGHT).flat_map(move |y| (0..CHUNK_SIZE).map(move |z| chunk.get_block(x, y, z)))
+    })
+}
+
+/// Bits per palette index; a palette of one still packs at one bit.
+fn index_bits(palette_len: usize) -> usize {
+    (usize::BITS - (palette_len.max(2) - 1).leading_zeros()) as usize
+}
+
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut out = vec![CODEC_VERSION];
+    for v in chunk.position.to_array() {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+
+    for section in 0..SECTIONS {
+        let mut palette: Vec<BlockType> = Vec::new();
+        let indices: Vec<usize> = section_blocks(chunk, section)
+            .map(|block| match palette.iter().position(|&b| b == block) {
+                Some(i) => i,
+                None => {
+                    palette.push(block);
+                    palette.len() - 1
+                }
+            })
+            .collect();
+
+        if palette.len() == 1 {
+            out.extend_from_slice(&[TAG_UNIFORM, palette[0].id()]);
+            continue;
+        }
+
+        out.extend_from_slice(&[TAG_PALETTE, (palette.len() - 1) as u8]);
+        out.extend(palette.iter().map(|b| b.id()));
+        let bits = index_bits(palette.len());
+        let mut packed = vec![0u8; (SECTION_VOLUME * bits).div_ceil(8)];
+        for (i, &index) in indices.iter().enumerate() {
+            for bit in 0..bits {
+                if index >> bit & 1 == 1 {
+                    let at = i * bits + bit;
+                    packed[at / 8] |= 1 << (at % 8);
+                }
+            }
+        }
+        out.extend_from_slice(&packed);
+    }
+    out
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ChunkCodecError> {
+        if n > self.bytes.len() {
+            return Err(ChunkCodecError::Format("unexpected end of data"));
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ChunkCodecError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, ChunkCodecError> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn block(&mut self) -> Result<BlockType, ChunkCodecError> {
+        BlockType::from_id(self.u8()?).ok_or(ChunkCodecError::Format("unknown block id"))
+    }
+}
+
+/// Decodes a chunk written by [`encode_chunk`]. The whole of `bytes` must
+/// be one chunk; trailing data is an error.
+pub fn decode_chunk(bytes: &[u8]) -> Result<Chunk, ChunkCodecError> {
+    let mut input = Cursor { bytes };
+    let version = input.u8()?;
+    if version != CODEC_VERSION {
+        return Err(ChunkCodecError::Version(version));
+    }
+    let mut chunk = Chunk::new(IVec3::new(input.i32()?, input.i32()?, input.i32()?));
+
+    for section in 0..SECTIONS {
+        let y0 = section * SECTION_HEIGHT;
+        let blocks: Vec<BlockType> = match input.u8()? {
+            TAG_UNIFORM => vec![input.block()?; SECTION_VOLUME],
+            TAG_PALETTE => {
+                let len = input.u8()? as usize + 1;
+                let palette = (0..len).map(|_| input.block()).collect::<Result<Vec<_>, _>>()?;
+                let bits = index_bits(len);
+                let packed = input.take((SECTION_VOLUME * bits).div_ceil(8))?;
+                (0..SECTION_VOLUME)
+                    .map(|i| {
+                        let index = (0..bits).fold(0, |index, bit| {
+                            let at = i * bits + bit;
+                            index | ((packed[at / 8] >> (at % 8) & 1) as usize) << bit
+                        });
+                        palette.get(index).copied().ok_or(ChunkCodecError::Format("palette index out of range"))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            _ => return Err(ChunkCodecError::Format("unknown section tag")),
+        };
+
+        let mut blocks = blocks.into_iter();
+        for x in 0..CHUNK_SIZE {
+            for y in y0..y0 + SECTION_HEIGHT {
+                for z in 0..CHUNK_SIZE {
+                    chunk.set_block(x, y, z, blocks.next().unwrap());
+                }
+            }
+        }
+    }
+
+    if !input.bytes.is_empty() {
+        return Err(ChunkCodecError::Format("trailing bytes after chunk"));
+    }
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::CHUNK_HEIGHT;
+
+    fn assert_same(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_HEIGHT {
+                for z in 0..CHUNK_SIZE {
+                    assert_eq!(a.get_block(x, y, z), b.get_block(x, y, z), "block {} {} {}", x, y, z);
+                }
+            }
+        }
+    }
+
+    /// Stone below, a few block types mixed through the middle sections,
+    /// air on top.
+    fn mixed_chunk() -> Chunk {
+        let mut chunk = Chunk::new(IVec3::new(-3, 0, 7));
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..SECTION_HEIGHT {
+                    chunk.set_block(x, y, z, BlockType::Stone);
+                }
+                for y in SECTION_HEIGHT..SECTION_HEIGHT * 3 {
+                    let id = (x * 7 + y * 3 + z * 5) % BlockType::ALL.len();
+                    chunk.set_block(x, y, z, BlockType::ALL[id]);
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn mixed_chunk_round_trips() {
+        let chunk = mixed_chunk();
+        assert_same(&decode_chunk(&encode_chunk(&chunk)).unwrap(), &chunk);
+    }
+
+    #[test]
+    fn single_block_chunks_round_trip() {
+        for block in [BlockType::Air, BlockType::Stone, BlockType::Water] {
+            let mut chunk = Chunk::new(IVec3::new(5, 0, -9));
+            for x in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_HEIGHT {
+                    for z in 0..CHUNK_SIZE {
+                        chunk.set_block(x, y, z, block);
+                    }
+                }
+            }
+            let bytes = encode_chunk(&chunk);
+            // Version, position, then two bytes per uniform section
+            assert_eq!(bytes.len(), 13 + 2 * SECTIONS);
+            assert_same(&decode_chunk(&bytes).unwrap(), &chunk);
+        }
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        let bytes = encode_chunk(&mixed_chunk());
+        for len in 0..bytes.len() {
+            assert!(decode_chunk(&bytes[..len]).is_err(), "decoded {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn corrupt_data_is_an_error() {
+        let bytes = encode_chunk(&mixed_chunk());
+        let corrupt = |at: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[at] = value;
+            decode_chunk(&bytes)
+        };
+        assert!(matches!(corrupt(0, CODEC_VERSION + 1), Err(ChunkCodecError::Version(_))));
+        // The first section is all stone: tag, then block id
+        assert!(corrupt(13, 7).is_err());
+        assert!(corrupt(14, 200).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode_chunk(&trailing).is_err());
+
+        // Three blocks pack at two bits, so index 3 points past the palette
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        chunk.set_block(0, 1, 0, BlockType::Stone);
+        chunk.set_block(0, 2, 0, BlockType::Dirt);
+        let mut bytes = encode_chunk(&chunk);
+        assert_eq!(bytes[14], 2);
+        bytes[18] = 0xFF;
+        assert!(decode_chunk(&bytes).is_err());
+    }
+
+    #[test]
+    fn random_corruption_never_panics() {
+        let bytes = encode_chunk(&mixed_chunk());
+        let mut seed = 0x9E37_79B9u32;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+        for _ in 0..2000 {
+            let mut bytes = bytes.clone();
+            for _ in 0..1 + next() % 4 {
+                let at = next() % bytes.len();
+                bytes[at] = next() as u8;
+            }
+            bytes.truncate(bytes.len() - next() % 8);
+            let _ = decode_chunk(&bytes);
+        }
+    }
+}
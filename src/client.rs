@@ -0,0 +1,323 @@
+use crate::actions::{Action, ActionState};
+use crate::block::BlockType;
+use crate::camera::{MovementMode, Player};
+use crate::chunk::ChunkMap;
+use crate::console::{Console, MessageKind};
+use crate::net::{ClientMessage, Connection, InputFrame, PROTOCOL_VERSION, ServerMessage, apply_input};
+use crate::physics::{
+    FallImpact, Grounded, PHYSICS_HZ, PLAYER_HEIGHT, PhysicsPosition, PlayerPhysicsSet, Velocity, step_player,
+};
+use crate::world::{BlockChanged, ChunkState, ChunkStates, RemoteWorld, write_blocks};
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Chunk requests sent per frame, so a fresh join does not flood the link
+const CHUNK_REQUESTS_PER_FRAME: usize = 4;
+// Predictions closer than this to the server's answer are left alone
+const RECONCILE_TOLERANCE: f32 = 0.05;
+// Inputs kept for replay; older ones are dropped if the server falls behind
+const MAX_PENDING_INPUTS: usize = 240;
+
+/// Connection to a multiplayer server. While this resource exists the
+/// world is remote: chunks are requested from the server and block edits
+/// are sent to it.
+#[derive(Resource)]
+pub struct NetClient {
+    connection: Connection,
+    pub id: u32,
+    pub seed: u32,
+    pub spawn: Vec3,
+}
+
+impl NetClient {
+    /// Tells the server about a block this client changed locally.
+    pub fn send_block(&mut self, pos: IVec3, block: BlockType) {
+        self.connection.send(&ClientMessage::SetBlock { pos, block });
+    }
+
+    pub fn send_chat(&mut self, text: &str) {
+        self.connection.send(&ClientMessage::Chat(text.to_string()));
+    }
+}
+
+/// Connects to `addr` and waits for the server's welcome.
+pub fn connect(addr: &str) -> io::Result<NetClient> {
+    let stream = TcpStream::connect(addr)?;
+    let mut connection = Connection::new(stream)?;
+    connection.send(&ClientMessage::Hello { version: PROTOCOL_VERSION });
+    connection.flush();
+
+    let started = Instant::now();
+    while started.elapsed() < CONNECT_TIMEOUT && !connection.closed {
+        for message in connection.receive::<ServerMessage>() {
+            if let ServerMessage::Welcome { id, seed, spawn } = message {
+                info!("Joined {} as player {}", addr, id);
+                return Ok(NetClient {
+                    connection,
+                    id,
+                    seed,
+                    spawn,
+                });
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Err(io::Error::new(io::ErrorKind::TimedOut, "no welcome from server"))
+}
+
+/// An input the server has not confirmed yet, with where it left the
+/// local player and in which mode.
+struct PendingInput {
+    input: InputFrame,
+    predicted: Vec3,
+    mode: MovementMode,
+}
+
+#[derive(Resource, Default)]
+struct Prediction {
+    next_seq: u32,
+    pending: VecDeque<PendingInput>,
+}
+
+#[derive(Component)]
+struct RemotePlayer {
+    id: u32,
+}
+
+#[derive(Resource)]
+struct RemotePlayerAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+pub struct ClientPlugin;
+
+impl Plugin for ClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RemoteWorld)
+            .init_resource::<Prediction>()
+            .add_systems(Startup, setup_remote_player_assets)
+            .add_systems(PostStartup, move_to_spawn)
+            .add_systems(
+                FixedUpdate,
+                (
+                    send_input.before(PlayerPhysicsSet),
+                    record_prediction.after(PlayerPhysicsSet),
+                ),
+            )
+            .add_systems(Update, (receive_messages, request_chunks).chain())
+            .add_systems(Last, flush_connection);
+    }
+}
+
+fn setup_remote_player_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(RemotePlayerAssets {
+        mesh: meshes.add(Capsule3d::new(0.35, PLAYER_HEIGHT - 0.7)),
+        material: materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.55, 0.2),
+            ..default()
+        }),
+    });
+}
+
+fn move_to_spawn(client: Res<NetClient>, mut query: Query<&mut PhysicsPosition, With<Player>>) {
+    for mut position in query.iter_mut() {
+        position.teleport(client.spawn);
+    }
+}
+
+/// Records this tick's movement input and sends it, before the local
+/// physics step predicts its outcome.
+fn send_input(
+    actions: Res<ActionState>,
+    mut client: ResMut<NetClient>,
+    mut prediction: ResMut<Prediction>,
+    query: Query<(&Player, &Velocity)>,
+) {
+    let Ok((player, velocity)) = query.get_single() else { return };
+    let input = InputFrame {
+        seq: prediction.next_seq,
+        velocity: velocity.0,
+        crouch: actions.pressed(Action::Crouch),
+    };
+    prediction.next_seq = prediction.next_seq.wrapping_add(1);
+    client.connection.send(&ClientMessage::Input(input));
+
+    if prediction.pending.len() >= MAX_PENDING_INPUTS {
+        prediction.pending.pop_front();
+    }
+    prediction.pending.push_back(PendingInput {
+        input,
+        predicted: Vec3::ZERO,
+        mode: player.mode,
+    });
+}
+
+fn record_prediction(mut prediction: ResMut<Prediction>, query: Query<(&Player, &PhysicsPosition)>) {
+    let Ok((player, position)) = query.get_single() else { return };
+    if let Some(last) = prediction.pending.back_mut() {
+        last.predicted = position.current;
+        last.mode = player.mode;
+    }
+}
+
+/// Compares the server's position and mode for an input with what was
+/// predicted; on a mismatch, takes the server's state and replays the
+/// newer inputs.
+#[allow(clippy::too_many_arguments)]
+fn reconcile(
+    prediction: &mut Prediction,
+    seq: u32,
+    server_position: Vec3,
+    server_velocity: Vec3,
+    server_grounded: bool,
+    server_mode: MovementMode,
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    player: &mut Player,
+    position: &mut PhysicsPosition,
+    velocity: &mut Velocity,
+    grounded: &mut Grounded,
+) {
+    let Some(index) = prediction.pending.iter().position(|p| p.input.seq == seq) else { return };
+    let acked = prediction.pending.drain(..=index).next_back().unwrap();
+    if acked.predicted.distance(server_position) <= RECONCILE_TOLERANCE && acked.mode == server_mode {
+        return;
+    }
+
+    let dt = 1.0 / PHYSICS_HZ as f32;
+    let mut current = server_position;
+    let mut vel = server_velocity;
+    grounded.0 = server_grounded;
+    player.mode = server_mode;
+    // Impacts were already felt when first predicted
+    let mut impact = 0.0;
+    for pending in prediction.pending.iter_mut() {
+        apply_input(player, &mut vel, grounded, &pending.input);
+        step_player(solid, player, &mut current, &mut vel, grounded, &mut impact, pending.input.crouch, dt);
+        pending.predicted = current;
+        pending.mode = player.mode;
+    }
+    position.current = current;
+    velocity.0 = vel;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_messages(
+    mut commands: Commands,
+    mut client: ResMut<NetClient>,
+    mut prediction: ResMut<Prediction>,
+    mut states: ResMut<ChunkStates>,
+    assets: Res<RemotePlayerAssets>,
+    mut map: ResMut<ChunkMap>,
+    mut block_events: EventWriter<BlockChanged>,
+    mut player_query: Query<(&mut Player, &mut PhysicsPosition, &mut Velocity, &mut Grounded), With<FallImpact>>,
+    mut remote_players: Query<(Entity, &RemotePlayer, &mut Transform)>,
+    mut console: ResMut<Console>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let messages = client.connection.receive::<ServerMessage>();
+    if client.connection.closed {
+        error!("Lost connection to the server");
+        exit.send(AppExit::error());
+        return;
+    }
+
+    let mut known_players: HashMap<u32, Entity> = remote_players.iter().map(|(e, r, _)| (r.id, e)).collect();
+    for message in messages {
+        match message {
+            ServerMessage::Welcome { .. } => {}
+            ServerMessage::ChunkData(chunk) => {
+                // Chunks that went out of range on their way are dropped
+                let chunk_pos = chunk.position;
+                if states.get(chunk_pos) != Some(ChunkState::Generating) {
+                    continue;
+                }
+                map.insert(chunk);
+                states.finish_generating(chunk_pos, None);
+            }
+            ServerMessage::BlockChanged { pos, block } => {
+                // Our own edits come back too; unchanged blocks are skipped.
+                // Not sent back to the server, nor an undo step of ours
+                write_blocks([(pos, block)], &mut map, &mut block_events, &mut None);
+            }
+            ServerMessage::PlayerState { seq, position, velocity, grounded, mode } => {
+                let Ok((mut player, mut current, mut vel, mut ground)) = player_query.get_single_mut() else {
+                    continue;
+                };
+                let solid = |x: i32, y: i32, z: i32| {
+                    map.get_block(IVec3::new(x, y, z)).is_some_and(|b| b.is_collidable())
+                };
+                reconcile(
+                    &mut prediction,
+                    seq,
+                    position,
+                    velocity,
+                    grounded,
+                    mode,
+                    &solid,
+                    &mut player,
+                    &mut current,
+                    &mut vel,
+                    &mut ground,
+                );
+            }
+            ServerMessage::PlayerMoved { id, .. } if id == client.id => {}
+            ServerMessage::PlayerMoved { id, position, crouching } => {
+                let height = if crouching { crate::physics::CROUCH_HEIGHT } else { PLAYER_HEIGHT };
+                // The capsule is centred half a body below the eye
+                let center = position - Vec3::Y * height / 2.0;
+                match known_players.get(&id).and_then(|&e| remote_players.get_mut(e).ok()) {
+                    Some((_, _, mut transform)) => {
+                        transform.translation = center;
+                        transform.scale.y = height / PLAYER_HEIGHT;
+                    }
+                    None => {
+                        let entity = commands
+                            .spawn((
+                                PbrBundle {
+                                    mesh: assets.mesh.clone(),
+                                    material: assets.material.clone(),
+                                    transform: Transform::from_translation(center),
+                                    ..default()
+                                },
+                                RemotePlayer { id },
+                            ))
+                            .id();
+                        known_players.insert(id, entity);
+                        console.print(MessageKind::Info, format!("Player {} joined", id));
+                    }
+                }
+            }
+            ServerMessage::PlayerLeft { id } => {
+                if let Some(entity) = known_players.remove(&id) {
+                    commands.entity(entity).despawn_recursive();
+                    console.print(MessageKind::Info, format!("Player {} left", id));
+                }
+            }
+            ServerMessage::Chat { id, text } => {
+                let name = if id == client.id { "You".to_string() } else { format!("Player {}", id) };
+                console.print(MessageKind::Chat, format!("<{}> {}", name, text));
+            }
+        }
+    }
+}
+
+/// Asks the server for the most urgent queued chunks; they count as
+/// generating until their data arrives.
+fn request_chunks(mut client: ResMut<NetClient>, mut states: ResMut<ChunkStates>) {
+    for pos in states.start_generating(CHUNK_REQUESTS_PER_FRAME) {
+        client.connection.send(&ClientMessage::RequestChunk(pos));
+    }
+}
+
+fn flush_connection(mut client: ResMut<NetClient>) {
+    client.connection.flush();
+}
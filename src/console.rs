@@ -0,0 +1,279 @@
+use crate::actions::{Action, ActionState};
+use crate::client::NetClient;
+use bevy::ecs::system::SystemId;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::window::CursorGrabMode;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// Oldest messages are dropped past this many
+const MAX_MESSAGES: usize = 100;
+const MAX_INPUT_LEN: usize = 256;
+
+/// What a command prints: `Ok` as a normal line (nothing if empty), `Err`
+/// in red.
+pub type CommandResult = Result<String, String>;
+
+/// Systems registered as commands take the words after the command name.
+pub type CommandSystem = SystemId<Vec<String>, CommandResult>;
+
+struct RegisteredCommand {
+    usage: &'static str,
+    help: &'static str,
+    system: CommandSystem,
+}
+
+/// Every `/command` the console understands, by name.
+#[derive(Resource, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, RegisteredCommand>,
+}
+
+/// Lets any plugin add console commands from its `build`:
+///
+/// ```ignore
+/// app.add_console_command("seed", "", "Show the world seed", seed_command);
+/// ```
+///
+/// The command is an ordinary system taking `In<Vec<String>>` and returning
+/// a [`CommandResult`], so it can use any system parameters.
+pub trait ConsoleCommandsExt {
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        system: impl IntoSystem<Vec<String>, CommandResult, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl ConsoleCommandsExt for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        system: impl IntoSystem<Vec<String>, CommandResult, M> + 'static,
+    ) -> &mut Self {
+        let system = self.world_mut().register_system(system);
+        self.world_mut()
+            .get_resource_or_insert_with(CommandRegistry::default)
+            .commands
+            .insert(name, RegisteredCommand { usage, help, system });
+        self
+    }
+}
+
+/// Parses argument `index`, naming it in the error when missing or invalid.
+pub fn parse_arg<T: FromStr>(args: &[String], index: usize, name: &str) -> Result<T, String> {
+    let text = args.get(index).ok_or_else(|| format!("Missing <{}>", name))?;
+    text.parse().map_err(|_| format!("Invalid <{}>: '{}'", name, text))
+}
+
+/// Parses a coordinate, where `~` and `~5` are relative to `current`.
+pub fn parse_coord(args: &[String], index: usize, name: &str, current: f32) -> Result<f32, String> {
+    match args.get(index).and_then(|a| a.strip_prefix('~')) {
+        Some("") => Ok(current),
+        Some(offset) => offset
+            .parse::<f32>()
+            .map(|o| current + o)
+            .map_err(|_| format!("Invalid <{}>: '~{}'", name, offset)),
+        None => parse_arg(args, index, name),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Chat,
+    Info,
+    Error,
+    /// A command the player typed, echoed back
+    Echo,
+}
+
+pub struct ConsoleMessage {
+    pub text: String,
+    pub kind: MessageKind,
+    /// `Console::clock` when printed, for fading out old lines
+    pub printed_at: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub messages: Vec<ConsoleMessage>,
+    /// Seconds since startup, updated every frame
+    pub clock: f32,
+    /// Submitted lines for Up/Down recall, oldest first
+    history: Vec<String>,
+    history_index: Option<usize>,
+    pending: Vec<String>,
+    // The key that opens the console must not also be typed into it
+    just_opened: bool,
+}
+
+impl Console {
+    pub fn print(&mut self, kind: MessageKind, text: impl Into<String>) {
+        if self.messages.len() >= MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(ConsoleMessage {
+            text: text.into(),
+            kind,
+            printed_at: self.clock,
+        });
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.input.clear();
+        self.history_index = None;
+    }
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .init_resource::<CommandRegistry>()
+            .add_console_command("help", "", "List every command", help_command)
+            .add_systems(Update, (open_console, type_into_console, run_commands).chain());
+    }
+}
+
+fn open_console(
+    time: Res<Time>,
+    mut console: ResMut<Console>,
+    mut actions: ResMut<ActionState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut windows: Query<&mut Window>,
+) {
+    console.clock = time.elapsed_seconds();
+    if console.open {
+        // Keep the player still even if another menu closed meanwhile
+        actions.suppressed = true;
+        return;
+    }
+    if !actions.just_pressed(Action::OpenConsole) {
+        return;
+    }
+
+    console.open = true;
+    console.just_opened = true;
+    // Opening with `/` starts a command straight away
+    if keyboard.just_pressed(KeyCode::Slash) {
+        console.input.push('/');
+    }
+    actions.suppressed = true;
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+}
+
+fn type_into_console(
+    mut console: ResMut<Console>,
+    mut actions: ResMut<ActionState>,
+    mut keys: EventReader<KeyboardInput>,
+) {
+    let skip = std::mem::take(&mut console.just_opened);
+    for event in keys.read() {
+        if !console.open || skip || event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                let line = console.input.trim().to_string();
+                if !line.is_empty() {
+                    console.history.push(line.clone());
+                    console.pending.push(line);
+                }
+                console.close();
+                actions.suppressed = false;
+                break;
+            }
+            Key::Escape => {
+                console.close();
+                actions.suppressed = false;
+                break;
+            }
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::ArrowUp | Key::ArrowDown => {
+                if console.history.is_empty() {
+                    continue;
+                }
+                let last = console.history.len() - 1;
+                let index = match (console.history_index, &event.logical_key) {
+                    (None, Key::ArrowUp) => Some(last),
+                    (Some(i), Key::ArrowUp) => Some(i.saturating_sub(1)),
+                    (Some(i), _) if i < last => Some(i + 1),
+                    _ => None,
+                };
+                console.history_index = index;
+                console.input = index.map(|i| console.history[i].clone()).unwrap_or_default();
+            }
+            Key::Space if console.input.len() < MAX_INPUT_LEN => console.input.push(' '),
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if console.input.len() < MAX_INPUT_LEN {
+                        console.input.push(c);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Runs submitted commands, or sends the line as chat. Exclusive so that
+/// each command system gets the whole world for its parameters.
+fn run_commands(world: &mut World) {
+    let lines = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in lines {
+        let Some(command_line) = line.strip_prefix('/') else {
+            match world.get_resource_mut::<NetClient>() {
+                Some(mut client) => client.send_chat(&line),
+                None => world.resource_mut::<Console>().print(MessageKind::Chat, format!("<You> {}", line)),
+            }
+            continue;
+        };
+
+        world.resource_mut::<Console>().print(MessageKind::Echo, line.clone());
+        let mut words = command_line.split_whitespace().map(str::to_string);
+        let name = words.next().unwrap_or_default();
+        let args: Vec<String> = words.collect();
+
+        let system = world.resource::<CommandRegistry>().commands.get(name.as_str()).map(|c| c.system);
+        let result = match system {
+            Some(system) => world
+                .run_system_with_input(system, args)
+                .unwrap_or_else(|e| Err(format!("/{} failed: {:?}", name, e))),
+            None => Err(format!("Unknown command /{}. Try /help", name)),
+        };
+
+        let mut console = world.resource_mut::<Console>();
+        match result {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => console.print(MessageKind::Info, text),
+            Err(text) => console.print(MessageKind::Error, text),
+        }
+    }
+}
+
+fn help_command(In(_args): In<Vec<String>>, registry: Res<CommandRegistry>) -> CommandResult {
+    let lines: Vec<String> = registry
+        .commands
+        .iter()
+        .map(|(name, command)| {
+            let usage = if command.usage.is_empty() { String::new() } else { format!(" {}", command.usage) };
+            format!("/{}{} — {}", name, usage, command.help)
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
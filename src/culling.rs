@@ -0,0 +1,270 @@
+use crate::camera::MainCamera;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, ChunkMap, chunk_of};
+use crate::world::{BlockChanged, ChunkRoot, World as GameWorld};
+use bevy::math::Affine3A;
+use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::render::view::VisibilitySystems;
+use std::collections::{HashSet, VecDeque};
+
+/// Chunks are split vertically into cubic sections for occlusion purposes
+pub const SECTION_SIZE: usize = CHUNK_SIZE;
+pub const SECTIONS: usize = CHUNK_HEIGHT / SECTION_SIZE;
+
+// Face order: -X, +X, -Y, +Y, -Z, +Z
+const FACE_DIRS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+fn opposite(face: usize) -> usize {
+    face ^ 1
+}
+
+/// Which faces of a section can see each other through transparent blocks.
+/// `links[a] & (1 << b)` is set when face `a` connects to face `b`.
+#[derive(Clone, Copy, Default)]
+pub struct FaceLinks {
+    links: [u8; 6],
+}
+
+impl FaceLinks {
+    const ALL: FaceLinks = FaceLinks { links: [0x3F; 6] };
+
+    pub fn connects(&self, a: usize, b: usize) -> bool {
+        self.links[a] & (1 << b) != 0
+    }
+}
+
+#[derive(Component)]
+pub struct ChunkOcclusion {
+    pub sections: [FaceLinks; SECTIONS],
+}
+
+#[derive(Resource, Default)]
+pub struct CullingStats {
+    pub frustum_culled: usize,
+    pub occlusion_culled: usize,
+}
+
+pub struct CullingPlugin;
+
+impl Plugin for CullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CullingStats>().add_systems(
+            PostUpdate,
+            (update_chunk_occlusion, cull_chunks)
+                .chain()
+                .after(VisibilitySystems::UpdateFrusta)
+                .before(VisibilitySystems::VisibilityPropagate),
+        );
+    }
+}
+
+/// Flood-fills the transparent blocks of one section and records which
+/// faces each connected pocket of air touches.
+fn section_links(chunk: &Chunk, section: usize) -> FaceLinks {
+    const N: usize = SECTION_SIZE;
+    let base_y = section * N;
+    let mut visited = vec![false; N * N * N];
+    let idx = |x: usize, y: usize, z: usize| (x * N + y) * N + z;
+    let mut links = FaceLinks::default();
+    let mut stack = Vec::new();
+
+    for start in 0..N * N * N {
+        let (sx, sy, sz) = (start / (N * N), (start / N) % N, start % N);
+        if visited[start] || !chunk.get_block(sx, base_y + sy, sz).is_transparent() {
+            continue;
+        }
+
+        let mut touched = 0u8;
+        visited[start] = true;
+        stack.push((sx, sy, sz));
+        while let Some((x, y, z)) = stack.pop() {
+            if x == 0 {
+                touched |= 1 << 0;
+            }
+            if x == N - 1 {
+                touched |= 1 << 1;
+            }
+            if y == 0 {
+                touched |= 1 << 2;
+            }
+            if y == N - 1 {
+                touched |= 1 << 3;
+            }
+            if z == 0 {
+                touched |= 1 << 4;
+            }
+            if z == N - 1 {
+                touched |= 1 << 5;
+            }
+
+            for dir in FACE_DIRS {
+                let nx = x as i32 + dir.x;
+                let ny = y as i32 + dir.y;
+                let nz = z as i32 + dir.z;
+                if nx < 0 || ny < 0 || nz < 0 || nx >= N as i32 || ny >= N as i32 || nz >= N as i32 {
+                    continue;
+                }
+                let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                let i = idx(nx, ny, nz);
+                if !visited[i] && chunk.get_block(nx, base_y + ny, nz).is_transparent() {
+                    visited[i] = true;
+                    stack.push((nx, ny, nz));
+                }
+            }
+        }
+
+        for face in 0..6 {
+            if touched & (1 << face) != 0 {
+                links.links[face] |= touched;
+            }
+        }
+    }
+
+    links
+}
+
+/// Recomputes occlusion for chunks that were just loaded or had blocks
+/// changed.
+fn update_chunk_occlusion(
+    mut commands: Commands,
+    world: Res<GameWorld>,
+    map: Res<ChunkMap>,
+    new_chunks: Query<&ChunkRoot, Added<ChunkRoot>>,
+    mut changes: EventReader<BlockChanged>,
+) {
+    let mut dirty: HashSet<IVec3> = new_chunks.iter().map(|root| root.position).collect();
+    dirty.extend(changes.read().map(|change| chunk_of(change.pos)));
+
+    for chunk_pos in dirty {
+        let (Some(&entity), Some(chunk)) = (world.chunks.get(&chunk_pos), map.get(chunk_pos)) else { continue };
+        let mut sections = [FaceLinks::default(); SECTIONS];
+        for (i, links) in sections.iter_mut().enumerate() {
+            *links = section_links(chunk, i);
+        }
+        commands.entity(entity).insert(ChunkOcclusion { sections });
+    }
+}
+
+fn section_aabb(section: IVec3) -> Aabb {
+    let min = Vec3::new(
+        (section.x * SECTION_SIZE as i32) as f32,
+        (section.y * SECTION_SIZE as i32) as f32,
+        (section.z * SECTION_SIZE as i32) as f32,
+    );
+    Aabb::from_min_max(min, min + Vec3::splat(SECTION_SIZE as f32))
+}
+
+fn cull_chunks(
+    world: Res<GameWorld>,
+    mut stats: ResMut<CullingStats>,
+    camera_query: Query<(&GlobalTransform, &Frustum), With<MainCamera>>,
+    occlusion: Query<&ChunkOcclusion>,
+    mut visibility: Query<&mut Visibility, With<ChunkRoot>>,
+) {
+    let Ok((cam_transform, frustum)) = camera_query.get_single() else { return };
+    let cam_pos = cam_transform.translation();
+
+    let in_frustum = |section: IVec3| {
+        frustum.intersects_obb(&section_aabb(section), &Affine3A::IDENTITY, true, true)
+    };
+    let links_of = |section: IVec3| -> Option<FaceLinks> {
+        let entity = world.chunks.get(&IVec3::new(section.x, 0, section.z))?;
+        // The layer above the world is open sky
+        if section.y == SECTIONS as i32 {
+            return Some(FaceLinks::ALL);
+        }
+        occlusion.get(*entity).ok().map(|o| o.sections[section.y as usize])
+    };
+
+    let start = IVec3::new(
+        (cam_pos.x / SECTION_SIZE as f32).floor() as i32,
+        ((cam_pos.y / SECTION_SIZE as f32).floor() as i32).clamp(0, SECTIONS as i32),
+        (cam_pos.z / SECTION_SIZE as f32).floor() as i32,
+    );
+
+    // Breadth-first walk through sections, only crossing from the face we
+    // entered to faces it connects to, and never turning back towards the
+    // camera along an axis we already travelled.
+    let mut reached: HashSet<IVec3> = HashSet::new();
+    let occlusion_enabled = links_of(start).is_some();
+    if occlusion_enabled {
+        let mut queue: VecDeque<(IVec3, Option<usize>, u8)> = VecDeque::new();
+        reached.insert(start);
+        queue.push_back((start, None, 0));
+
+        while let Some((section, entered, travelled)) = queue.pop_front() {
+            let Some(links) = links_of(section) else { continue };
+
+            for (face, dir) in FACE_DIRS.iter().enumerate() {
+                if travelled & (1 << opposite(face)) != 0 {
+                    continue;
+                }
+                if let Some(from) = entered
+                    && !links.connects(from, face)
+                {
+                    continue;
+                }
+
+                let next = section + *dir;
+                if next.y < 0 || next.y > SECTIONS as i32 || reached.contains(&next) {
+                    continue;
+                }
+                if !in_frustum(next) || links_of(next).is_none() {
+                    continue;
+                }
+
+                reached.insert(next);
+                queue.push_back((next, Some(opposite(face)), travelled | (1 << face)));
+            }
+        }
+    }
+
+    let mut frustum_culled = 0;
+    let mut occlusion_culled = 0;
+    for (chunk_pos, &entity) in world.chunks.iter() {
+        let Ok(mut vis) = visibility.get_mut(entity) else { continue };
+
+        let min = Vec3::new(
+            (chunk_pos.x * CHUNK_SIZE as i32) as f32,
+            0.0,
+            (chunk_pos.z * CHUNK_SIZE as i32) as f32,
+        );
+        let aabb = Aabb::from_min_max(
+            min,
+            min + Vec3::new(CHUNK_SIZE as f32, CHUNK_HEIGHT as f32, CHUNK_SIZE as f32),
+        );
+
+        let visible = if !frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, true) {
+            frustum_culled += 1;
+            false
+        } else if occlusion_enabled
+            && !(0..SECTIONS as i32)
+                .any(|y| reached.contains(&IVec3::new(chunk_pos.x, y, chunk_pos.z)))
+        {
+            occlusion_culled += 1;
+            false
+        } else {
+            true
+        };
+
+        let wanted = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        // Avoid tripping change detection on every chunk every frame
+        if *vis != wanted {
+            *vis = wanted;
+        }
+    }
+
+    stats.frustum_culled = frustum_culled;
+    stats.occlusion_culled = occlusion_culled;
+}
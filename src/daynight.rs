@@ -1,3 +1,4 @@
+use crate::console::{CommandResult, ConsoleCommandsExt};
 use bevy::prelude::*;
 
 // Full day/night cycle duration in seconds (10 minutes real time)
@@ -5,12 +6,22 @@ const DAY_DURATION: f32 = 600.0;
 
 #[derive(Resource)]
 pub struct DayNightCycle {
-    pub time: f32,       // 0.0 to 1.0 — 0.0 = midnight, 0.25 = sunrise, 0.5 = noon, 0.75 = sunset
+    pub time: f32,       // 0.0 to 1.0 — 0.0 = sunrise, 0.25 = noon, 0.5 = sunset, 0.75 = midnight
 }
 
+// Named times for `/time set`, where the sun actually is at each
+const NAMED_TIMES: [(&str, f32); 4] = [("sunrise", 0.0), ("noon", 0.25), ("sunset", 0.5), ("midnight", 0.75)];
+
 impl Default for DayNightCycle {
     fn default() -> Self {
-        Self { time: 0.25 } // start at sunrise
+        Self { time: 0.25 } // start at noon
+    }
+}
+
+impl DayNightCycle {
+    /// True while the sun is below the horizon
+    pub fn is_night(&self) -> bool {
+        (self.time * std::f32::consts::TAU).sin() < 0.0
     }
 }
 
@@ -19,6 +30,12 @@ pub struct DayNightPlugin;
 impl Plugin for DayNightPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<DayNightCycle>()
+            .add_console_command(
+                "time",
+                "[set <sunrise|noon|sunset|midnight|0..1>]",
+                "Show or set the time of day",
+                time_command,
+            )
             .add_systems(Update, (update_day_night, update_sky_color));
     }
 }
@@ -33,7 +50,7 @@ fn update_day_night(
     let t = cycle.time;
 
     // Sun angle — full 360 degrees over the day
-    // 0.25 = sunrise (east), 0.5 = noon (overhead), 0.75 = sunset (west)
+    // 0.0 = sunrise (east), 0.25 = noon (overhead), 0.5 = sunset (west)
     let angle = t * std::f32::consts::TAU; // 0 to 2π
 
     // Sun position orbits on the X/Y plane
@@ -119,4 +136,24 @@ fn update_sky_color(
     };
 
     clear_color.0 = sky;
-}
\ No newline at end of file
+}
+
+fn time_command(In(args): In<Vec<String>>, mut cycle: ResMut<DayNightCycle>) -> CommandResult {
+    match args.first().map(String::as_str) {
+        None => Ok(format!("Time is {:.3}{}", cycle.time, if cycle.is_night() { " (night)" } else { "" })),
+        Some("set") => {
+            let value = args.get(1).ok_or("Missing time")?;
+            let time = match NAMED_TIMES.iter().find(|(name, _)| name == value) {
+                Some(&(_, time)) => time,
+                None => value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|t| (0.0..=1.0).contains(t))
+                    .ok_or_else(|| format!("Unknown time '{}'", value))?,
+            };
+            cycle.time = time % 1.0;
+            Ok(format!("Time set to {}", value))
+        }
+        Some(other) => Err(format!("Unknown subcommand '{}'", other)),
+    }
+}
@@ -0,0 +1,381 @@
+use crate::chunk::CHUNK_HEIGHT;
+use crate::world::{SEA_LEVEL, sample_column};
+use bevy::prelude::*;
+use noise::Perlin;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Erosion runs on square regions of this many blocks, cached so every
+/// chunk reads the same result and chunk borders line up.
+pub const REGION_SIZE: i32 = 64;
+// Each region also simulates this far into its neighbours, so droplets
+// flowing in from outside still shape it. Where simulated areas overlap the
+// results are blended, which keeps region borders seamless.
+const MARGIN: i32 = 16;
+const SIMULATED_SIZE: i32 = REGION_SIZE + MARGIN * 2;
+// Erosion only applies above this height, ramping in over MOUNTAIN_RAMP blocks
+const MOUNTAIN_START: f32 = 32.0;
+const MOUNTAIN_RAMP: f32 = 6.0;
+
+const MAX_CACHED_REGIONS: usize = 64;
+// Generation budget per region; exceeding it is logged so settings can be tuned
+const REGION_BUDGET_MS: f32 = 40.0;
+
+#[derive(Clone, Copy)]
+pub struct ErosionSettings {
+    /// Simulated droplets per column of the simulated area
+    pub droplets_per_column: f32,
+    pub max_lifetime: u32,
+    pub inertia: f32,
+    pub capacity: f32,
+    pub min_capacity: f32,
+    pub erode_speed: f32,
+    pub deposit_speed: f32,
+    pub evaporate_speed: f32,
+    pub gravity: f32,
+    pub brush_radius: i32,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        Self {
+            droplets_per_column: 0.7,
+            max_lifetime: 40,
+            inertia: 0.05,
+            capacity: 6.0,
+            min_capacity: 0.01,
+            erode_speed: 0.3,
+            deposit_speed: 0.3,
+            evaporate_speed: 0.02,
+            gravity: 4.0,
+            brush_radius: 2,
+        }
+    }
+}
+
+/// Height offsets (in blocks) over the whole simulated area of one region,
+/// margin included, indexed `[x * SIMULATED_SIZE + z]`.
+pub struct ErodedRegion {
+    /// World position of the simulated area's first column
+    origin: IVec2,
+    deltas: Vec<f32>,
+}
+
+impl ErodedRegion {
+    /// The offset at a column and how much it counts when blended with
+    /// overlapping regions: full weight away from the simulated edges,
+    /// falling towards zero at them where droplets were cut short.
+    fn weighted_delta(&self, world_x: i32, world_z: i32) -> Option<(f32, f32)> {
+        let lx = world_x - self.origin.x;
+        let lz = world_z - self.origin.y;
+        if lx < 0 || lz < 0 || lx >= SIMULATED_SIZE || lz >= SIMULATED_SIZE {
+            return None;
+        }
+        let edge = lx.min(lz).min(SIMULATED_SIZE - 1 - lx).min(SIMULATED_SIZE - 1 - lz);
+        let weight = smoothstep(((edge + 1) as f32 / (MARGIN * 2) as f32).min(1.0));
+        Some((self.deltas[(lx * SIMULATED_SIZE + lz) as usize], weight))
+    }
+}
+
+/// The eroded regions covering an area, blended into one set of offsets.
+pub struct ErodedArea {
+    regions: Vec<Arc<ErodedRegion>>,
+}
+
+impl ErodedArea {
+    pub fn delta(&self, world_x: i32, world_z: i32) -> f32 {
+        let (sum, total) = self
+            .regions
+            .iter()
+            .filter_map(|region| region.weighted_delta(world_x, world_z))
+            .fold((0.0, 0.0), |(sum, total), (delta, weight)| (sum + delta * weight, total + weight));
+        if total > 0.0 { sum / total } else { 0.0 }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ErosionCache {
+    regions: HashMap<IVec2, Arc<ErodedRegion>>,
+    pub regions_generated: u32,
+    pub total_ms: f32,
+}
+
+impl ErosionCache {
+    pub fn region_of(world_x: i32, world_z: i32) -> IVec2 {
+        IVec2::new(world_x.div_euclid(REGION_SIZE), world_z.div_euclid(REGION_SIZE))
+    }
+
+    /// Erosion for every column from `min` to `max` (inclusive, world
+    /// columns), simulating the regions that overlap it if needed.
+    pub fn get_or_erode_area(
+        &mut self,
+        min: IVec2,
+        max: IVec2,
+        noise: &Perlin,
+        seed: u32,
+        settings: &ErosionSettings,
+    ) -> ErodedArea {
+        let first = Self::region_of(min.x - MARGIN, min.y - MARGIN);
+        let last = Self::region_of(max.x + MARGIN, max.y + MARGIN);
+        let mut regions = Vec::new();
+        for rx in first.x..=last.x {
+            for rz in first.y..=last.y {
+                regions.push(self.get_or_erode(IVec2::new(rx, rz), noise, seed, settings));
+            }
+        }
+        ErodedArea { regions }
+    }
+
+    /// Returns the cached region, simulating it first if needed.
+    fn get_or_erode(
+        &mut self,
+        region: IVec2,
+        noise: &Perlin,
+        seed: u32,
+        settings: &ErosionSettings,
+    ) -> Arc<ErodedRegion> {
+        if let Some(eroded) = self.regions.get(&region) {
+            return eroded.clone();
+        }
+
+        let started = Instant::now();
+        let eroded = Arc::new(erode_region(region, noise, seed, settings));
+        let ms = started.elapsed().as_secs_f32() * 1000.0;
+
+        self.regions_generated += 1;
+        self.total_ms += ms;
+        if ms > REGION_BUDGET_MS {
+            warn!(
+                "Erosion for region {} took {:.1}ms (budget {:.0}ms)",
+                region, ms, REGION_BUDGET_MS
+            );
+        }
+
+        self.regions.insert(region, eroded.clone());
+        eroded
+    }
+
+    pub fn average_ms(&self) -> f32 {
+        if self.regions_generated == 0 {
+            0.0
+        } else {
+            self.total_ms / self.regions_generated as f32
+        }
+    }
+
+    /// Drops every region `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(IVec2) -> bool) {
+        self.regions.retain(|&region, _| keep(region));
+    }
+
+    /// Drops the regions furthest from `center` once the cache is full.
+    pub fn trim(&mut self, center: IVec2) {
+        if self.regions.len() <= MAX_CACHED_REGIONS {
+            return;
+        }
+        let mut keys: Vec<IVec2> = self.regions.keys().copied().collect();
+        keys.sort_by_key(|k| {
+            let d = *k - center;
+            -(d.x * d.x + d.y * d.y)
+        });
+        let excess = self.regions.len() - MAX_CACHED_REGIONS;
+        for key in keys.into_iter().take(excess) {
+            self.regions.remove(&key);
+        }
+    }
+}
+
+struct Rng(u32);
+
+impl Rng {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 & 0xFFFFFF) as f32 / 16777216.0
+    }
+}
+
+struct Heightmap {
+    size: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    fn idx(&self, x: usize, z: usize) -> usize {
+        x * self.size + z
+    }
+
+    /// Bilinear height and gradient at a fractional position.
+    fn height_and_gradient(&self, x: f32, z: f32) -> (f32, Vec2) {
+        let cx = x as usize;
+        let cz = z as usize;
+        let u = x - cx as f32;
+        let v = z - cz as f32;
+
+        let h00 = self.heights[self.idx(cx, cz)];
+        let h10 = self.heights[self.idx(cx + 1, cz)];
+        let h01 = self.heights[self.idx(cx, cz + 1)];
+        let h11 = self.heights[self.idx(cx + 1, cz + 1)];
+
+        let gradient = Vec2::new(
+            (h10 - h00) * (1.0 - v) + (h11 - h01) * v,
+            (h01 - h00) * (1.0 - u) + (h11 - h10) * u,
+        );
+        let height = h00 * (1.0 - u) * (1.0 - v) + h10 * u * (1.0 - v) + h01 * (1.0 - u) * v
+            + h11 * u * v;
+        (height, gradient)
+    }
+}
+
+fn erode_region(region: IVec2, noise: &Perlin, seed: u32, settings: &ErosionSettings) -> ErodedRegion {
+    let size = SIMULATED_SIZE as usize;
+    let origin = region * REGION_SIZE - IVec2::splat(MARGIN);
+
+    let mut original = Vec::with_capacity(size * size);
+    let mut wet = Vec::with_capacity(size * size);
+    for x in 0..size as i32 {
+        for z in 0..size as i32 {
+            let column = sample_column(noise, origin.x + x, origin.y + z);
+            original.push(column.height as f32);
+            wet.push(column.water_level >= column.height);
+        }
+    }
+
+    let mut map = Heightmap {
+        size,
+        heights: original.clone(),
+    };
+    simulate_droplets(&mut map, region, seed, settings);
+
+    // Every offset keeps its column inside the clamp below, and so does any
+    // blend of overlapping regions' offsets
+    let deltas = (0..size * size)
+        .map(|i| {
+            if wet[i] {
+                return 0.0;
+            }
+            let mountain = smoothstep(((original[i] - MOUNTAIN_START) / MOUNTAIN_RAMP).clamp(0.0, 1.0));
+            let eroded = original[i] + (map.heights[i] - original[i]) * mountain;
+            // Never dig dry land below the sea, it would flood as a stray puddle
+            let eroded = eroded.clamp(SEA_LEVEL as f32 + 1.0, CHUNK_HEIGHT as f32 - 1.0);
+            eroded - original[i]
+        })
+        .collect();
+
+    ErodedRegion { origin, deltas }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Particle-based hydraulic erosion: each droplet runs downhill, picking up
+/// sediment while it speeds up and dropping it where it slows down, which
+/// cuts gullies into slopes and leaves fans at their feet.
+fn simulate_droplets(map: &mut Heightmap, region: IVec2, seed: u32, settings: &ErosionSettings) {
+    let size = map.size;
+    let mut rng = Rng(
+        (seed ^ (region.x as u32).wrapping_mul(73856093) ^ (region.y as u32).wrapping_mul(19349663))
+            | 1,
+    );
+
+    // Precomputed erosion brush: weights fall off linearly with distance
+    let r = settings.brush_radius;
+    let mut brush: Vec<(i32, i32, f32)> = Vec::new();
+    for dx in -r..=r {
+        for dz in -r..=r {
+            let d = ((dx * dx + dz * dz) as f32).sqrt();
+            if d <= r as f32 {
+                brush.push((dx, dz, 1.0 - d / (r as f32 + 1.0)));
+            }
+        }
+    }
+    let brush_total: f32 = brush.iter().map(|b| b.2).sum();
+
+    let droplets = (size * size) as f32 * settings.droplets_per_column;
+    for _ in 0..droplets as u32 {
+        let mut pos = Vec2::new(
+            rng.next_f32() * (size - 2) as f32,
+            rng.next_f32() * (size - 2) as f32,
+        );
+        let mut dir = Vec2::ZERO;
+        let mut speed = 1.0_f32;
+        let mut water = 1.0_f32;
+        let mut sediment = 0.0_f32;
+
+        for _ in 0..settings.max_lifetime {
+            let cell_x = pos.x as usize;
+            let cell_z = pos.y as usize;
+            let offset = pos - Vec2::new(cell_x as f32, cell_z as f32);
+            let (height, gradient) = map.height_and_gradient(pos.x, pos.y);
+
+            dir = dir * settings.inertia - gradient * (1.0 - settings.inertia);
+            if dir.length_squared() < 1e-8 {
+                break;
+            }
+            dir = dir.normalize();
+            pos += dir;
+
+            if pos.x < 0.0 || pos.y < 0.0 || pos.x >= (size - 1) as f32 || pos.y >= (size - 1) as f32 {
+                break;
+            }
+
+            let (new_height, _) = map.height_and_gradient(pos.x, pos.y);
+            let delta_height = new_height - height;
+            let capacity = (-delta_height * speed * water * settings.capacity).max(settings.min_capacity);
+
+            if sediment > capacity || delta_height > 0.0 {
+                // Uphill: fill the pit we came from; otherwise drop the excess
+                let amount = if delta_height > 0.0 {
+                    delta_height.min(sediment)
+                } else {
+                    (sediment - capacity) * settings.deposit_speed
+                };
+                sediment -= amount;
+
+                let i00 = map.idx(cell_x, cell_z);
+                let i10 = map.idx(cell_x + 1, cell_z);
+                let i01 = map.idx(cell_x, cell_z + 1);
+                let i11 = map.idx(cell_x + 1, cell_z + 1);
+                map.heights[i00] += amount * (1.0 - offset.x) * (1.0 - offset.y);
+                map.heights[i10] += amount * offset.x * (1.0 - offset.y);
+                map.heights[i01] += amount * (1.0 - offset.x) * offset.y;
+                map.heights[i11] += amount * offset.x * offset.y;
+            } else {
+                let amount = ((capacity - sediment) * settings.erode_speed).min(-delta_height);
+                for &(dx, dz, weight) in &brush {
+                    let bx = cell_x as i32 + dx;
+                    let bz = cell_z as i32 + dz;
+                    if bx < 0 || bz < 0 || bx >= size as i32 || bz >= size as i32 {
+                        continue;
+                    }
+                    let i = map.idx(bx as usize, bz as usize);
+                    let removed = (amount * weight / brush_total).min(map.heights[i]);
+                    map.heights[i] -= removed;
+                    sediment += removed;
+                }
+            }
+
+            speed = (speed * speed - delta_height * settings.gravity).max(0.0).sqrt();
+            water *= 1.0 - settings.evaporate_speed;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::Bencher;
+
+    /// One full region, margin included, as chunk generation simulates it.
+    #[bench]
+    fn erode_one_region(b: &mut Bencher) {
+        let noise = Perlin::new(1);
+        let settings = ErosionSettings::default();
+        b.iter(|| erode_region(test::black_box(IVec2::new(3, -2)), &noise, 1, &settings));
+    }
+}
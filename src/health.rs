@@ -0,0 +1,303 @@
+use crate::actions::{Action, ActionState};
+use crate::block::BlockType;
+use crate::camera::{MovementMode, Player};
+use crate::fire::FireEffect;
+use crate::hunger::{HEAL_EXHAUSTION, Hunger};
+use crate::physics::{FallImpact, GRAVITY, PhysicsPosition, Velocity};
+use crate::voxel_world::VoxelWorld;
+use crate::world::World as GameWorld;
+use bevy::prelude::*;
+use bevy::window::CursorGrabMode;
+
+pub const MAX_HEALTH: f32 = 20.0;
+pub const MAX_AIR: f32 = 10.0;
+
+// Falls up to this many blocks are free; each block beyond costs one point
+const SAFE_FALL_BLOCKS: f32 = 3.0;
+// Once out of air, drowning deals this much per tick
+const DROWN_DAMAGE: f32 = 2.0;
+const FIRE_DAMAGE: f32 = 1.0;
+const FIRE_RADIUS: f32 = 1.2;
+const FIRE_HEIGHT: f32 = 2.0;
+// Seconds between repeated drowning / burning hits
+const DAMAGE_INTERVAL: f32 = 1.0;
+// Regain one point this often while fed and not being hurt
+const REGEN_INTERVAL: f32 = 4.0;
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Seconds of breath left underwater
+    pub air: f32,
+    drown_timer: f32,
+    fire_timer: f32,
+    regen_timer: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: MAX_HEALTH,
+            max: MAX_HEALTH,
+            air: MAX_AIR,
+            drown_timer: 0.0,
+            fire_timer: 0.0,
+            regen_timer: 0.0,
+        }
+    }
+}
+
+impl Health {
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+        self.regen_timer = 0.0;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Where the player reappears after dying: the eye position, like the
+/// player transform.
+#[derive(Resource)]
+pub struct SpawnPoint(pub Vec3);
+
+#[derive(Component)]
+struct DeathScreen;
+
+#[derive(Component)]
+struct RespawnButton;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, init_spawn_point).add_systems(
+            Update,
+            (
+                fall_damage,
+                drowning,
+                fire_damage,
+                regenerate,
+                show_death_screen,
+                respawn,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn init_spawn_point(mut commands: Commands, world: Res<GameWorld>) {
+    let feet_y = crate::world::get_spawn_height(&world.noise);
+    commands.insert_resource(SpawnPoint(Vec3::new(0.0, feet_y + crate::physics::PLAYER_HEIGHT, 0.0)));
+}
+
+fn fall_damage(mut query: Query<(&Player, &mut Health, &mut FallImpact)>) {
+    for (player, mut health, mut impact) in query.iter_mut() {
+        let speed = std::mem::take(&mut impact.0);
+        if player.mode != MovementMode::Survival || health.is_dead() {
+            continue;
+        }
+        // v² = 2gh gives the height the player fell from
+        let fallen = speed * speed / (2.0 * -GRAVITY);
+        let damage = (fallen - SAFE_FALL_BLOCKS).floor();
+        if damage > 0.0 {
+            health.damage(damage);
+            info!("Fell {:.1} blocks, took {} damage", fallen, damage);
+        }
+    }
+}
+
+fn drowning(
+    time: Res<Time>,
+    voxels: VoxelWorld,
+    mut query: Query<(&Player, &Transform, &mut Health)>,
+) {
+    let dt = time.delta_seconds();
+    for (player, transform, mut health) in query.iter_mut() {
+        let submerged = voxels.get_block(transform.translation.floor().as_ivec3()) == Some(BlockType::Water);
+        if !submerged || player.mode == MovementMode::Spectator {
+            health.air = (health.air + dt * 5.0).min(MAX_AIR);
+            health.drown_timer = 0.0;
+            continue;
+        }
+
+        health.air = (health.air - dt).max(0.0);
+        if health.air > 0.0 || health.is_dead() {
+            continue;
+        }
+        health.drown_timer -= dt;
+        if health.drown_timer <= 0.0 {
+            health.drown_timer = DAMAGE_INTERVAL;
+            if player.mode == MovementMode::Survival {
+                health.damage(DROWN_DAMAGE);
+            }
+        }
+    }
+}
+
+fn fire_damage(
+    time: Res<Time>,
+    fires: Query<&GlobalTransform, With<FireEffect>>,
+    mut query: Query<(&Player, &Transform, &mut Health)>,
+) {
+    let dt = time.delta_seconds();
+    for (player, transform, mut health) in query.iter_mut() {
+        let feet = transform.translation - Vec3::Y * player.height();
+        let burning = fires.iter().any(|fire| {
+            let offset = feet - fire.translation();
+            offset.xz().length() < FIRE_RADIUS && offset.y > -0.5 && offset.y < FIRE_HEIGHT
+        });
+
+        if !burning || player.mode != MovementMode::Survival || health.is_dead() {
+            health.fire_timer = 0.0;
+            continue;
+        }
+        health.fire_timer -= dt;
+        if health.fire_timer <= 0.0 {
+            health.fire_timer = DAMAGE_INTERVAL;
+            health.damage(FIRE_DAMAGE);
+        }
+    }
+}
+
+fn regenerate(time: Res<Time>, mut query: Query<(&mut Health, Option<&mut Hunger>)>) {
+    for (mut health, mut hunger) in query.iter_mut() {
+        let fed = hunger.as_ref().is_none_or(|h| h.is_fed());
+        if health.is_dead() || health.current >= health.max || !fed {
+            health.regen_timer = 0.0;
+            continue;
+        }
+        health.regen_timer += time.delta_seconds();
+        if health.regen_timer >= REGEN_INTERVAL {
+            health.regen_timer = 0.0;
+            health.current = (health.current + 1.0).min(health.max);
+            if let Some(hunger) = hunger.as_mut() {
+                hunger.exhaust(HEAL_EXHAUSTION);
+            }
+        }
+    }
+}
+
+fn show_death_screen(
+    mut commands: Commands,
+    mut actions: ResMut<ActionState>,
+    health_query: Query<&Health, With<Player>>,
+    screen_query: Query<Entity, With<DeathScreen>>,
+    mut windows: Query<&mut Window>,
+) {
+    let Ok(health) = health_query.get_single() else { return };
+    if !health.is_dead() {
+        return;
+    }
+    // Keep gameplay input off for as long as the player is dead
+    actions.suppressed = true;
+    if !screen_query.is_empty() {
+        return;
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(24.0),
+                    ..default()
+                },
+                background_color: Color::srgba(0.5, 0.0, 0.0, 0.45).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            DeathScreen,
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                "You died!",
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            screen
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(24.0), Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: Color::srgba(0.15, 0.15, 0.15, 0.9).into(),
+                        ..default()
+                    },
+                    RespawnButton,
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        "Respawn (Enter)",
+                        TextStyle {
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn respawn(
+    mut commands: Commands,
+    mut actions: ResMut<ActionState>,
+    spawn_point: Res<SpawnPoint>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<RespawnButton>)>,
+    screen_query: Query<Entity, With<DeathScreen>>,
+    mut player_query: Query<(
+        &mut Player,
+        &mut Health,
+        &mut PhysicsPosition,
+        &mut Velocity,
+        &mut FallImpact,
+        Option<&mut Hunger>,
+    )>,
+) {
+    let Ok((mut player, mut health, mut position, mut velocity, mut impact, hunger)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+    if !health.is_dead() {
+        return;
+    }
+    let clicked = buttons.iter().any(|i| *i == Interaction::Pressed);
+    if !clicked && !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+
+    *health = Health::default();
+    if let Some(mut hunger) = hunger {
+        *hunger = Hunger::default();
+    }
+    position.teleport(spawn_point.0);
+    velocity.0 = Vec3::ZERO;
+    impact.0 = 0.0;
+    player.mode = MovementMode::Survival;
+    player.crouching = false;
+    actions.suppressed = false;
+
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    info!("Respawned at {}", spawn_point.0);
+}
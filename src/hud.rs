@@ -1,10 +1,16 @@
 use bevy::prelude::*;
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 use crate::camera::{Player, PlayerCamera};
-use crate::physics::PLAYER_HEIGHT;
-use crate::world::World as GameWorld;
-use crate::chunk::{CHUNK_SIZE, CHUNK_HEIGHT};
+use crate::voxel_world::VoxelWorld;
+use crate::world::{BlockChanged, ChunkState, ChunkStates};
+use crate::actions::{Action, ActionState};
+use crate::erosion::ErosionCache;
+use crate::culling::CullingStats;
+use crate::chunk::ChunkMap;
+use crate::health::{Health, MAX_AIR};
+use crate::hunger::{FoodBag, Hunger, MAX_STAMINA, Stamina};
 use crate::block::BlockType;
+use crate::console::{Console, MessageKind};
 
 #[derive(Component)]
 pub struct CoordText;
@@ -15,6 +21,51 @@ pub struct Crosshair;
 #[derive(Component)]
 pub struct StatsText;
 
+/// One heart of the health bar; `index` 0 is the leftmost.
+#[derive(Component)]
+pub struct HeartFill {
+    pub index: usize,
+}
+
+/// One air bubble, shown while the player is underwater.
+#[derive(Component)]
+pub struct AirBubble {
+    pub index: usize,
+}
+
+#[derive(Component)]
+pub struct AirBar;
+
+/// One slot of the hunger bar; `index` 0 is the leftmost.
+#[derive(Component)]
+pub struct HungerFill {
+    pub index: usize,
+}
+
+#[derive(Component)]
+pub struct StaminaFill;
+
+#[derive(Component)]
+pub struct AppleCountText;
+
+#[derive(Component)]
+pub struct ChatLogText;
+
+/// The console's input line; hidden while the console is closed.
+#[derive(Component)]
+pub struct ConsoleInputBox;
+
+#[derive(Component)]
+pub struct ConsoleInputText;
+
+const HEARTS: usize = 10;
+const BUBBLES: usize = 10;
+const HUNGER_SLOTS: usize = 10;
+const STAMINA_BAR_WIDTH: f32 = 190.0;
+const CHAT_MESSAGES: usize = 10;
+// Messages fade from the HUD this long after arriving, unless the console is open
+const CHAT_VISIBLE_SECONDS: f32 = 10.0;
+
 #[derive(Component)]
 #[allow(dead_code)]
 pub struct MinimapDot {
@@ -65,17 +116,365 @@ impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(FrameTimeDiagnosticsPlugin)
             .init_resource::<MinimapState>()
-            .add_systems(Startup, (setup_hud, setup_crosshair, setup_minimap, setup_stats))
+            .add_systems(Startup, (setup_hud, setup_crosshair, setup_minimap, setup_stats, setup_health_bar, setup_chat))
             .add_systems(Update, (
                 update_coords,
+                update_health_bar,
+                update_hunger_bar,
+                update_chat,
                 update_stats,
                 toggle_minimap,
+                redraw_minimap_on_block_change,
                 update_minimap_terrain,
                 update_minimap_overlay,
             ));
     }
 }
 
+fn setup_health_bar(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            // Air bubbles sit above the hearts and only show underwater
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(3.0),
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    AirBar,
+                ))
+                .with_children(|bar| {
+                    for index in 0..BUBBLES {
+                        bar.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(12.0),
+                                    height: Val::Px(12.0),
+                                    ..default()
+                                },
+                                background_color: BackgroundColor(Color::srgb(0.4, 0.7, 1.0)),
+                                border_radius: BorderRadius::MAX,
+                                ..default()
+                            },
+                            AirBubble { index },
+                        ));
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(3.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|bar| {
+                    for index in 0..HEARTS {
+                        // Dark slot with a red fill that shrinks to half or nothing
+                        bar.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(16.0),
+                                height: Val::Px(16.0),
+                                border: UiRect::all(Val::Px(1.0)),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::srgba(0.15, 0.0, 0.0, 0.7)),
+                            border_color: BorderColor(Color::srgba(0.0, 0.0, 0.0, 0.9)),
+                            ..default()
+                        })
+                        .with_children(|slot| {
+                            slot.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        height: Val::Percent(100.0),
+                                        ..default()
+                                    },
+                                    background_color: BackgroundColor(Color::srgb(0.85, 0.1, 0.1)),
+                                    ..default()
+                                },
+                                HeartFill { index },
+                            ));
+                        });
+                    }
+                });
+
+            // Hunger slots with the carried apple count beside them
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(3.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|bar| {
+                    for index in 0..HUNGER_SLOTS {
+                        bar.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(16.0),
+                                height: Val::Px(12.0),
+                                border: UiRect::all(Val::Px(1.0)),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::srgba(0.12, 0.07, 0.0, 0.7)),
+                            border_color: BorderColor(Color::srgba(0.0, 0.0, 0.0, 0.9)),
+                            ..default()
+                        })
+                        .with_children(|slot| {
+                            slot.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        height: Val::Percent(100.0),
+                                        ..default()
+                                    },
+                                    background_color: BackgroundColor(Color::srgb(0.8, 0.5, 0.15)),
+                                    ..default()
+                                },
+                                HungerFill { index },
+                            ));
+                        });
+                    }
+                    bar.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        AppleCountText,
+                    ));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(STAMINA_BAR_WIDTH),
+                        height: Val::Px(5.0),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::srgb(0.3, 0.85, 0.35)),
+                            ..default()
+                        },
+                        StaminaFill,
+                    ));
+                });
+        });
+}
+
+fn setup_chat(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                width: Val::Px(480.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+                ChatLogText,
+            ));
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(6.0), Val::Px(3.0)),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    ConsoleInputBox,
+                ))
+                .with_children(|input| {
+                    input.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        ConsoleInputText,
+                    ));
+                });
+        });
+}
+
+fn update_chat(
+    console: Res<Console>,
+    mut log_text: Query<&mut Text, (With<ChatLogText>, Without<ConsoleInputText>)>,
+    mut input_box: Query<&mut Visibility, With<ConsoleInputBox>>,
+    mut input_text: Query<&mut Text, With<ConsoleInputText>>,
+) {
+    if let Ok(mut visibility) = input_box.get_single_mut() {
+        let wanted = if console.open { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    if let Ok(mut text) = input_text.get_single_mut() {
+        let value = format!("> {}_", console.input);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    let Ok(mut text) = log_text.get_single_mut() else { return };
+    let shown: Vec<(String, Color)> = console
+        .messages
+        .iter()
+        .rev()
+        .take(CHAT_MESSAGES)
+        .filter(|m| console.open || console.clock - m.printed_at < CHAT_VISIBLE_SECONDS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(|m| {
+            let color = match m.kind {
+                MessageKind::Chat => Color::WHITE,
+                MessageKind::Info => Color::srgb(0.75, 0.85, 1.0),
+                MessageKind::Error => Color::srgb(1.0, 0.45, 0.4),
+                MessageKind::Echo => Color::srgb(0.6, 0.6, 0.6),
+            };
+            (format!("{}\n", m.text), color)
+        })
+        .collect();
+
+    let unchanged = text.sections.len() == shown.len()
+        && text.sections.iter().zip(&shown).all(|(s, (value, _))| &s.value == value);
+    if unchanged {
+        return;
+    }
+    let style = text.sections[0].style.clone();
+    text.sections = shown
+        .into_iter()
+        .map(|(value, color)| TextSection::new(value, TextStyle { color, ..style.clone() }))
+        .collect();
+    if text.sections.is_empty() {
+        text.sections.push(TextSection::new("", style));
+    }
+}
+
+fn update_health_bar(
+    health_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut hearts: Query<(&HeartFill, &mut Style), Without<AirBubble>>,
+    mut bubbles: Query<(&AirBubble, &mut Visibility), Without<AirBar>>,
+    mut air_bar: Query<&mut Visibility, With<AirBar>>,
+) {
+    let Ok(health) = health_query.get_single() else { return };
+
+    // Each heart is two points of health
+    for (heart, mut style) in hearts.iter_mut() {
+        let points = (health.current - heart.index as f32 * 2.0).clamp(0.0, 2.0);
+        let fill = (points.ceil() / 2.0) * 100.0;
+        style.width = Val::Percent(fill);
+    }
+
+    let underwater = health.air < MAX_AIR;
+    if let Ok(mut visibility) = air_bar.get_single_mut() {
+        *visibility = if underwater { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    let per_bubble = MAX_AIR / BUBBLES as f32;
+    for (bubble, mut visibility) in bubbles.iter_mut() {
+        *visibility = if health.air > bubble.index as f32 * per_bubble {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_hunger_bar(
+    player_query: Query<(&Hunger, &Stamina, &FoodBag), With<Player>>,
+    mut slots: Query<(&HungerFill, &mut Style), Without<StaminaFill>>,
+    mut stamina_fill: Query<(&mut Style, &mut BackgroundColor), With<StaminaFill>>,
+    mut apple_text: Query<&mut Text, With<AppleCountText>>,
+) {
+    let Ok((hunger, stamina, bag)) = player_query.get_single() else { return };
+
+    // Each slot is two points of hunger, like the hearts
+    for (slot, mut style) in slots.iter_mut() {
+        let points = (hunger.level - slot.index as f32 * 2.0).clamp(0.0, 2.0);
+        let fill = Val::Percent((points.ceil() / 2.0) * 100.0);
+        if style.width != fill {
+            style.width = fill;
+        }
+    }
+
+    if let Ok((mut style, mut color)) = stamina_fill.get_single_mut() {
+        let fill = Val::Percent(stamina.current / MAX_STAMINA * 100.0);
+        if style.width != fill {
+            style.width = fill;
+        }
+        // Grey out while winded so it is clear why sprinting stopped
+        let wanted = if stamina.winded {
+            Color::srgb(0.5, 0.5, 0.5)
+        } else {
+            Color::srgb(0.3, 0.85, 0.35)
+        };
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+    }
+
+    if let Ok(mut text) = apple_text.get_single_mut() {
+        let value = if bag.apples > 0 { format!(" {} apples", bag.apples) } else { String::new() };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
 fn setup_crosshair(mut commands: Commands) {
     commands
         .spawn(NodeBundle {
@@ -171,22 +570,23 @@ fn setup_hud(mut commands: Commands) {
 
 fn update_coords(
     mut text_query: Query<&mut Text, With<CoordText>>,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Transform, &Player)>,
 ) {
-    let Ok(transform) = player_query.get_single() else { return };
+    let Ok((transform, player)) = player_query.get_single() else { return };
     let Ok(mut text) = text_query.get_single_mut() else { return };
 
     let eye = transform.translation;
-    let feet_y = eye.y - PLAYER_HEIGHT;
+    let feet_y = eye.y - player.height();
 
     let bx = snap_coord(eye.x) as i32;
     let by = snap_coord(feet_y) as i32;
     let bz = snap_coord(eye.z) as i32;
 
     text.sections[0].value = format!(
-        "X: {}  Y: {}  Z: {}\n({:.1}, {:.1}, {:.1})",
+        "X: {}  Y: {}  Z: {}\n({:.1}, {:.1}, {:.1})\nMode: {:?}",
         bx, by, bz,
-        eye.x, feet_y, eye.z
+        eye.x, feet_y, eye.z,
+        player.mode
     );
 }
 
@@ -220,7 +620,10 @@ fn setup_stats(mut commands: Commands) {
 fn update_stats(
     mut text_query: Query<&mut Text, With<StatsText>>,
     diagnostics: Res<DiagnosticsStore>,
-    world: Res<GameWorld>,
+    map: Res<ChunkMap>,
+    states: Res<ChunkStates>,
+    erosion: Res<ErosionCache>,
+    culling: Res<CullingStats>,
     all_entities: Query<Entity>,
 ) {
     let Ok(mut text) = text_query.get_single_mut() else { return };
@@ -237,7 +640,9 @@ fn update_stats(
         * 1000.0;
 
     let entity_count  = all_entities.iter().count();
-    let chunk_count   = world.chunks.len();
+    let chunk_count   = map.len();
+    let queued_count  = states.count(ChunkState::Queued);
+    let chunk_kib     = map.memory_bytes() as f32 / 1024.0;
 
     let fps_color = if fps >= 55.0 {
         Color::srgb(0.3, 1.0, 0.3)
@@ -249,8 +654,9 @@ fn update_stats(
 
     text.sections[0].style.color = fps_color;
     text.sections[0].value = format!(
-        "FPS {:.0} ({:.1}ms)  |  {} entities  |  {} chunks",
-        fps, frame_ms, entity_count, chunk_count,
+        "FPS {:.0} ({:.1}ms)  |  {} entities  |  {} chunks, {} queued ({} frustum / {} occlusion culled)  |  chunk data {:.0} KiB ({:.1} KiB/chunk)  |  erosion {:.1}ms/region",
+        fps, frame_ms, entity_count, chunk_count, queued_count, culling.frustum_culled, culling.occlusion_culled,
+        chunk_kib, chunk_kib / chunk_count.max(1) as f32, erosion.average_ms(),
     );
 }
 
@@ -276,11 +682,11 @@ fn setup_minimap(mut commands: Commands) {
 }
 
 fn toggle_minimap(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut state: ResMut<MinimapState>,
     mut container_query: Query<&mut Style, With<MinimapContainer>>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyM) {
+    if actions.just_pressed(Action::ToggleMinimap) {
         state.expanded = !state.expanded;
         state.dirty = true;
         if let Ok(mut style) = container_query.get_single_mut() {
@@ -294,11 +700,16 @@ fn toggle_minimap(
 #[derive(Component)]
 pub struct MinimapTerrainDot;
 
+fn redraw_minimap_on_block_change(mut changes: EventReader<BlockChanged>, mut state: ResMut<MinimapState>) {
+    if changes.read().next().is_some() {
+        state.dirty = true;
+    }
+}
+
 fn update_minimap_terrain(
     mut commands: Commands,
     player_query: Query<&Transform, With<Player>>,
-    world: Res<GameWorld>,
-    chunks: Query<&crate::chunk::Chunk>,
+    voxels: VoxelWorld,
     dot_query: Query<Entity, With<MinimapTerrainDot>>,
     windows: Query<&Window>,
     mut state: ResMut<MinimapState>,
@@ -343,26 +754,7 @@ fn update_minimap_terrain(
             let wx = (px + dx as f32).floor() as i32;
             let wz = (pz + dz as f32).floor() as i32;
 
-            let chunk_x   = wx.div_euclid(CHUNK_SIZE as i32);
-            let chunk_z   = wz.div_euclid(CHUNK_SIZE as i32);
-            let chunk_pos = IVec3::new(chunk_x, 0, chunk_z);
-
-            let Some(&chunk_entity) = world.chunks.get(&chunk_pos) else { continue };
-            let Ok(chunk) = chunks.get(chunk_entity) else { continue };
-
-            let lx = wx.rem_euclid(CHUNK_SIZE as i32) as usize;
-            let lz = wz.rem_euclid(CHUNK_SIZE as i32) as usize;
-
-            let mut top_block = BlockType::Air;
-            for y in (0..CHUNK_HEIGHT).rev() {
-                let b = chunk.get_block(lx, y, lz);
-                if b.is_solid() || matches!(b, BlockType::Water) {
-                    top_block = b;
-                    break;
-                }
-            }
-
-            if matches!(top_block, BlockType::Air) { continue; }
+            let Some((_, top_block)) = voxels.top_block(wx, wz) else { continue };
 
             let dot_color = minimap_color(top_block);
             let screen_x  = map_left + (dx + map_radius) as f32 * scale;
@@ -520,7 +912,7 @@ fn update_minimap_overlay(
     ));
 }
 
-fn minimap_color(block: BlockType) -> Color {
+pub fn minimap_color(block: BlockType) -> Color {
     match block {
         BlockType::Grass  => Color::srgb(0.25, 0.65, 0.15),
         BlockType::Dirt   => Color::srgb(0.45, 0.28, 0.12),
@@ -0,0 +1,280 @@
+use crate::actions::{Action, ActionState};
+use crate::camera::{MovementMode, Player};
+use crate::console::{CommandResult, ConsoleCommandsExt, parse_arg};
+use crate::health::Health;
+use crate::physics::{Grounded, VoxelBody, VoxelBodyBundle};
+use bevy::prelude::*;
+
+pub const MAX_HUNGER: f32 = 20.0;
+pub const MAX_STAMINA: f32 = 100.0;
+
+// Every this much exhaustion costs one hunger point
+const EXHAUSTION_PER_POINT: f32 = 4.0;
+const SPRINT_EXHAUSTION: f32 = 0.8; // per second
+const WALK_EXHAUSTION: f32 = 0.05; // per second
+const JUMP_EXHAUSTION: f32 = 0.2;
+pub const HEAL_EXHAUSTION: f32 = 1.5;
+
+const SPRINT_STAMINA: f32 = 20.0; // per second
+const JUMP_STAMINA: f32 = 10.0;
+const STAMINA_REGEN: f32 = 15.0; // per second
+// Stamina only starts to come back after this long without spending any
+const STAMINA_REGEN_DELAY: f32 = 1.0;
+// After running dry, sprinting unlocks again at this much stamina
+const STAMINA_RECOVERED: f32 = 30.0;
+
+/// Health only regenerates at or above this hunger level
+pub const FED_LEVEL: f32 = 16.0;
+// Too hungry to sprint at or below this level
+const SPRINT_HUNGER: f32 = 6.0;
+// An empty stomach hurts this often, but never below STARVE_FLOOR health
+const STARVE_INTERVAL: f32 = 4.0;
+const STARVE_FLOOR: f32 = 1.0;
+
+const APPLE_CHANCE: f32 = 0.08;
+const APPLE_FOOD: f32 = 4.0;
+const PICKUP_RADIUS: f32 = 1.5;
+const APPLE_DESPAWN: f32 = 120.0;
+
+#[derive(Component)]
+pub struct Hunger {
+    pub level: f32,
+    pub exhaustion: f32,
+    starve_timer: f32,
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self {
+            level: MAX_HUNGER,
+            exhaustion: 0.0,
+            starve_timer: 0.0,
+        }
+    }
+}
+
+impl Hunger {
+    pub fn exhaust(&mut self, amount: f32) {
+        self.exhaustion += amount;
+        while self.exhaustion >= EXHAUSTION_PER_POINT {
+            self.exhaustion -= EXHAUSTION_PER_POINT;
+            self.level = (self.level - 1.0).max(0.0);
+        }
+    }
+
+    pub fn is_fed(&self) -> bool {
+        self.level >= FED_LEVEL
+    }
+}
+
+#[derive(Component)]
+pub struct Stamina {
+    pub current: f32,
+    /// Set when stamina ran dry or the player is too hungry; blocks sprinting
+    pub winded: bool,
+    regen_delay: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            current: MAX_STAMINA,
+            winded: false,
+            regen_delay: 0.0,
+        }
+    }
+}
+
+/// Food the player is carrying.
+#[derive(Component, Default)]
+pub struct FoodBag {
+    pub apples: u32,
+}
+
+#[derive(Component)]
+pub struct AppleDrop {
+    age: f32,
+}
+
+/// Sent when leaves are destroyed, so they can drop apples.
+#[derive(Event)]
+pub struct LeavesBroken {
+    pub position: Vec3,
+    pub count: u32,
+}
+
+#[derive(Resource)]
+struct AppleAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+pub struct HungerPlugin;
+
+impl Plugin for HungerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LeavesBroken>()
+            .add_console_command("give", "apple [count]", "Put items in your bag", give_command)
+            .add_systems(Startup, setup_apple_assets)
+            .add_systems(
+                Update,
+                (
+                    spend_energy,
+                    starve,
+                    eat,
+                    drop_apples,
+                    pick_up_apples,
+                ),
+            );
+    }
+}
+
+fn setup_apple_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(AppleAssets {
+        mesh: meshes.add(Cuboid::new(0.25, 0.25, 0.25)),
+        material: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.8, 0.08, 0.05),
+            ..default()
+        }),
+    });
+}
+
+fn spend_energy(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    mut query: Query<(&Player, &Grounded, &mut Hunger, &mut Stamina)>,
+) {
+    let dt = time.delta_seconds();
+    for (player, grounded, mut hunger, mut stamina) in query.iter_mut() {
+        if player.mode != MovementMode::Survival {
+            continue;
+        }
+
+        let mut spent = 0.0;
+        if player.sprinting {
+            spent += SPRINT_STAMINA * dt;
+            hunger.exhaust(SPRINT_EXHAUSTION * dt);
+        } else if actions.movement != Vec2::ZERO {
+            hunger.exhaust(WALK_EXHAUSTION * dt);
+        }
+        if actions.just_pressed(Action::Jump) && grounded.0 {
+            spent += JUMP_STAMINA;
+            hunger.exhaust(JUMP_EXHAUSTION);
+        }
+
+        if spent > 0.0 {
+            stamina.current = (stamina.current - spent).max(0.0);
+            stamina.regen_delay = STAMINA_REGEN_DELAY;
+        } else if stamina.regen_delay > 0.0 {
+            stamina.regen_delay -= dt;
+        } else {
+            stamina.current = (stamina.current + STAMINA_REGEN * dt).min(MAX_STAMINA);
+        }
+
+        if stamina.current <= 0.0 || hunger.level <= SPRINT_HUNGER {
+            stamina.winded = true;
+        } else if stamina.current >= STAMINA_RECOVERED {
+            stamina.winded = false;
+        }
+    }
+}
+
+fn starve(time: Res<Time>, mut query: Query<(&Player, &mut Hunger, &mut Health)>) {
+    for (player, mut hunger, mut health) in query.iter_mut() {
+        if hunger.level > 0.0 || player.mode != MovementMode::Survival || health.is_dead() {
+            hunger.starve_timer = 0.0;
+            continue;
+        }
+        hunger.starve_timer += time.delta_seconds();
+        if hunger.starve_timer >= STARVE_INTERVAL {
+            hunger.starve_timer = 0.0;
+            if health.current > STARVE_FLOOR {
+                health.damage(1.0);
+            }
+        }
+    }
+}
+
+fn eat(actions: Res<ActionState>, mut query: Query<(&mut Hunger, &mut FoodBag)>) {
+    if !actions.just_pressed(Action::Eat) {
+        return;
+    }
+    for (mut hunger, mut bag) in query.iter_mut() {
+        if bag.apples == 0 || hunger.level >= MAX_HUNGER {
+            continue;
+        }
+        bag.apples -= 1;
+        hunger.level = (hunger.level + APPLE_FOOD).min(MAX_HUNGER);
+        info!("Ate an apple ({} left)", bag.apples);
+    }
+}
+
+fn drop_apples(
+    mut commands: Commands,
+    mut events: EventReader<LeavesBroken>,
+    assets: Res<AppleAssets>,
+) {
+    for event in events.read() {
+        for i in 0..event.count {
+            if rand::random::<f32>() >= APPLE_CHANCE {
+                continue;
+            }
+            // Scatter a little so several apples do not stack exactly
+            let angle = i as f32 * 2.4;
+            let position = event.position + Vec3::new(angle.cos() * 0.6, 0.5, angle.sin() * 0.6);
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material: assets.material.clone(),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                AppleDrop { age: 0.0 },
+                VoxelBodyBundle::new(
+                    VoxelBody::new(Vec3::splat(0.125)).with_bounciness(0.2),
+                    position,
+                    Vec3::Y * 2.0,
+                ),
+            ));
+        }
+    }
+}
+
+fn pick_up_apples(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut apples: Query<(Entity, &Transform, &mut AppleDrop)>,
+    mut player_query: Query<(&Transform, &Player, &mut FoodBag), Without<AppleDrop>>,
+) {
+    let Ok((player_transform, player, mut bag)) = player_query.get_single_mut() else { return };
+    let feet = player_transform.translation - Vec3::Y * player.height();
+
+    for (entity, transform, mut apple) in apples.iter_mut() {
+        apple.age += time.delta_seconds();
+        // Measure from the middle of the body so apples at the feet count
+        let to_body = transform.translation - (feet + Vec3::Y * player.height() * 0.5);
+        if to_body.xz().length() < PICKUP_RADIUS && to_body.y.abs() < player.height() * 0.5 + 0.5 {
+            bag.apples += 1;
+            commands.entity(entity).despawn_recursive();
+        } else if apple.age > APPLE_DESPAWN {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn give_command(In(args): In<Vec<String>>, mut query: Query<&mut FoodBag, With<Player>>) -> CommandResult {
+    let item: String = parse_arg(&args, 0, "item")?;
+    if item != "apple" {
+        return Err(format!("Unknown item '{}'; only apples can be carried", item));
+    }
+    let count: u32 = if args.len() > 1 { parse_arg(&args, 1, "count")? } else { 1 };
+    let Ok(mut bag) = query.get_single_mut() else {
+        return Err("No player to give to".to_string());
+    };
+    bag.apples = bag.apples.saturating_add(count);
+    Ok(format!("Gave {} apple{}", count, if count == 1 { "" } else { "s" }))
+}
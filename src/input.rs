@@ -1,49 +1,116 @@
 use bevy::prelude::*;
-use crate::camera::{Player, PlayerCamera};
+use crate::actions::{Action, ActionState};
+use crate::camera::{MovementMode, Player, PlayerCamera};
+use crate::hunger::Stamina;
 use crate::physics::Velocity;
+use crate::world::RemoteWorld;
+
+// Two jump presses closer together than this toggle creative flight
+const DOUBLE_TAP_WINDOW: f32 = 0.3;
 
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, player_movement);
+        app.add_systems(Update, (switch_movement_mode, toggle_step_assist, player_movement).chain());
     }
 }
 
+fn switch_movement_mode(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    mut last_jump: Local<Option<f32>>,
+    mut query: Query<(&mut Player, &mut Velocity)>,
+    remote: Option<Res<RemoteWorld>>,
+) {
+    // On a server the mode is the server's to decide
+    if remote.is_some() {
+        return;
+    }
+    let Ok((mut player, mut velocity)) = query.get_single_mut() else { return };
+    let now = time.elapsed_seconds();
+
+    if actions.just_pressed(Action::ToggleSpectator) {
+        player.mode = if player.mode == MovementMode::Spectator {
+            MovementMode::Survival
+        } else {
+            MovementMode::Spectator
+        };
+        velocity.0.y = 0.0;
+        info!("Movement mode: {:?}", player.mode);
+    }
+
+    if actions.just_pressed(Action::Jump) {
+        let double_tap = last_jump.is_some_and(|t| now - t < DOUBLE_TAP_WINDOW);
+        if double_tap && player.mode != MovementMode::Spectator {
+            player.mode = if player.mode == MovementMode::Creative {
+                MovementMode::Survival
+            } else {
+                MovementMode::Creative
+            };
+            velocity.0.y = 0.0;
+            info!("Movement mode: {:?}", player.mode);
+            *last_jump = None;
+        } else {
+            *last_jump = Some(now);
+        }
+    }
+}
+
+fn toggle_step_assist(
+    actions: Res<ActionState>,
+    mut query: Query<&mut Player>,
+    remote: Option<Res<RemoteWorld>>,
+) {
+    if !actions.just_pressed(Action::ToggleStepAssist) {
+        return;
+    }
+    // The server simulates movement without it, and prediction must agree
+    if remote.is_some() {
+        info!("Step assist can't be changed on a server");
+        return;
+    }
+    let Ok(mut player) = query.get_single_mut() else { return };
+    player.step_assist = !player.step_assist;
+    info!("Step assist {}", if player.step_assist { "on" } else { "off" });
+}
+
 fn player_movement(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&Player, &PlayerCamera, &mut Velocity), With<Player>>,
+    actions: Res<ActionState>,
+    mut query: Query<(&mut Player, &PlayerCamera, &mut Velocity, Option<&Stamina>)>,
 ) {
-    for (player, camera, mut velocity) in query.iter_mut() {
+    for (mut player, camera, mut velocity, stamina) in query.iter_mut() {
         let forward = Vec3::new(camera.yaw.sin(), 0.0, camera.yaw.cos());
         let right = Vec3::new(camera.yaw.cos(), 0.0, -camera.yaw.sin());
 
-        let mut horizontal = Vec3::ZERO;
-        if keyboard.pressed(KeyCode::KeyW) { horizontal -= forward; }
-        if keyboard.pressed(KeyCode::KeyS) { horizontal += forward; }
-        if keyboard.pressed(KeyCode::KeyA) { horizontal -= right; }
-        if keyboard.pressed(KeyCode::KeyD) { horizontal += right; }
-
-        let shift_held = keyboard.pressed(KeyCode::ShiftLeft)
-            || keyboard.pressed(KeyCode::ShiftRight);
-        let digit_held = keyboard.pressed(KeyCode::Digit1)
-            || keyboard.pressed(KeyCode::Digit2)
-            || keyboard.pressed(KeyCode::Digit3)
-            || keyboard.pressed(KeyCode::Digit4);
+        // Stick input keeps its magnitude, so a half-tilted stick walks slowly
+        let input = actions.movement;
+        let mut horizontal = right * input.x - forward * input.y;
 
-        let sprinting = shift_held && !digit_held && keyboard.pressed(KeyCode::KeyW);
+        let winded = stamina.is_some_and(|s| s.winded);
+        let sprinting = actions.pressed(Action::Sprint) && input.y > 0.0 && !player.crouching && !winded;
+        player.sprinting = sprinting;
 
-        let speed = if sprinting {
-            player.speed * player.sprint_multiplier
+        let base_speed = player.mode_speed();
+        let speed = if player.crouching {
+            base_speed * player.crouch_multiplier
+        } else if sprinting {
+            base_speed * player.sprint_multiplier
         } else {
-            player.speed
+            base_speed
         };
 
         if horizontal.length_squared() > 0.0 {
-            horizontal = horizontal.normalize() * speed;
+            horizontal = horizontal.clamp_length_max(1.0) * speed;
         }
 
         velocity.0.x = horizontal.x;
         velocity.0.z = horizontal.z;
+
+        // Flying modes own vertical movement; survival leaves it to gravity
+        if player.mode != MovementMode::Survival {
+            let vertical = actions.pressed(Action::Jump) as i32 - actions.pressed(Action::Descend) as i32;
+            velocity.0.y = vertical as f32 * base_speed;
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,299 @@
+use crate::block::BlockType;
+use crate::chunk::{Chunk, chunk_of, local_of};
+use crate::console::{CommandResult, ConsoleCommandsExt};
+use crate::voxel_world::VoxelWorld;
+use crate::world::{BlockChanged, RemoteWorld};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Steps kept for /undo; the oldest are forgotten first
+const MAX_UNDO: usize = 32;
+
+// Journal file layout:
+//
+//   u8       format version
+//   u32      edit count
+//   per edit: i32 × 3 position, u8 old block, u8 new block
+const JOURNAL_VERSION: u8 = 1;
+const EDIT_BYTES: usize = 14;
+
+/// Every block change made in this world, so a chunk can be rebuilt from
+/// the seed by generating it and replaying its edits, plus the undo and
+/// redo stacks. Each stack entry is one step: a broken block, a felled
+/// tree, a placed structure or a whole `/fill`.
+#[derive(Resource, Default)]
+pub struct EditJournal {
+    /// Edits per chunk, in the order they were made
+    log: HashMap<IVec3, Vec<BlockChanged>>,
+    /// The block last written at each edited position
+    latest: HashMap<IVec3, BlockType>,
+    undo: Vec<Vec<BlockChanged>>,
+    redo: Vec<Vec<BlockChanged>>,
+    unsaved: bool,
+    /// Chunks edited since their blocks were last saved
+    unsaved_chunks: HashSet<IVec3>,
+}
+
+impl EditJournal {
+    /// Adds block changes to the log without making them an undo step.
+    pub fn log_edits(&mut self, edits: impl IntoIterator<Item = BlockChanged>) {
+        for edit in edits {
+            self.log.entry(chunk_of(edit.pos)).or_default().push(edit);
+            self.latest.insert(edit.pos, edit.new);
+            self.unsaved = true;
+            self.unsaved_chunks.insert(chunk_of(edit.pos));
+        }
+    }
+
+    /// Replays every logged edit inside `chunk` onto it, e.g. after it was
+    /// freshly generated.
+    pub fn replay(&self, chunk: &mut Chunk) {
+        for edit in self.log.get(&chunk.position).into_iter().flatten() {
+            let (lx, ly, lz) = local_of(edit.pos);
+            chunk.set_block(lx, ly, lz, edit.new);
+        }
+    }
+
+    /// Whether any block in the chunk was ever changed.
+    pub fn has_edits(&self, chunk_pos: IVec3) -> bool {
+        self.log.contains_key(&chunk_pos)
+    }
+
+    /// The block the journal last wrote at `pos`, if it ever wrote one.
+    pub fn edited_block(&self, pos: IVec3) -> Option<BlockType> {
+        self.latest.get(&pos).copied()
+    }
+
+    /// Collapses every position's edits into one, from the block it had
+    /// before its first edit to the one it has now. Replaying the result
+    /// gives the same blocks.
+    pub fn compact(&mut self) {
+        for edits in self.log.values_mut() {
+            let mut first: HashMap<IVec3, usize> = HashMap::new();
+            let mut compacted: Vec<BlockChanged> = Vec::new();
+            for edit in edits.drain(..) {
+                match first.get(&edit.pos) {
+                    Some(&i) => compacted[i].new = edit.new,
+                    None => {
+                        first.insert(edit.pos, compacted.len());
+                        compacted.push(edit);
+                    }
+                }
+            }
+            *edits = compacted;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.values().map(Vec::len).sum()
+    }
+
+    /// True once after each change, for saving only when needed.
+    pub fn take_unsaved(&mut self) -> bool {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// The chunks edited since the last call.
+    pub fn take_unsaved_chunks(&mut self) -> HashSet<IVec3> {
+        std::mem::take(&mut self.unsaved_chunks)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![JOURNAL_VERSION];
+        out.extend_from_slice(&(self.len() as u32).to_le_bytes());
+        for edit in self.log.values().flatten() {
+            for v in edit.pos.to_array() {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            out.extend_from_slice(&[edit.old.id(), edit.new.id()]);
+        }
+        out
+    }
+
+    /// Reads a journal written by [`EditJournal::to_bytes`]. The undo
+    /// history is not saved, so it starts empty.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let (&version, rest) = bytes.split_first().ok_or("empty file")?;
+        if version != JOURNAL_VERSION {
+            return Err("unsupported journal version");
+        }
+        let (count, rest) = rest.split_first_chunk::<4>().ok_or("missing edit count")?;
+        let count = u32::from_le_bytes(*count) as usize;
+        if rest.len() != count * EDIT_BYTES {
+            return Err("edit count does not match the file size");
+        }
+
+        let mut journal = EditJournal::default();
+        let edits = rest
+            .chunks_exact(EDIT_BYTES)
+            .map(|b| {
+                let i32_at = |at: usize| i32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]);
+                let block = |id| BlockType::from_id(id).ok_or("unknown block id");
+                Ok(BlockChanged {
+                    pos: IVec3::new(i32_at(0), i32_at(4), i32_at(8)),
+                    old: block(b[12])?,
+                    new: block(b[13])?,
+                })
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+        journal.log_edits(edits);
+        journal.unsaved = false;
+        journal.unsaved_chunks.clear();
+        Ok(journal)
+    }
+}
+
+/// Reads and writes blocks by world position like [`VoxelWorld`], but
+/// each [`BlockEditor::apply`] is one undo step.
+#[derive(SystemParam)]
+pub struct BlockEditor<'w> {
+    voxels: VoxelWorld<'w>,
+    journal: ResMut<'w, EditJournal>,
+    remote: Option<Res<'w, RemoteWorld>>,
+}
+
+impl BlockEditor<'_> {
+    /// Bulk edits (commands, structures, undo) only work in single player.
+    /// A server checks each edit against what the player could do by hand
+    /// and would send most of them back.
+    pub fn allow_bulk_edits(&self) -> Result<(), String> {
+        if self.remote.is_some() {
+            return Err("World editing only works in single player".to_string());
+        }
+        Ok(())
+    }
+
+    /// The block at `pos`, or `None` outside loaded chunks.
+    pub fn get_block(&self, pos: IVec3) -> Option<BlockType> {
+        self.voxels.get_block(pos)
+    }
+
+    fn write(&mut self, edits: impl IntoIterator<Item = (IVec3, BlockType)>) -> Vec<BlockChanged> {
+        self.voxels.set_blocks(edits)
+    }
+
+    fn record(&mut self, step: Vec<BlockChanged>) {
+        let journal = &mut *self.journal;
+        if journal.undo.len() >= MAX_UNDO {
+            journal.undo.remove(0);
+        }
+        journal.undo.push(step);
+        journal.redo.clear();
+    }
+
+    /// Sets one block as its own undo step. Returns the change, if the
+    /// block is loaded and was different.
+    pub fn set_block(&mut self, pos: IVec3, block: BlockType) -> Option<BlockChanged> {
+        let change = self.voxels.set_block(pos, block)?;
+        self.record(vec![change]);
+        Some(change)
+    }
+
+    /// Applies a batch of edits as one undo step. Returns the changes made.
+    pub fn apply(&mut self, edits: impl IntoIterator<Item = (IVec3, BlockType)>) -> Vec<BlockChanged> {
+        let changed = self.write(edits);
+        if !changed.is_empty() {
+            self.record(changed.clone());
+        }
+        changed
+    }
+
+    /// Puts back the blocks of the last step. Returns how many changed.
+    pub fn undo(&mut self) -> Option<usize> {
+        let step = self.journal.undo.pop()?;
+        let restored = self.write(step.iter().rev().map(|edit| (edit.pos, edit.old)));
+        self.journal.redo.push(step);
+        Some(restored.len())
+    }
+
+    pub fn redo(&mut self) -> Option<usize> {
+        let step = self.journal.redo.pop()?;
+        let redone = self.write(step.iter().map(|edit| (edit.pos, edit.new)));
+        self.journal.undo.push(step);
+        Some(redone.len())
+    }
+}
+
+pub struct JournalPlugin;
+
+impl Plugin for JournalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditJournal>()
+            .add_systems(Update, log_block_changes)
+            .add_console_command("undo", "", "Undo the last block change", undo_command)
+            .add_console_command("redo", "", "Redo the last undone change", redo_command);
+    }
+}
+
+fn log_block_changes(mut changes: EventReader<BlockChanged>, mut journal: ResMut<EditJournal>) {
+    journal.log_edits(changes.read().copied());
+}
+
+fn undo_command(In(_args): In<Vec<String>>, mut editor: BlockEditor) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let count = editor.undo().ok_or("Nothing to undo")?;
+    Ok(format!("Undid {} blocks", count))
+}
+
+fn redo_command(In(_args): In<Vec<String>>, mut editor: BlockEditor) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let count = editor.redo().ok_or("Nothing to redo")?;
+    Ok(format!("Redid {} blocks", count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(pos: IVec3, old: BlockType, new: BlockType) -> BlockChanged {
+        BlockChanged { pos, old, new }
+    }
+
+    fn journal() -> EditJournal {
+        let a = IVec3::new(-1, 20, 3);
+        let b = IVec3::new(17, 30, -40);
+        let mut journal = EditJournal::default();
+        journal.log_edits([
+            edit(a, BlockType::Grass, BlockType::Air),
+            edit(b, BlockType::Air, BlockType::Wood),
+            edit(a, BlockType::Air, BlockType::Stone),
+            edit(a, BlockType::Stone, BlockType::Sand),
+        ]);
+        journal
+    }
+
+    #[test]
+    fn edited_block_is_the_latest_edit() {
+        let journal = journal();
+        assert_eq!(journal.edited_block(IVec3::new(-1, 20, 3)), Some(BlockType::Sand));
+        assert_eq!(journal.edited_block(IVec3::new(17, 30, -40)), Some(BlockType::Wood));
+        assert_eq!(journal.edited_block(IVec3::new(0, 20, 3)), None);
+    }
+
+    #[test]
+    fn compacting_keeps_one_edit_per_position() {
+        let mut journal = journal();
+        journal.compact();
+        assert_eq!(journal.len(), 2);
+
+        let edits = &journal.log[&IVec3::new(-1, 0, 0)];
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].old, edits[0].new), (BlockType::Grass, BlockType::Sand));
+
+        let mut chunk = Chunk::new(IVec3::new(-1, 0, 0));
+        journal.replay(&mut chunk);
+        assert_eq!(chunk.get_block(15, 20, 3), BlockType::Sand);
+    }
+
+    #[test]
+    fn saved_journal_reads_back() {
+        let mut journal = journal();
+        journal.compact();
+        let loaded = EditJournal::from_bytes(&journal.to_bytes()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.edited_block(IVec3::new(-1, 20, 3)), Some(BlockType::Sand));
+        assert_eq!(loaded.edited_block(IVec3::new(17, 30, -40)), Some(BlockType::Wood));
+        assert!(EditJournal::from_bytes(&journal.to_bytes()[..20]).is_err());
+    }
+}
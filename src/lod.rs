@@ -0,0 +1,283 @@
+use crate::block::BlockType;
+use crate::camera::MainCamera;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::erosion::{ErodedArea, ErosionCache};
+use crate::hud::minimap_color;
+use crate::world::{World as GameWorld, WorldSettings, sample_column};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::collections::{HashMap, HashSet};
+
+// Quadtree roots are this many chunks wide
+const ROOT_SIZE: i32 = 16;
+// A node is split while the camera is closer than this many node widths
+const SPLIT_FACTOR: i32 = 2;
+// Every tile is a grid of this many quads per side, whatever its size
+const TILE_RESOLUTION: i32 = CHUNK_SIZE as i32;
+const MAX_TILES_PER_FRAME: usize = 4;
+// Tiles up to this many chunks wide get the same erosion as full chunks.
+// Only tiles this small are ever replaced by chunks; coarser ones sample
+// too sparsely to show gullies, and eroding every region out to the LOD
+// distance would take seconds.
+const MAX_ERODED_TILE_SIZE: i32 = 4;
+
+/// Quadtree leaf: `origin` and `size` are in chunk units.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LodKey {
+    pub origin: IVec2,
+    pub size: i32,
+}
+
+#[derive(Component)]
+pub struct LodTile;
+
+#[derive(Resource, Default)]
+pub struct LodState {
+    pub tiles: HashMap<LodKey, Entity>,
+    desired: Vec<LodKey>,
+    last_center: Option<IVec2>,
+    last_loaded: usize,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+pub struct LodPlugin;
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodState>()
+            .add_systems(Update, update_lod_tiles);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_lod_tiles(
+    mut commands: Commands,
+    world: Res<GameWorld>,
+    settings: Res<WorldSettings>,
+    mut state: ResMut<LodState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut erosion: ResMut<ErosionCache>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(cam) = camera_query.get_single() else { return };
+
+    let center = IVec2::new(
+        (cam.translation.x / CHUNK_SIZE as f32).floor() as i32,
+        (cam.translation.z / CHUNK_SIZE as f32).floor() as i32,
+    );
+
+    // Re-plan only when the camera changes chunk or full chunks come and go
+    if state.last_center != Some(center) || state.last_loaded != world.chunks.len() {
+        state.last_center = Some(center);
+        state.last_loaded = world.chunks.len();
+
+        let mut desired = Vec::new();
+        let reach = settings.lod_distance;
+        let min_root = (center - IVec2::splat(reach)).div_euclid(IVec2::splat(ROOT_SIZE));
+        let max_root = (center + IVec2::splat(reach)).div_euclid(IVec2::splat(ROOT_SIZE));
+        for rx in min_root.x..=max_root.x {
+            for rz in min_root.y..=max_root.y {
+                collect_leaves(
+                    LodKey {
+                        origin: IVec2::new(rx, rz) * ROOT_SIZE,
+                        size: ROOT_SIZE,
+                    },
+                    center,
+                    &world,
+                    reach,
+                    &mut desired,
+                );
+            }
+        }
+        desired.sort_by_key(|key| chebyshev_to_node(*key, center));
+        state.desired = desired;
+    }
+
+    let material = state
+        .material
+        .get_or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.95,
+                reflectance: 0.1,
+                // Skirts are seen from both sides
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            })
+        })
+        .clone();
+
+    let missing: Vec<LodKey> = state
+        .desired
+        .iter()
+        .filter(|key| !state.tiles.contains_key(key))
+        .take(MAX_TILES_PER_FRAME)
+        .copied()
+        .collect();
+
+    for key in &missing {
+        let eroded = (settings.erosion_enabled && key.size <= MAX_ERODED_TILE_SIZE).then(|| {
+            let min = key.origin * CHUNK_SIZE as i32;
+            let max = min + IVec2::splat(TILE_RESOLUTION * key.size);
+            erosion.get_or_erode_area(min, max, &world.noise, world.seed, &settings.erosion)
+        });
+        let entity = commands
+            .spawn((
+                PbrBundle {
+                    mesh: meshes.add(build_tile_mesh(*key, &world, eroded.as_ref())),
+                    material: material.clone(),
+                    ..default()
+                },
+                LodTile,
+            ))
+            .id();
+        state.tiles.insert(*key, entity);
+    }
+
+    // Old tiles stay until their replacements exist, so moving never opens holes
+    if missing.is_empty() {
+        let keep: HashSet<LodKey> = state.desired.iter().copied().collect();
+        state.tiles.retain(|key, entity| {
+            let keep_tile = keep.contains(key);
+            if !keep_tile {
+                commands.entity(*entity).despawn_recursive();
+            }
+            keep_tile
+        });
+    }
+}
+
+fn chebyshev_to_node(key: LodKey, center: IVec2) -> i32 {
+    let max = key.origin + IVec2::splat(key.size - 1);
+    let dx = (key.origin.x - center.x).max(center.x - max.x).max(0);
+    let dz = (key.origin.y - center.y).max(center.y - max.y).max(0);
+    dx.max(dz)
+}
+
+fn collect_leaves(
+    key: LodKey,
+    center: IVec2,
+    world: &GameWorld,
+    reach: i32,
+    out: &mut Vec<LodKey>,
+) {
+    let dist = chebyshev_to_node(key, center);
+    if dist > reach {
+        return;
+    }
+
+    // Split near the camera, and always where the node may overlap
+    // full-detail chunks, which stay loaded out to the unload margin
+    let full_detail = world.render_distance + world.unload_margin;
+    if key.size > 1 && (dist < key.size * SPLIT_FACTOR || dist <= full_detail) {
+        let half = key.size / 2;
+        for (ox, oz) in [(0, 0), (half, 0), (0, half), (half, half)] {
+            collect_leaves(
+                LodKey {
+                    origin: key.origin + IVec2::new(ox, oz),
+                    size: half,
+                },
+                center,
+                world,
+                reach,
+                out,
+            );
+        }
+        return;
+    }
+
+    // Single chunks already generated at full detail need no stand-in
+    if key.size == 1
+        && dist <= full_detail
+        && world.chunks.contains_key(&IVec3::new(key.origin.x, 0, key.origin.y))
+    {
+        return;
+    }
+
+    out.push(key);
+}
+
+/// Heightfield of the column tops inside the tile, sampled every `size`
+/// blocks, with a skirt around the edge to hide cracks against coarser or
+/// finer neighbours. `eroded` offsets the heights the way chunk generation
+/// does.
+fn build_tile_mesh(key: LodKey, world: &GameWorld, eroded: Option<&ErodedArea>) -> Mesh {
+    let step = key.size;
+    let base_x = key.origin.x * CHUNK_SIZE as i32;
+    let base_z = key.origin.y * CHUNK_SIZE as i32;
+    let verts_per_side = (TILE_RESOLUTION + 1) as usize;
+    let skirt_depth = 2.0 + step as f32 * 2.0;
+
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(verts_per_side * verts_per_side * 2);
+    let mut colors: Vec<[f32; 4]> = Vec::with_capacity(positions.capacity());
+
+    for i in 0..=TILE_RESOLUTION {
+        for j in 0..=TILE_RESOLUTION {
+            let wx = base_x + i * step;
+            let wz = base_z + j * step;
+            let mut column = sample_column(&world.noise, wx, wz);
+            if let Some(area) = eroded {
+                let eroded_height = column.height as f32 + area.delta(wx, wz);
+                column.height = (eroded_height.round() as usize).clamp(1, CHUNK_HEIGHT - 1);
+            }
+
+            let (top, block) = if column.water_level > column.height {
+                (column.water_level, BlockType::Water)
+            } else {
+                (column.height, column.surface_block())
+            };
+
+            let color = minimap_color(block).to_linear();
+            positions.push([wx as f32, top as f32 + 1.0, wz as f32]);
+            colors.push([color.red, color.green, color.blue, 1.0]);
+        }
+    }
+
+    let index = |i: usize, j: usize| (i * verts_per_side + j) as u32;
+    let mut indices: Vec<u32> = Vec::with_capacity(TILE_RESOLUTION as usize * TILE_RESOLUTION as usize * 6);
+    for i in 0..TILE_RESOLUTION as usize {
+        for j in 0..TILE_RESOLUTION as usize {
+            let a = index(i, j);
+            let b = index(i + 1, j);
+            let c = index(i, j + 1);
+            let d = index(i + 1, j + 1);
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    // Skirt: duplicate each edge vertex lower down and stitch a vertical strip
+    let last = TILE_RESOLUTION as usize;
+    let edges: [Vec<(usize, usize)>; 4] = [
+        (0..=last).map(|j| (0, j)).collect(),
+        (0..=last).map(|i| (i, last)).collect(),
+        (0..=last).rev().map(|j| (last, j)).collect(),
+        (0..=last).rev().map(|i| (i, 0)).collect(),
+    ];
+    for edge in edges {
+        let start = positions.len() as u32;
+        for &(i, j) in &edge {
+            let top = positions[index(i, j) as usize];
+            positions.push([top[0], top[1] - skirt_depth, top[2]]);
+            colors.push(colors[index(i, j) as usize]);
+        }
+        for k in 0..edge.len() - 1 {
+            let (i0, j0) = edge[k];
+            let (i1, j1) = edge[k + 1];
+            let t0 = index(i0, j0);
+            let t1 = index(i1, j1);
+            let b0 = start + k as u32;
+            let b1 = start + k as u32 + 1;
+            indices.extend_from_slice(&[t0, b0, t1, t1, b0, b1]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_indices(Indices::U32(indices));
+    mesh.compute_smooth_normals();
+    mesh
+}
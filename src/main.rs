@@ -1,36 +1,109 @@
+// `cargo +nightly bench --features bench` runs the #[bench] functions
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
+
 use bevy::prelude::*;
 use bevy::render::RenderPlugin;
 use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
 use bevy::window::PresentMode;
 
+mod actions;
 mod block;
 mod block_breaking;
 mod block_registry;
+mod bindings_menu;
 mod camera;
 mod chunk;
+mod chunk_cache;
+mod chunk_codec;
+mod client;
+mod console;
+mod culling;
 mod daynight;
+mod erosion;
 mod fire;
+mod health;
+mod hunger;
 mod hud;
 mod input;
+mod journal;
+mod lod;
+mod mobs;
+mod net;
+mod pathfinding;
 mod physics;
+mod save;
+mod server;
 mod tree_breaking;
+mod vox;
+mod voxel_world;
 mod world;
+mod world_edit;
 
+use actions::ActionsPlugin;
+use bindings_menu::BindingsMenuPlugin;
 use block_breaking::BlockBreakingPlugin;
 use block_registry::{BlockRegistry, BlockRegistryPlugin};
 use camera::CameraPlugin;
 use chunk::ChunkPlugin;
+use client::ClientPlugin;
+use console::ConsolePlugin;
+use culling::CullingPlugin;
 use daynight::DayNightPlugin;
 use fire::FirePlugin;
+use health::HealthPlugin;
+use hunger::HungerPlugin;
 use hud::HudPlugin;
 use input::InputPlugin;
+use journal::JournalPlugin;
+use lod::LodPlugin;
+use mobs::MobsPlugin;
 use physics::PhysicsPlugin;
+use save::SavePlugin;
 use tree_breaking::TreeBreakingPlugin;
+use vox::VoxPlugin;
 use world::WorldPlugin;
+use world_edit::WorldEditPlugin;
 
 fn main() {
-    App::new()
-        .add_plugins(
+    let args: Vec<String> = std::env::args().collect();
+    let client = match args.get(1).map(String::as_str) {
+        Some("server") => {
+            let default_addr = format!("0.0.0.0:{}", net::DEFAULT_PORT);
+            let addr = args.get(2).cloned().unwrap_or(default_addr);
+            if let Err(e) = server::run(&addr) {
+                eprintln!("Server failed on {}: {}", addr, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("connect") => {
+            let Some(addr) = args.get(2) else {
+                eprintln!("Usage: VoxelVerse connect <host:port>");
+                std::process::exit(2);
+            };
+            match client::connect(addr) {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    eprintln!("Could not join {}: {}", addr, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut app = App::new();
+    if let Some(client) = client {
+        // The server's seed, so locally predicted terrain matches its chunks
+        app.insert_resource(world::World::new(client.seed))
+            .insert_resource(client)
+            .add_plugins(ClientPlugin);
+    } else {
+        // Only single player saves; a server owns a shared world
+        app.add_plugins(SavePlugin);
+    }
+
+    app.add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
                     primary_window: Some(Window {
@@ -49,6 +122,7 @@ fn main() {
                     ..default()
                 }),
         )
+        .add_plugins((ActionsPlugin, BindingsMenuPlugin, ConsolePlugin, HealthPlugin, HungerPlugin, JournalPlugin, MobsPlugin, WorldEditPlugin))
         .add_plugins((
             WorldPlugin,
             ChunkPlugin,
@@ -61,6 +135,9 @@ fn main() {
             BlockBreakingPlugin,
             DayNightPlugin,
             TreeBreakingPlugin,
+            VoxPlugin,
+            LodPlugin,
+            CullingPlugin,
         ))
         // Start with sunrise sky — day/night will take over immediately
         .insert_resource(ClearColor(Color::srgb(0.53, 0.81, 0.92)))
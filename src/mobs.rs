@@ -0,0 +1,675 @@
+use crate::actions::{Action, ActionState};
+use crate::block::BlockType;
+use crate::block_breaking::BreakingState;
+use crate::camera::{MovementMode, Player};
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, ChunkMap};
+use crate::daynight::DayNightCycle;
+use crate::fire::{FireCubeAssets, FireEffect, FireParticle, ParticleKind};
+use crate::health::Health;
+use crate::pathfinding::{PathRules, can_stand, find_path};
+use crate::physics::{Grounded, PhysicsPosition, Velocity, VoxelBody, VoxelBodyBundle};
+use crate::voxel_world::VoxelWorld;
+use crate::world::{BlockChanged, ChunkRoot, TreeRng, World as GameWorld};
+use bevy::prelude::*;
+
+// Roughly one chunk in this many gets a herd
+const HERD_CHANCE: f32 = 0.12;
+const MAX_HERD: i32 = 3;
+const MAX_MOBS: usize = 40;
+
+const WANDER_RADIUS: i32 = 8;
+const WANDER_SPEED: f32 = 1.6;
+const FLEE_SPEED: f32 = 4.5;
+// Players closer than this scare animals off, unless they sneak up
+const FLEE_RADIUS: f32 = 5.0;
+const FLEE_DISTANCE: f32 = 10.0;
+// Seconds a fleeing animal needs without a threat nearby to calm down
+const CALM_DOWN: f32 = 3.0;
+const REPATH_INTERVAL: f32 = 1.0;
+// Give up on a path that has not brought the mob closer for this long
+const STUCK_TIME: f32 = 2.0;
+const MAX_PATH_NODES: usize = 600;
+const JUMP_VELOCITY: f32 = 8.5;
+const WAYPOINT_REACHED: f32 = 0.3;
+
+// Night spawning happens in a ring around the player, away from fire light
+const NIGHT_SPAWN_INTERVAL: f32 = 3.0;
+const MAX_HOSTILE: usize = 8;
+const SPAWN_MIN_DISTANCE: f32 = 16.0;
+const SPAWN_MAX_DISTANCE: f32 = 28.0;
+const FIRE_LIGHT_RADIUS: f32 = 10.0;
+
+const CHASE_RADIUS: f32 = 16.0;
+const CHASE_SPEED: f32 = 3.2;
+// Within this range the chaser walks straight at the player
+const DIRECT_CHASE: f32 = 2.0;
+const CHASE_REPATH: f32 = 0.5;
+const ATTACK_RANGE: f32 = 1.3;
+const ATTACK_DAMAGE: f32 = 3.0;
+const ATTACK_COOLDOWN: f32 = 1.0;
+// Health lost per second by hostile mobs in daylight
+const SUN_DAMAGE: f32 = 2.0;
+
+const PLAYER_HIT_DAMAGE: f32 = 4.0;
+const HIT_COOLDOWN: f32 = 0.4;
+const KNOCKBACK_SPEED: f32 = 6.0;
+const KNOCKBACK_LIFT: f32 = 4.0;
+// Seconds a mob drifts with its knockback before steering again
+const STUN_TIME: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MobKind {
+    Sheep,
+    /// Hostile; comes out at night and burns in daylight
+    Zombie,
+}
+
+impl MobKind {
+    fn half_extents(self) -> Vec3 {
+        match self {
+            MobKind::Sheep => Vec3::new(0.4, 0.45, 0.4),
+            MobKind::Zombie => Vec3::new(0.3, 0.9, 0.3),
+        }
+    }
+
+    fn path_rules(self) -> PathRules {
+        match self {
+            MobKind::Sheep => PathRules {
+                height: 1,
+                max_jump: 1,
+                max_drop: 3,
+            },
+            MobKind::Zombie => PathRules {
+                height: 2,
+                max_jump: 1,
+                max_drop: 3,
+            },
+        }
+    }
+
+    fn max_health(self) -> f32 {
+        match self {
+            MobKind::Sheep => 8.0,
+            MobKind::Zombie => 20.0,
+        }
+    }
+
+    pub fn is_hostile(self) -> bool {
+        matches!(self, MobKind::Zombie)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MobState {
+    /// Standing still until the timer runs out
+    Idle { timer: f32 },
+    Wander,
+    /// Running from `from`; `calm` counts down once it is out of range
+    Flee { from: Vec3, calm: f32, repath: f32 },
+    /// Hunting the player
+    Chase { repath: f32 },
+}
+
+#[derive(Component)]
+pub struct Mob {
+    pub kind: MobKind,
+    pub state: MobState,
+    pub path: Vec<IVec3>,
+    /// The chunk the mob was spawned for; it leaves when that unloads
+    pub home_chunk: IVec3,
+    pub health: f32,
+    rng: TreeRng,
+    stuck_timer: f32,
+    best_distance: f32,
+    attack_cooldown: f32,
+    stun: f32,
+}
+
+/// Body and head meshes of one kind of mob, offset from the body centre.
+struct MobModel {
+    body_mesh: Handle<Mesh>,
+    body_material: Handle<StandardMaterial>,
+    body_offset: Vec3,
+    head_mesh: Handle<Mesh>,
+    head_material: Handle<StandardMaterial>,
+    head_offset: Vec3,
+}
+
+#[derive(Resource)]
+struct MobAssets {
+    sheep: MobModel,
+    zombie: MobModel,
+}
+
+impl MobAssets {
+    fn model(&self, kind: MobKind) -> &MobModel {
+        match kind {
+            MobKind::Sheep => &self.sheep,
+            MobKind::Zombie => &self.zombie,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct NightSpawner {
+    timer: Timer,
+}
+
+pub struct MobsPlugin;
+
+impl Plugin for MobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NightSpawner {
+            timer: Timer::from_seconds(NIGHT_SPAWN_INTERVAL, TimerMode::Repeating),
+        })
+        .add_systems(Startup, setup_mob_assets)
+        .add_systems(
+            Update,
+            (
+                spawn_herds,
+                spawn_night_mobs,
+                despawn_unloaded_mobs,
+                drop_stale_paths,
+                update_mob_state,
+                follow_paths,
+                mob_attacks,
+                player_attacks,
+                burn_in_daylight,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn setup_mob_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let sheep = MobKind::Sheep.half_extents();
+    let zombie = MobKind::Zombie.half_extents();
+    let mut material = |r: f32, g: f32, b: f32| {
+        materials.add(StandardMaterial {
+            base_color: Color::srgb(r, g, b),
+            ..default()
+        })
+    };
+
+    commands.insert_resource(MobAssets {
+        sheep: MobModel {
+            body_mesh: meshes.add(Cuboid::new(sheep.x * 2.0, sheep.y * 1.6, sheep.z * 2.6)),
+            body_material: material(0.92, 0.92, 0.88),
+            body_offset: Vec3::Y * 0.05,
+            head_mesh: meshes.add(Cuboid::new(0.4, 0.4, 0.4)),
+            head_material: material(0.35, 0.28, 0.22),
+            head_offset: Vec3::new(0.0, 0.25, sheep.z * 1.3 + 0.15),
+        },
+        zombie: MobModel {
+            // Legs and torso below the neck, head on top
+            body_mesh: meshes.add(Cuboid::new(zombie.x * 2.0, zombie.y * 2.0 - 0.45, zombie.z * 1.4)),
+            body_material: material(0.2, 0.35, 0.55),
+            body_offset: Vec3::Y * -0.225,
+            head_mesh: meshes.add(Cuboid::new(0.45, 0.45, 0.45)),
+            head_material: material(0.3, 0.55, 0.3),
+            head_offset: Vec3::Y * (zombie.y - 0.225),
+        },
+    });
+}
+
+fn foot_cell(center: Vec3, half: Vec3) -> IVec3 {
+    (center - Vec3::Y * (half.y - 0.01)).floor().as_ivec3()
+}
+
+/// Highest grass block in a chunk column with room for a mob on top.
+fn grass_surface(chunk: &Chunk, x: usize, z: usize) -> Option<usize> {
+    let top = (0..CHUNK_HEIGHT).rev().find(|&y| chunk.get_block(x, y, z) != BlockType::Air)?;
+    (chunk.get_block(x, top, z) == BlockType::Grass && top + 2 < CHUNK_HEIGHT).then_some(top + 1)
+}
+
+/// Each newly generated chunk rolls for a herd from the world seed and its
+/// position, so the same animals come back when it is generated again.
+fn spawn_herds(
+    mut commands: Commands,
+    world: Res<GameWorld>,
+    assets: Res<MobAssets>,
+    map: Res<ChunkMap>,
+    new_chunks: Query<&ChunkRoot, Added<ChunkRoot>>,
+    mobs: Query<(), With<Mob>>,
+) {
+    let mut population = mobs.iter().count();
+    for chunk in new_chunks.iter().filter_map(|root| map.get(root.position)) {
+        let seed = world.seed
+            ^ (chunk.position.x as u32).wrapping_mul(73856093)
+            ^ (chunk.position.z as u32).wrapping_mul(19349663);
+        let mut rng = TreeRng::new(seed);
+        if rng.f32() >= HERD_CHANCE {
+            continue;
+        }
+
+        let herd = rng.range(1, MAX_HERD + 1);
+        for _ in 0..herd {
+            if population >= MAX_MOBS {
+                return;
+            }
+            let x = rng.range(0, CHUNK_SIZE as i32) as usize;
+            let z = rng.range(0, CHUNK_SIZE as i32) as usize;
+            let Some(y) = grass_surface(chunk, x, z) else { continue };
+
+            let kind = MobKind::Sheep;
+            let half = kind.half_extents();
+            let center = Vec3::new(
+                (chunk.position.x * CHUNK_SIZE as i32 + x as i32) as f32 + 0.5,
+                y as f32 + half.y,
+                (chunk.position.z * CHUNK_SIZE as i32 + z as i32) as f32 + 0.5,
+            );
+            spawn_mob(&mut commands, &assets, kind, center, chunk.position, rng.next());
+            population += 1;
+        }
+    }
+}
+
+fn spawn_mob(commands: &mut Commands, assets: &MobAssets, kind: MobKind, center: Vec3, home_chunk: IVec3, seed: u32) {
+    let half = kind.half_extents();
+    let model = assets.model(kind);
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_translation(center)),
+            Mob {
+                kind,
+                state: MobState::Idle { timer: 1.0 },
+                path: Vec::new(),
+                home_chunk,
+                health: kind.max_health(),
+                rng: TreeRng::new(seed),
+                stuck_timer: 0.0,
+                best_distance: f32::MAX,
+                attack_cooldown: 0.0,
+                stun: 0.0,
+            },
+            // Walking speed is set directly each frame, so no ground friction
+            VoxelBodyBundle::new(VoxelBody::new(half).with_friction(0.0), center, Vec3::ZERO),
+        ))
+        .with_children(|mob| {
+            mob.spawn(PbrBundle {
+                mesh: model.body_mesh.clone(),
+                material: model.body_material.clone(),
+                transform: Transform::from_translation(model.body_offset),
+                ..default()
+            });
+            mob.spawn(PbrBundle {
+                mesh: model.head_mesh.clone(),
+                material: model.head_material.clone(),
+                transform: Transform::from_translation(model.head_offset),
+                ..default()
+            });
+        });
+}
+
+/// At night, hostile mobs appear on dark ground in a ring around the player.
+#[allow(clippy::too_many_arguments)]
+fn spawn_night_mobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    cycle: Res<DayNightCycle>,
+    mut spawner: ResMut<NightSpawner>,
+    voxels: VoxelWorld,
+    assets: Res<MobAssets>,
+    player_query: Query<&Transform, With<Player>>,
+    fires: Query<&GlobalTransform, With<FireEffect>>,
+    mobs: Query<&Mob>,
+) {
+    if !cycle.is_night() || !spawner.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if mobs.iter().filter(|m| m.kind.is_hostile()).count() >= MAX_HOSTILE {
+        return;
+    }
+    let Ok(player) = player_query.get_single() else { return };
+
+    let angle = rand::random::<f32>() * std::f32::consts::TAU;
+    let distance = SPAWN_MIN_DISTANCE + rand::random::<f32>() * (SPAWN_MAX_DISTANCE - SPAWN_MIN_DISTANCE);
+    let column = player.translation + Vec3::new(angle.cos(), 0.0, angle.sin()) * distance;
+    if fires.iter().any(|fire| fire.translation().xz().distance(column.xz()) < FIRE_LIGHT_RADIUS) {
+        return;
+    }
+
+    let solid = |x: i32, y: i32, z: i32| voxels.is_solid(IVec3::new(x, y, z));
+    let (x, z) = (column.x.floor() as i32, column.z.floor() as i32);
+    let kind = MobKind::Zombie;
+    let Some(ground) = (0..CHUNK_HEIGHT as i32).rev().find(|&y| solid(x, y, z)) else { return };
+    let feet = IVec3::new(x, ground + 1, z);
+    if !can_stand(&solid, feet, kind.path_rules().height) {
+        return;
+    }
+
+    let home_chunk = IVec3::new(x.div_euclid(CHUNK_SIZE as i32), 0, z.div_euclid(CHUNK_SIZE as i32));
+    let center = feet.as_vec3() + Vec3::new(0.5, kind.half_extents().y, 0.5);
+    spawn_mob(&mut commands, &assets, kind, center, home_chunk, rand::random());
+}
+
+fn despawn_unloaded_mobs(mut commands: Commands, world: Res<GameWorld>, mobs: Query<(Entity, &Mob)>) {
+    for (entity, mob) in mobs.iter() {
+        if !world.chunks.contains_key(&mob.home_chunk) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Finds somewhere to stand near `around`, searching a few blocks up and down.
+fn standable_near(solid: &impl Fn(i32, i32, i32) -> bool, around: IVec3, height: i32) -> Option<IVec3> {
+    (0..=4)
+        .flat_map(|dy| [dy, -dy])
+        .map(|dy| around + IVec3::Y * dy)
+        .find(|&cell| can_stand(solid, cell, height))
+}
+
+fn path_to(
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    from: IVec3,
+    target: IVec3,
+    rules: &PathRules,
+) -> Option<Vec<IVec3>> {
+    standable_near(solid, target, rules.height).and_then(|goal| find_path(solid, from, goal, rules, MAX_PATH_NODES))
+}
+
+/// A path through a changed block may now be blocked, or lead off a new
+/// drop; forgetting it makes the mob plan again on its next update.
+fn drop_stale_paths(mut changes: EventReader<BlockChanged>, mut mobs: Query<&mut Mob>) {
+    let changed: Vec<IVec3> = changes.read().map(|c| c.pos).collect();
+    if changed.is_empty() {
+        return;
+    }
+    for mut mob in mobs.iter_mut() {
+        let height = mob.kind.path_rules().height;
+        // The cells a walker on the path stands on and passes through
+        let touches = |cell: &IVec3| {
+            changed
+                .iter()
+                .any(|pos| pos.x == cell.x && pos.z == cell.z && (cell.y - 1..cell.y + height).contains(&pos.y))
+        };
+        if mob.path.iter().any(touches) {
+            mob.path.clear();
+        }
+    }
+}
+
+fn update_mob_state(
+    time: Res<Time>,
+    voxels: VoxelWorld,
+    player_query: Query<(&Transform, &Player, &Health), Without<Mob>>,
+    mut mobs: Query<(&mut Mob, &PhysicsPosition)>,
+) {
+    let dt = time.delta_seconds();
+    let solid = |x: i32, y: i32, z: i32| voxels.is_solid(IVec3::new(x, y, z));
+    let player = player_query.get_single().ok();
+    let feet_of = |(transform, player, _): (&Transform, &Player, &Health)| transform.translation - Vec3::Y * player.height();
+    // Animals only notice players who do not sneak; monsters only hunt
+    // players who can be hurt
+    let threat = player
+        .filter(|(_, p, _)| !p.crouching && p.mode != MovementMode::Spectator)
+        .map(feet_of);
+    let prey = player
+        .filter(|(_, p, health)| p.mode == MovementMode::Survival && !health.is_dead())
+        .map(feet_of);
+
+    for (mut mob, position) in mobs.iter_mut() {
+        let mob = &mut *mob;
+        let rules = mob.kind.path_rules();
+        let here = foot_cell(position.current, mob.kind.half_extents());
+        let hostile = mob.kind.is_hostile();
+        let scared_by = threat.filter(|t| !hostile && t.distance(position.current) < FLEE_RADIUS);
+        let hunting = prey.filter(|p| hostile && p.distance(position.current) < CHASE_RADIUS);
+
+        if let Some(from) = scared_by
+            && !matches!(mob.state, MobState::Flee { .. })
+        {
+            mob.path.clear();
+            mob.state = MobState::Flee {
+                from,
+                calm: CALM_DOWN,
+                repath: 0.0,
+            };
+        }
+        if hunting.is_some() && !matches!(mob.state, MobState::Chase { .. }) {
+            mob.path.clear();
+            mob.state = MobState::Chase { repath: 0.0 };
+        }
+
+        mob.state = match mob.state {
+            MobState::Idle { timer } if timer > 0.0 => MobState::Idle { timer: timer - dt },
+            MobState::Idle { .. } => {
+                let offset = IVec3::new(
+                    mob.rng.range(-WANDER_RADIUS, WANDER_RADIUS + 1),
+                    0,
+                    mob.rng.range(-WANDER_RADIUS, WANDER_RADIUS + 1),
+                );
+                match path_to(&solid, here, here + offset, &rules) {
+                    Some(path) => {
+                        mob.path = path;
+                        MobState::Wander
+                    }
+                    // Try somewhere else a little later
+                    None => MobState::Idle { timer: 0.5 },
+                }
+            }
+            MobState::Wander if mob.path.is_empty() => MobState::Idle {
+                timer: 2.0 + mob.rng.f32() * 6.0,
+            },
+            MobState::Wander => MobState::Wander,
+            MobState::Flee { from, calm, repath } => {
+                let from = scared_by.unwrap_or(from);
+                let calm = if scared_by.is_some() { CALM_DOWN } else { calm - dt };
+                let mut repath = repath - dt;
+                if repath <= 0.0 || mob.path.is_empty() {
+                    repath = REPATH_INTERVAL;
+                    let away = (position.current - from).with_y(0.0).normalize_or(Vec3::X);
+                    // Veer a little so herds scatter instead of running in a line
+                    let angle = (mob.rng.f32() - 0.5) * 1.2;
+                    let dir = Quat::from_rotation_y(angle) * away * FLEE_DISTANCE;
+                    let target = here + IVec3::new(dir.x.round() as i32, 0, dir.z.round() as i32);
+                    if let Some(path) = path_to(&solid, here, target, &rules) {
+                        mob.path = path;
+                    }
+                }
+                if calm <= 0.0 {
+                    mob.path.clear();
+                    MobState::Idle { timer: 1.0 }
+                } else {
+                    MobState::Flee { from, calm, repath }
+                }
+            }
+            MobState::Chase { repath } => match hunting {
+                None => {
+                    mob.path.clear();
+                    MobState::Idle { timer: 1.0 }
+                }
+                Some(target) => {
+                    let target_cell = target.floor().as_ivec3();
+                    let mut repath = repath - dt;
+                    if target.distance(position.current) < DIRECT_CHASE {
+                        mob.path = vec![target_cell];
+                    } else if repath <= 0.0 || mob.path.is_empty() {
+                        repath = CHASE_REPATH;
+                        if let Some(path) = path_to(&solid, here, target_cell, &rules) {
+                            mob.path = path;
+                        }
+                    }
+                    MobState::Chase { repath }
+                }
+            },
+        };
+    }
+}
+
+/// Steers each mob's body towards its next waypoint, jumping onto ledges.
+fn follow_paths(
+    time: Res<Time>,
+    mut mobs: Query<(&mut Mob, &PhysicsPosition, &mut Velocity, &Grounded, &mut Transform)>,
+) {
+    let dt = time.delta_seconds();
+    for (mut mob, position, mut velocity, grounded, mut transform) in mobs.iter_mut() {
+        let half = mob.kind.half_extents();
+        let here = foot_cell(position.current, half);
+
+        // Let knockback carry the body for a moment
+        if mob.stun > 0.0 {
+            mob.stun -= dt;
+            continue;
+        }
+
+        let Some(&next) = mob.path.first() else {
+            velocity.0.x = 0.0;
+            velocity.0.z = 0.0;
+            continue;
+        };
+        let target = Vec3::new(next.x as f32 + 0.5, position.current.y, next.z as f32 + 0.5);
+        let to_target = target - position.current;
+        let distance = to_target.length();
+
+        if distance < WAYPOINT_REACHED && here.y == next.y {
+            mob.path.remove(0);
+            mob.stuck_timer = 0.0;
+            mob.best_distance = f32::MAX;
+            continue;
+        }
+
+        if distance < mob.best_distance - 0.05 {
+            mob.best_distance = distance;
+            mob.stuck_timer = 0.0;
+        } else {
+            mob.stuck_timer += dt;
+            if mob.stuck_timer > STUCK_TIME {
+                mob.path.clear();
+                mob.stuck_timer = 0.0;
+                mob.best_distance = f32::MAX;
+                continue;
+            }
+        }
+
+        let speed = match mob.state {
+            MobState::Flee { .. } => FLEE_SPEED,
+            MobState::Chase { .. } => CHASE_SPEED,
+            _ => WANDER_SPEED,
+        };
+        let dir = to_target.normalize_or_zero();
+        velocity.0.x = dir.x * speed;
+        velocity.0.z = dir.z * speed;
+        if next.y > here.y && grounded.0 {
+            velocity.0.y = JUMP_VELOCITY;
+        }
+        if dir != Vec3::ZERO {
+            transform.rotation = Quat::from_rotation_arc(Vec3::Z, dir);
+        }
+    }
+}
+
+fn mob_attacks(
+    time: Res<Time>,
+    mut mobs: Query<(&mut Mob, &PhysicsPosition)>,
+    mut player_query: Query<(&Transform, &Player, &mut Health), Without<Mob>>,
+) {
+    let dt = time.delta_seconds();
+    let Ok((transform, player, mut health)) = player_query.get_single_mut() else { return };
+    let feet = transform.translation - Vec3::Y * player.height();
+
+    for (mut mob, position) in mobs.iter_mut() {
+        mob.attack_cooldown = (mob.attack_cooldown - dt).max(0.0);
+        if !matches!(mob.state, MobState::Chase { .. }) || mob.attack_cooldown > 0.0 {
+            continue;
+        }
+        let offset = position.current - feet;
+        let in_reach = offset.xz().length() < ATTACK_RANGE && offset.y > -1.0 && offset.y < player.height() + 1.0;
+        if in_reach && player.mode == MovementMode::Survival && !health.is_dead() {
+            health.damage(ATTACK_DAMAGE);
+            mob.attack_cooldown = ATTACK_COOLDOWN;
+        }
+    }
+}
+
+/// Left click hits the mob under the crosshair, knocking it back.
+fn player_attacks(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    state: Res<BreakingState>,
+    mut cooldown: Local<f32>,
+    player_query: Query<&Transform, With<Player>>,
+    mut mobs: Query<(&mut Mob, &PhysicsPosition, &mut Velocity)>,
+) {
+    *cooldown = (*cooldown - time.delta_seconds()).max(0.0);
+    let Some(entity) = state.mob_target else { return };
+    if !actions.just_pressed(Action::Break) || *cooldown > 0.0 {
+        return;
+    }
+    let Ok(player) = player_query.get_single() else { return };
+    let Ok((mut mob, position, mut velocity)) = mobs.get_mut(entity) else { return };
+    *cooldown = HIT_COOLDOWN;
+
+    mob.health -= PLAYER_HIT_DAMAGE;
+    if mob.health <= 0.0 {
+        info!("Killed a {:?}", mob.kind);
+        commands.entity(entity).despawn_recursive();
+        return;
+    }
+
+    let away = (position.current - player.translation).with_y(0.0).normalize_or(Vec3::X);
+    velocity.0 = away * KNOCKBACK_SPEED + Vec3::Y * KNOCKBACK_LIFT;
+    mob.stun = STUN_TIME;
+    if !mob.kind.is_hostile() {
+        mob.path.clear();
+        mob.state = MobState::Flee {
+            from: player.translation,
+            calm: CALM_DOWN,
+            repath: 0.0,
+        };
+    }
+}
+
+/// Hostile mobs catch fire once the sun is up and burn away.
+fn burn_in_daylight(
+    mut commands: Commands,
+    time: Res<Time>,
+    cycle: Res<DayNightCycle>,
+    fire_assets: Res<FireCubeAssets>,
+    mut mobs: Query<(Entity, &mut Mob, &Transform)>,
+) {
+    if cycle.is_night() {
+        return;
+    }
+    let dt = time.delta_seconds();
+    for (entity, mut mob, transform) in mobs.iter_mut() {
+        if !mob.kind.is_hostile() {
+            continue;
+        }
+        mob.health -= SUN_DAMAGE * dt;
+        if mob.health <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        // A few flames per second rising off the body
+        if mob.rng.f32() < dt * 12.0 {
+            let half = mob.kind.half_extents();
+            let offset = Vec3::new(
+                (mob.rng.f32() - 0.5) * half.x * 2.0,
+                (mob.rng.f32() - 0.5) * half.y * 2.0,
+                (mob.rng.f32() - 0.5) * half.z * 2.0,
+            );
+            let size = 0.08 + mob.rng.f32() * 0.1;
+            commands.spawn((
+                PbrBundle {
+                    mesh: fire_assets.fire_mesh.clone(),
+                    material: fire_assets.fire_mat.clone(),
+                    transform: Transform::from_translation(transform.translation + offset).with_scale(Vec3::splat(size)),
+                    ..default()
+                },
+                FireParticle {
+                    velocity: Vec3::Y * 1.5,
+                    lifetime: 0.6,
+                    age: 0.0,
+                    kind: ParticleKind::Fire,
+                },
+            ));
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::block::BlockType;
+use crate::camera::{MovementMode, Player};
+use crate::chunk::Chunk;
+use crate::chunk_codec::{decode_chunk, encode_chunk};
+use crate::physics::{Grounded, JUMP_VELOCITY};
+use bevy::prelude::*;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+pub const DEFAULT_PORT: u16 = 7777;
+pub const PROTOCOL_VERSION: u32 = 4;
+
+// Frames larger than this mean a corrupt or hostile stream
+const MAX_FRAME: usize = 1 << 20;
+
+/// One tick of player input, as sent by the client and replayed by both
+/// sides. `velocity` is the velocity the client's movement code wanted
+/// before the tick ran; the server only trusts it within limits. The
+/// movement mode is not part of it: the server decides that.
+#[derive(Clone, Copy, Debug)]
+pub struct InputFrame {
+    pub seq: u32,
+    pub velocity: Vec3,
+    pub crouch: bool,
+}
+
+pub enum ClientMessage {
+    Hello { version: u32 },
+    RequestChunk(IVec3),
+    Input(InputFrame),
+    SetBlock { pos: IVec3, block: BlockType },
+    Chat(String),
+}
+
+pub enum ServerMessage {
+    Welcome { id: u32, seed: u32, spawn: Vec3 },
+    ChunkData(Arc<Chunk>),
+    BlockChanged { pos: IVec3, block: BlockType },
+    /// Where the server put this client's player after input `seq`
+    PlayerState { seq: u32, position: Vec3, velocity: Vec3, grounded: bool, mode: MovementMode },
+    /// Another player's eye position
+    PlayerMoved { id: u32, position: Vec3, crouching: bool },
+    PlayerLeft { id: u32 },
+    /// A chat line from player `id`, sent to everyone including them
+    Chat { id: u32, text: String },
+}
+
+/// Applies an input to a player's velocity the way the server does, so
+/// client-side prediction replays it identically. Speeds are capped at
+/// what the player's current mode allows.
+pub fn apply_input(player: &Player, velocity: &mut Vec3, grounded: &Grounded, input: &InputFrame) {
+    let mode_speed = player.mode_speed();
+    let max_speed = mode_speed * player.sprint_multiplier;
+    let horizontal = Vec2::new(input.velocity.x, input.velocity.z).clamp_length_max(max_speed);
+    velocity.x = horizontal.x;
+    velocity.z = horizontal.y;
+
+    if player.mode != MovementMode::Survival {
+        velocity.y = input.velocity.y.clamp(-mode_speed, mode_speed);
+    } else if grounded.0 && input.velocity.y > velocity.y + 0.5 {
+        // The client jumped this tick
+        velocity.y = input.velocity.y.min(JUMP_VELOCITY);
+    }
+}
+
+fn mode_id(mode: MovementMode) -> u8 {
+    match mode {
+        MovementMode::Survival => 0,
+        MovementMode::Creative => 1,
+        MovementMode::Spectator => 2,
+    }
+}
+
+fn mode_from_id(id: u8) -> Option<MovementMode> {
+    match id {
+        0 => Some(MovementMode::Survival),
+        1 => Some(MovementMode::Creative),
+        2 => Some(MovementMode::Spectator),
+        _ => None,
+    }
+}
+
+/// Little-endian writers for message bodies.
+#[derive(Default)]
+pub struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+    pub fn u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    pub fn i32(&mut self, v: i32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    pub fn f32(&mut self, v: f32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    pub fn vec3(&mut self, v: Vec3) {
+        self.f32(v.x);
+        self.f32(v.y);
+        self.f32(v.z);
+    }
+    pub fn ivec3(&mut self, v: IVec3) {
+        self.i32(v.x);
+        self.i32(v.y);
+        self.i32(v.z);
+    }
+    pub fn block(&mut self, block: BlockType) {
+        self.u8(block.id());
+    }
+    /// Length-prefixed bytes
+    pub fn blob(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.bytes.extend_from_slice(bytes);
+    }
+    pub fn string(&mut self, text: &str) {
+        self.blob(text.as_bytes());
+    }
+}
+
+/// Reads a message body back; every getter returns `None` past the end.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*head)
+    }
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|b| b[0])
+    }
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+    pub fn i32(&mut self) -> Option<i32> {
+        self.take().map(i32::from_le_bytes)
+    }
+    pub fn f32(&mut self) -> Option<f32> {
+        self.take().map(f32::from_le_bytes)
+    }
+    pub fn vec3(&mut self) -> Option<Vec3> {
+        Some(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+    pub fn ivec3(&mut self) -> Option<IVec3> {
+        Some(IVec3::new(self.i32()?, self.i32()?, self.i32()?))
+    }
+    pub fn block(&mut self) -> Option<BlockType> {
+        BlockType::from_id(self.u8()?)
+    }
+    pub fn blob(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(head)
+    }
+    pub fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.blob()?.to_vec()).ok()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// A message that can cross the wire.
+pub trait Message: Sized {
+    fn write(&self, out: &mut Writer);
+    fn read(input: &mut Reader) -> Option<Self>;
+}
+
+impl Message for ClientMessage {
+    fn write(&self, out: &mut Writer) {
+        match self {
+            ClientMessage::Hello { version } => {
+                out.u8(0);
+                out.u32(*version);
+            }
+            ClientMessage::RequestChunk(pos) => {
+                out.u8(1);
+                out.ivec3(*pos);
+            }
+            ClientMessage::Input(input) => {
+                out.u8(2);
+                out.u32(input.seq);
+                out.vec3(input.velocity);
+                out.u8(input.crouch as u8);
+            }
+            ClientMessage::SetBlock { pos, block } => {
+                out.u8(3);
+                out.ivec3(*pos);
+                out.block(*block);
+            }
+            ClientMessage::Chat(text) => {
+                out.u8(4);
+                out.string(text);
+            }
+        }
+    }
+
+    fn read(input: &mut Reader) -> Option<Self> {
+        Some(match input.u8()? {
+            0 => ClientMessage::Hello { version: input.u32()? },
+            1 => ClientMessage::RequestChunk(input.ivec3()?),
+            2 => ClientMessage::Input(InputFrame {
+                seq: input.u32()?,
+                velocity: input.vec3()?,
+                crouch: input.u8()? != 0,
+            }),
+            3 => ClientMessage::SetBlock {
+                pos: input.ivec3()?,
+                block: input.block()?,
+            },
+            4 => ClientMessage::Chat(input.string()?),
+            _ => return None,
+        })
+    }
+}
+
+impl Message for ServerMessage {
+    fn write(&self, out: &mut Writer) {
+        match self {
+            ServerMessage::Welcome { id, seed, spawn } => {
+                out.u8(0);
+                out.u32(*id);
+                out.u32(*seed);
+                out.vec3(*spawn);
+            }
+            ServerMessage::ChunkData(chunk) => {
+                out.u8(1);
+                out.blob(&encode_chunk(chunk));
+            }
+            ServerMessage::BlockChanged { pos, block } => {
+                out.u8(2);
+                out.ivec3(*pos);
+                out.block(*block);
+            }
+            ServerMessage::PlayerState { seq, position, velocity, grounded, mode } => {
+                out.u8(3);
+                out.u32(*seq);
+                out.vec3(*position);
+                out.vec3(*velocity);
+                out.u8(*grounded as u8);
+                out.u8(mode_id(*mode));
+            }
+            ServerMessage::PlayerMoved { id, position, crouching } => {
+                out.u8(4);
+                out.u32(*id);
+                out.vec3(*position);
+                out.u8(*crouching as u8);
+            }
+            ServerMessage::PlayerLeft { id } => {
+                out.u8(5);
+                out.u32(*id);
+            }
+            ServerMessage::Chat { id, text } => {
+                out.u8(6);
+                out.u32(*id);
+                out.string(text);
+            }
+        }
+    }
+
+    fn read(input: &mut Reader) -> Option<Self> {
+        Some(match input.u8()? {
+            0 => ServerMessage::Welcome {
+                id: input.u32()?,
+                seed: input.u32()?,
+                spawn: input.vec3()?,
+            },
+            1 => ServerMessage::ChunkData(Arc::new(decode_chunk(input.blob()?).ok()?)),
+            2 => ServerMessage::BlockChanged {
+                pos: input.ivec3()?,
+                block: input.block()?,
+            },
+            3 => ServerMessage::PlayerState {
+                seq: input.u32()?,
+                position: input.vec3()?,
+                velocity: input.vec3()?,
+                grounded: input.u8()? != 0,
+                mode: mode_from_id(input.u8()?)?,
+            },
+            4 => ServerMessage::PlayerMoved {
+                id: input.u32()?,
+                position: input.vec3()?,
+                crouching: input.u8()? != 0,
+            },
+            5 => ServerMessage::PlayerLeft { id: input.u32()? },
+            6 => ServerMessage::Chat {
+                id: input.u32()?,
+                text: input.string()?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// A non-blocking TCP stream carrying length-prefixed messages. Sends are
+/// queued until `flush`; `receive` returns whatever complete messages have
+/// arrived so far.
+pub struct Connection {
+    stream: TcpStream,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+    /// Set once the peer hung up or sent something unreadable
+    pub closed: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            inbox: Vec::new(),
+            outbox: Vec::new(),
+            closed: false,
+        })
+    }
+
+    pub fn send(&mut self, message: &impl Message) {
+        let mut body = Writer::default();
+        message.write(&mut body);
+        self.outbox.extend_from_slice(&(body.bytes.len() as u32).to_le_bytes());
+        self.outbox.extend_from_slice(&body.bytes);
+    }
+
+    pub fn flush(&mut self) {
+        while !self.outbox.is_empty() && !self.closed {
+            match self.stream.write(&self.outbox) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outbox.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    pub fn receive<M: Message>(&mut self) -> Vec<M> {
+        let mut buf = [0u8; 16 * 1024];
+        while !self.closed {
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.inbox.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => self.closed = true,
+            }
+        }
+
+        let mut messages = Vec::new();
+        let mut start = 0;
+        while let Some(len_bytes) = self.inbox.get(start..start + 4) {
+            let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+            if len > MAX_FRAME {
+                self.closed = true;
+                break;
+            }
+            let Some(body) = self.inbox.get(start + 4..start + 4 + len) else { break };
+            let mut reader = Reader::new(body);
+            match M::read(&mut reader) {
+                Some(message) if reader.is_empty() => messages.push(message),
+                _ => {
+                    self.closed = true;
+                    break;
+                }
+            }
+            start += 4 + len;
+        }
+        self.inbox.drain(..start);
+        messages
+    }
+}
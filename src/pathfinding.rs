@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+// Costs are in tenths of a block so the open list can use integers
+const STEP_COST: u32 = 10;
+const JUMP_COST: u32 = 5; // per block climbed
+const DROP_COST: u32 = 2; // per block fallen
+
+const DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// What a walker can fit through and survive. Positions handed to and
+/// returned from [`find_path`] are the block cells the walker's feet occupy.
+#[derive(Clone, Copy)]
+pub struct PathRules {
+    /// Body height in whole blocks
+    pub height: i32,
+    /// Highest ledge it can jump onto
+    pub max_jump: i32,
+    /// Furthest it is willing to fall
+    pub max_drop: i32,
+}
+
+fn clear(solid: &impl Fn(i32, i32, i32) -> bool, x: i32, y_from: i32, y_to: i32, z: i32) -> bool {
+    (y_from..y_to).all(|y| !solid(x, y, z))
+}
+
+/// Feet fit in `cell` with room above and something solid underneath.
+pub fn can_stand(solid: &impl Fn(i32, i32, i32) -> bool, cell: IVec3, height: i32) -> bool {
+    solid(cell.x, cell.y - 1, cell.z) && clear(solid, cell.x, cell.y, cell.y + height, cell.z)
+}
+
+/// Where a walker standing at `from` ends up stepping one block along
+/// `dir`: up onto a ledge, level, or down off an edge.
+fn step(solid: &impl Fn(i32, i32, i32) -> bool, from: IVec3, dir: IVec3, rules: &PathRules) -> Option<(IVec3, u32)> {
+    let (x, z) = (from.x + dir.x, from.z + dir.z);
+
+    for rise in 1..=rules.max_jump {
+        let target = IVec3::new(x, from.y + rise, z);
+        // Needs headroom above the current cell for the jump itself
+        if can_stand(solid, target, rules.height) && clear(solid, from.x, from.y + rules.height, target.y + rules.height, from.z) {
+            return Some((target, STEP_COST + JUMP_COST * rise as u32));
+        }
+    }
+
+    // Walk forward, then fall to the first floor below
+    if !clear(solid, x, from.y, from.y + rules.height, z) {
+        return None;
+    }
+    for fall in 0..=rules.max_drop {
+        let y = from.y - fall;
+        if solid(x, y - 1, z) {
+            return Some((IVec3::new(x, y, z), STEP_COST + DROP_COST * fall as u32));
+        }
+    }
+    None
+}
+
+fn heuristic(a: IVec3, b: IVec3) -> u32 {
+    ((a.x - b.x).unsigned_abs() + (a.z - b.z).unsigned_abs()) * STEP_COST
+}
+
+/// A* over the voxel grid from `start` to `goal`, both feet cells. Gives up
+/// after expanding `max_nodes` cells. The returned path excludes `start`
+/// and ends at `goal`, so it is empty when they are the same cell.
+///
+/// Blocks are read through `solid(x, y, z)`, so any block source works —
+/// loaded chunks in game, a hand-built `Chunk` in tests.
+pub fn find_path(
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    start: IVec3,
+    goal: IVec3,
+    rules: &PathRules,
+    max_nodes: usize,
+) -> Option<Vec<IVec3>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+    if !can_stand(solid, goal, rules.height) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    // Cells already expanded; the heap may still hold older, costlier
+    // entries for them
+    let mut closed: HashSet<IVec3> = HashSet::new();
+    let mut cost: HashMap<IVec3, u32> = HashMap::new();
+    let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+    cost.insert(start, 0);
+    open.push(Reverse((heuristic(start, goal), start.to_array())));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, cell))) = open.pop() {
+        let cell = IVec3::from_array(cell);
+        if cell == goal {
+            let mut path = vec![goal];
+            let mut at = goal;
+            while let Some(&prev) = came_from.get(&at) {
+                if prev == start {
+                    break;
+                }
+                path.push(prev);
+                at = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if !closed.insert(cell) {
+            continue;
+        }
+
+        expanded += 1;
+        if expanded > max_nodes {
+            return None;
+        }
+
+        let here = cost[&cell];
+        for dir in DIRECTIONS {
+            let Some((next, step_cost)) = step(solid, cell, dir, rules) else { continue };
+            if closed.contains(&next) {
+                continue;
+            }
+            let new_cost = here + step_cost;
+            if cost.get(&next).is_some_and(|&c| c <= new_cost) {
+                continue;
+            }
+            cost.insert(next, new_cost);
+            came_from.insert(next, cell);
+            open.push(Reverse((new_cost + heuristic(next, goal), next.to_array())));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockType;
+    use crate::chunk::{CHUNK_SIZE, Chunk};
+
+    const FLOOR: i32 = 10;
+    const RULES: PathRules = PathRules {
+        height: 2,
+        max_jump: 1,
+        max_drop: 3,
+    };
+
+    /// A chunk of stone up to `FLOOR`, with `blocks` placed on top.
+    fn chunk(blocks: &[(i32, i32, i32, BlockType)]) -> Chunk {
+        let mut chunk = Chunk::new(IVec3::ZERO);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..FLOOR as usize {
+                    chunk.set_block(x, y, z, BlockType::Stone);
+                }
+            }
+        }
+        for &(x, y, z, block) in blocks {
+            chunk.set_block(x as usize, y as usize, z as usize, block);
+        }
+        chunk
+    }
+
+    /// Reads the chunk as the world, with nothing outside it.
+    fn solid(chunk: &Chunk) -> impl Fn(i32, i32, i32) -> bool + '_ {
+        |x, y, z| {
+            x >= 0 && y >= 0 && z >= 0 && chunk.get_block(x as usize, y as usize, z as usize).is_collidable()
+        }
+    }
+
+    /// Every step moves one block sideways and stays within the rules.
+    fn assert_walkable(start: IVec3, path: &[IVec3]) {
+        let mut at = start;
+        for &next in path {
+            let d = next - at;
+            assert_eq!(d.x.abs() + d.z.abs(), 1, "{} to {} is not one step", at, next);
+            assert!((-RULES.max_drop..=RULES.max_jump).contains(&d.y), "{} to {} climbs {}", at, next, d.y);
+            at = next;
+        }
+    }
+
+    #[test]
+    fn same_cell_is_an_empty_path() {
+        let chunk = chunk(&[]);
+        let cell = IVec3::new(3, FLOOR, 3);
+        assert_eq!(find_path(&solid(&chunk), cell, cell, &RULES, 100), Some(Vec::new()));
+    }
+
+    #[test]
+    fn open_ground_takes_the_shortest_route() {
+        let chunk = chunk(&[]);
+        let start = IVec3::new(1, FLOOR, 1);
+        let goal = IVec3::new(6, FLOOR, 4);
+        let path = find_path(&solid(&chunk), start, goal, &RULES, 1000).unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path.last(), Some(&goal));
+        assert_walkable(start, &path);
+    }
+
+    #[test]
+    fn blocked_goal_has_no_path() {
+        let chunk = chunk(&[(6, FLOOR + 1, 4, BlockType::Stone)]);
+        let start = IVec3::new(1, FLOOR, 1);
+        let goal = IVec3::new(6, FLOOR, 4);
+        assert_eq!(find_path(&solid(&chunk), start, goal, &RULES, 1000), None);
+    }
+
+    #[test]
+    fn walks_around_a_wall() {
+        // Wall along x = 5 with a single gap at z = 12
+        let wall: Vec<_> = (0..CHUNK_SIZE as i32)
+            .filter(|&z| z != 12)
+            .flat_map(|z| (FLOOR..FLOOR + 3).map(move |y| (5, y, z, BlockType::Stone)))
+            .collect();
+        let chunk = chunk(&wall);
+        let start = IVec3::new(2, FLOOR, 2);
+        let goal = IVec3::new(8, FLOOR, 2);
+        let path = find_path(&solid(&chunk), start, goal, &RULES, 1000).unwrap();
+        assert!(path.contains(&IVec3::new(5, FLOOR, 12)), "did not use the gap");
+        assert_eq!(path.len(), 6 + 2 * 10);
+        assert_walkable(start, &path);
+    }
+
+    #[test]
+    fn climbs_a_ledge_and_drops_off_it() {
+        // A wall-to-wall step one block high, and a pit past it
+        let mut blocks: Vec<_> = (0..CHUNK_SIZE as i32).map(|z| (5, FLOOR, z, BlockType::Stone)).collect();
+        for z in 0..CHUNK_SIZE as i32 {
+            for y in FLOOR - 3..FLOOR {
+                blocks.push((9, y, z, BlockType::Air));
+            }
+        }
+        let chunk = chunk(&blocks);
+        let solid = solid(&chunk);
+
+        let start = IVec3::new(2, FLOOR, 3);
+        let path = find_path(&solid, start, IVec3::new(9, FLOOR - 3, 3), &RULES, 1000).unwrap();
+        assert!(path.contains(&IVec3::new(5, FLOOR + 1, 3)), "did not climb onto the step");
+        assert_walkable(start, &path);
+
+        // Back out of the pit is three blocks up, more than a jump
+        assert_eq!(find_path(&solid, IVec3::new(9, FLOOR - 3, 3), start, &RULES, 1000), None);
+    }
+}
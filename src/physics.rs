@@ -1,8 +1,9 @@
+use bevy::app::RunFixedMainLoop;
 use bevy::prelude::*;
-use crate::camera::Player;
-use crate::world::World as GameWorld;
-use crate::chunk::{CHUNK_SIZE, CHUNK_HEIGHT};
-use crate::block::BlockType;
+use crate::actions::{Action, ActionState};
+use crate::camera::{MovementMode, Player};
+use crate::console::{CommandResult, ConsoleCommandsExt, parse_coord};
+use crate::voxel_world::VoxelWorld;
 
 #[derive(Component)]
 pub struct Velocity(pub Vec3);
@@ -10,65 +11,184 @@ pub struct Velocity(pub Vec3);
 #[derive(Component)]
 pub struct Grounded(pub bool);
 
-const GRAVITY: f32 = -28.0;
-const JUMP_VELOCITY: f32 = 9.0;
+/// Fastest downward speed the body hit the ground with since it was last
+/// read; consumed by fall damage.
+#[derive(Component, Default)]
+pub struct FallImpact(pub f32);
+
+/// Generic voxel-colliding body for drops, particles and mobs. Its
+/// `PhysicsPosition` is the centre of an axis-aligned box of `half_extents`.
+/// Needs `Velocity`, `Grounded` and `PhysicsPosition` alongside it.
+#[derive(Component, Clone, Copy)]
+pub struct VoxelBody {
+    pub half_extents: Vec3,
+    pub gravity_scale: f32,
+    /// Fraction of speed kept (and reversed) when hitting a block
+    pub bounciness: f32,
+    /// Horizontal speed lost per second while resting on the ground
+    pub friction: f32,
+}
+
+impl VoxelBody {
+    pub fn new(half_extents: Vec3) -> Self {
+        Self {
+            half_extents,
+            gravity_scale: 1.0,
+            bounciness: 0.0,
+            friction: 8.0,
+        }
+    }
+
+    pub fn with_bounciness(mut self, bounciness: f32) -> Self {
+        self.bounciness = bounciness;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+}
+
+/// Bundle of everything a `VoxelBody` needs to be simulated.
+#[derive(Bundle)]
+pub struct VoxelBodyBundle {
+    pub body: VoxelBody,
+    pub position: PhysicsPosition,
+    pub velocity: Velocity,
+    pub grounded: Grounded,
+}
+
+impl VoxelBodyBundle {
+    pub fn new(body: VoxelBody, position: Vec3, velocity: Vec3) -> Self {
+        Self {
+            body,
+            position: PhysicsPosition::new(position),
+            velocity: Velocity(velocity),
+            grounded: Grounded(false),
+        }
+    }
+}
+
+/// Authoritative position of a physics body, advanced on the fixed timestep.
+/// `Transform` is only the rendered position, interpolated between ticks;
+/// move bodies by writing here, not to their transform.
+#[derive(Component)]
+pub struct PhysicsPosition {
+    pub previous: Vec3,
+    pub current: Vec3,
+}
+
+impl PhysicsPosition {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            previous: position,
+            current: position,
+        }
+    }
+
+    /// Moves without interpolating through the space in between.
+    pub fn teleport(&mut self, position: Vec3) {
+        self.previous = position;
+        self.current = position;
+    }
+}
+
+pub const GRAVITY: f32 = -28.0;
+pub const JUMP_VELOCITY: f32 = 9.0;
 const PLAYER_WIDTH: f32 = 0.3;
 pub const PLAYER_HEIGHT: f32 = 2.5;
+pub const CROUCH_HEIGHT: f32 = 2.0;
+// Ledges this high are climbed automatically; step assist raises it to a full block
+pub const STEP_HEIGHT: f32 = 0.6;
+pub const STEP_ASSIST_HEIGHT: f32 = 1.0;
+
+pub const PHYSICS_HZ: f64 = 60.0;
+// Furthest a body moves along any axis in one collision pass
+const MAX_SUBSTEP: f32 = 0.4;
+const MAX_SUBSTEPS: u32 = 16;
+// Bounces slower than this come to rest instead of jittering forever
+const MIN_BOUNCE_SPEED: f32 = 1.0;
+// Bodies that fall this far below the world are removed
+const KILL_DEPTH: f32 = -32.0;
+
+/// The fixed-tick player movement step, for systems that must see the
+/// player right before or after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerPhysicsSet;
 
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (apply_physics, handle_jump).chain());
+        app.insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ))
+            .add_systems(
+                FixedUpdate,
+                (store_previous_positions, apply_physics.in_set(PlayerPhysicsSet), step_voxel_bodies).chain(),
+            )
+            .add_systems(
+                RunFixedMainLoop,
+                interpolate_transforms.after(bevy::time::run_fixed_main_schedule),
+            )
+            .add_systems(Update, handle_jump)
+            .add_console_command("tp", "<x> <y> <z>", "Teleport your feet to a position (~ for relative)", tp_command);
     }
 }
 
-fn is_solid_at(
-    world: &GameWorld,
-    chunks: &Query<&crate::chunk::Chunk>,
-    bx: i32, by: i32, bz: i32,
-) -> bool {
-    if by < 0 || by >= CHUNK_HEIGHT as i32 {
-        return false;
-    }
-    let chunk_x = bx.div_euclid(CHUNK_SIZE as i32);
-    let chunk_z = bz.div_euclid(CHUNK_SIZE as i32);
-    let chunk_pos = IVec3::new(chunk_x, 0, chunk_z);
-
-    let Some(&entity) = world.chunks.get(&chunk_pos) else {
-        return false;
+fn tp_command(
+    In(args): In<Vec<String>>,
+    mut query: Query<(&Player, &mut PhysicsPosition, &mut Velocity)>,
+) -> CommandResult {
+    let Ok((player, mut position, mut velocity)) = query.get_single_mut() else {
+        return Err("No player to teleport".to_string());
     };
-    let Ok(chunk) = chunks.get(entity) else {
-        return false;
-    };
-
-    let lx = bx.rem_euclid(CHUNK_SIZE as i32) as usize;
-    let lz = bz.rem_euclid(CHUNK_SIZE as i32) as usize;
-    let block = chunk.get_block(lx, by as usize, lz as usize);
-    block.is_solid() && !matches!(block, BlockType::Water)
+    // Coordinates are the feet, matching the HUD
+    let feet = position.current - Vec3::Y * player.height();
+    let target = Vec3::new(
+        parse_coord(&args, 0, "x", feet.x)?,
+        parse_coord(&args, 1, "y", feet.y)?,
+        parse_coord(&args, 2, "z", feet.z)?,
+    );
+    position.teleport(target + Vec3::Y * player.height());
+    velocity.0 = Vec3::ZERO;
+    Ok(format!("Teleported to {:.1} {:.1} {:.1}", target.x, target.y, target.z))
 }
 
 /// Check if the player AABB overlaps any solid block.
-/// The AABB is defined by center (x, feet_y..feet_y+PLAYER_HEIGHT, z)
+/// The AABB is defined by center (x, feet_y..feet_y+height, z)
 /// with half-width PLAYER_WIDTH on X and Z.
-fn aabb_overlaps_solid(
-    world: &GameWorld,
-    chunks: &Query<&crate::chunk::Chunk>,
+///
+/// The movement helpers below take the world as a `solid(x, y, z)` sampler
+/// so they can run against any block source, not just loaded chunks.
+pub fn aabb_overlaps_solid(
+    solid: &impl Fn(i32, i32, i32) -> bool,
     x: f32,
     feet_y: f32,
     z: f32,
+    height: f32,
 ) -> bool {
-    let min_x = (x - PLAYER_WIDTH + 0.001).floor() as i32;
-    let max_x = (x + PLAYER_WIDTH - 0.001).floor() as i32;
-    let min_y = feet_y.floor() as i32;
-    let max_y = (feet_y + PLAYER_HEIGHT - 0.001).floor() as i32;
-    let min_z = (z - PLAYER_WIDTH + 0.001).floor() as i32;
-    let max_z = (z + PLAYER_WIDTH - 0.001).floor() as i32;
+    box_overlaps_solid(
+        solid,
+        Vec3::new(x - PLAYER_WIDTH, feet_y, z - PLAYER_WIDTH),
+        Vec3::new(x + PLAYER_WIDTH, feet_y + height, z + PLAYER_WIDTH),
+    )
+}
 
-    for bx in min_x..=max_x {
-        for by in min_y..=max_y {
-            for bz in min_z..=max_z {
-                if is_solid_at(world, chunks, bx, by, bz) {
+/// Check if the box `min..max` overlaps any solid block. Faces exactly on a
+/// block boundary do not count as touching the block beyond it.
+pub fn box_overlaps_solid(solid: &impl Fn(i32, i32, i32) -> bool, min: Vec3, max: Vec3) -> bool {
+    let lo = (min + Vec3::splat(0.001)).floor().as_ivec3();
+    let hi = (max - Vec3::splat(0.001)).floor().as_ivec3();
+
+    for bx in lo.x..=hi.x {
+        for by in lo.y..=hi.y {
+            for bz in lo.z..=hi.z {
+                if solid(bx, by, bz) {
                     return true;
                 }
             }
@@ -77,92 +197,299 @@ fn aabb_overlaps_solid(
     false
 }
 
+/// Distance along a ray (with normalized `dir`) to where it enters the box
+/// `min..max`, or `None` if it misses. A ray starting inside hits at 0.
+pub fn ray_box_distance(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<f32> {
+    let inv = dir.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let near = t1.min(t2).max_element();
+    let far = t1.max(t2).min_element();
+    (far >= near.max(0.0)).then_some(near.max(0.0))
+}
+
+/// Whether any block under the player's footprint would hold them up.
+pub fn has_support(solid: &impl Fn(i32, i32, i32) -> bool, x: f32, feet_y: f32, z: f32) -> bool {
+    let foot_block_y = (feet_y - 0.001).floor() as i32;
+    let min_x = (x - PLAYER_WIDTH + 0.001).floor() as i32;
+    let max_x = (x + PLAYER_WIDTH - 0.001).floor() as i32;
+    let min_z = (z - PLAYER_WIDTH + 0.001).floor() as i32;
+    let max_z = (z + PLAYER_WIDTH - 0.001).floor() as i32;
+
+    (min_x..=max_x).any(|bx| (min_z..=max_z).any(|bz| solid(bx, foot_block_y, bz)))
+}
+
+/// Per-frame movement rules for the horizontal axis resolvers.
+#[derive(Clone, Copy)]
+pub struct MoveRules {
+    pub height: f32,
+    /// Ledges up to this many blocks high are climbed without jumping
+    pub step_height: f32,
+    /// Refuse moves that would leave the player without ground underfoot
+    pub sneak: bool,
+}
+
+/// Copies the last tick's position aside so rendering can blend toward the new one.
+fn store_previous_positions(mut query: Query<&mut PhysicsPosition>) {
+    for mut position in query.iter_mut() {
+        position.previous = position.current;
+    }
+}
+
 fn apply_physics(
     time: Res<Time>,
-    world: Res<GameWorld>,
-    chunks: Query<&crate::chunk::Chunk>,
-    mut query: Query<(&mut Transform, &mut Velocity, &mut Grounded), With<Player>>,
+    actions: Res<ActionState>,
+    voxels: VoxelWorld,
+    mut query: Query<(&mut Player, &mut PhysicsPosition, &mut Velocity, &mut Grounded, &mut FallImpact)>,
 ) {
+    // Runs in FixedUpdate, so this is always the fixed timestep
     let dt = time.delta_seconds();
+    let solid = |x: i32, y: i32, z: i32| voxels.is_solid(IVec3::new(x, y, z));
 
-    for (mut transform, mut velocity, mut grounded) in query.iter_mut() {
-        velocity.0.y += GRAVITY * dt;
+    for (mut player, mut position, mut velocity, mut grounded, mut impact) in query.iter_mut() {
+        // Hold still until the terrain underneath has been generated
+        if player.mode != MovementMode::Spectator && !voxels.is_loaded(position.current.floor().as_ivec3()) {
+            continue;
+        }
 
-        let pos = transform.translation;
-        let feet_y = pos.y - PLAYER_HEIGHT;
+        let wants_crouch = actions.pressed(Action::Crouch);
+        step_player(
+            &solid,
+            &mut player,
+            &mut position.current,
+            &mut velocity.0,
+            &mut grounded,
+            &mut impact.0,
+            wants_crouch,
+            dt,
+        );
+    }
+}
+
+/// Advances a player by one fixed tick: crouching, gravity and substepped
+/// collision against `solid`. `position` is the eye position. The local
+/// player, the server's remote players and client prediction all share it,
+/// so given the same input they end up in the same place.
+#[allow(clippy::too_many_arguments)]
+pub fn step_player(
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    player: &mut Player,
+    position: &mut Vec3,
+    velocity: &mut Vec3,
+    grounded: &mut Grounded,
+    impact: &mut f32,
+    wants_crouch: bool,
+    dt: f32,
+) {
+    if player.mode == MovementMode::Spectator {
+        // Noclip: no gravity and no collision at all
+        *position += *velocity * dt;
+        grounded.0 = false;
+        player.crouching = false;
+        return;
+    }
+
+    // Crouching keeps the feet in place and lowers the eye
+    let wants_crouch = wants_crouch && player.mode == MovementMode::Survival;
+    let feet_y = position.y - player.height();
+    if wants_crouch != player.crouching {
+        let stand_blocked = !wants_crouch
+            && aabb_overlaps_solid(solid, position.x, feet_y, position.z, PLAYER_HEIGHT);
+        if !stand_blocked {
+            player.crouching = wants_crouch;
+            position.y = feet_y + player.height();
+        }
+    }
+
+    // Split the tick so no axis moves more than MAX_SUBSTEP per pass;
+    // the resolvers only check the layer they move into, so this keeps
+    // fast falls from tunnelling through a block
+    let fall_speed = if player.mode == MovementMode::Survival {
+        (velocity.y + GRAVITY * dt).abs()
+    } else {
+        velocity.y.abs()
+    };
+    let travel = velocity.x.abs().max(velocity.z.abs()).max(fall_speed) * dt;
+    let substeps = ((travel / MAX_SUBSTEP).ceil() as u32).clamp(1, MAX_SUBSTEPS);
+    let sub_dt = dt / substeps as f32;
+
+    for _ in 0..substeps {
+        if player.mode == MovementMode::Survival {
+            velocity.y += GRAVITY * sub_dt;
+        }
+
+        let pos = *position;
+        let feet_y = pos.y - player.height();
+        let rules = MoveRules {
+            height: player.height(),
+            step_height: if player.mode == MovementMode::Survival && grounded.0 {
+                player.step_height()
+            } else {
+                0.0
+            },
+            sneak: player.crouching && grounded.0,
+        };
 
         // --- Y axis ---
-        let desired_feet_y = feet_y + velocity.0.y * dt;
-        let new_feet_y = resolve_y(
-            &world, &chunks,
+        let desired_feet_y = feet_y + velocity.y * sub_dt;
+        let mut new_feet_y = resolve_y(
+            solid,
             pos.x, desired_feet_y, pos.z,
-            &mut velocity.0.y, &mut grounded,
+            rules.height,
+            &mut velocity.y, grounded, impact,
         );
 
         // --- X axis ---
-        let desired_x = pos.x + velocity.0.x * dt;
-        let new_x = resolve_axis(
-            &world, &chunks,
-            desired_x, new_feet_y, pos.z,
-            pos.x, new_feet_y, pos.z,
+        let desired_x = pos.x + velocity.x * sub_dt;
+        let new_x;
+        (new_x, new_feet_y) = resolve_axis(
+            solid,
+            Vec3::new(pos.x, new_feet_y, pos.z),
+            desired_x,
             true,
-            &mut velocity.0.x,
+            rules,
+            &mut velocity.x,
         );
 
         // --- Z axis ---
-        let desired_z = pos.z + velocity.0.z * dt;
-        let new_z = resolve_axis(
-            &world, &chunks,
-            new_x, new_feet_y, desired_z,
-            new_x, new_feet_y, pos.z,
+        let desired_z = pos.z + velocity.z * sub_dt;
+        let new_z;
+        (new_z, new_feet_y) = resolve_axis(
+            solid,
+            Vec3::new(new_x, new_feet_y, pos.z),
+            desired_z,
             false,
-            &mut velocity.0.z,
+            rules,
+            &mut velocity.z,
         );
 
-        transform.translation = Vec3::new(new_x, new_feet_y + PLAYER_HEIGHT, new_z);
+        *position = Vec3::new(new_x, new_feet_y + rules.height, new_z);
+    }
+
+    // Flying down onto the ground lands the player
+    if player.mode == MovementMode::Creative && grounded.0 {
+        player.mode = MovementMode::Survival;
+    }
+}
+
+fn step_voxel_bodies(
+    mut commands: Commands,
+    time: Res<Time>,
+    voxels: VoxelWorld,
+    mut query: Query<(Entity, &VoxelBody, &mut PhysicsPosition, &mut Velocity, &mut Grounded)>,
+) {
+    let dt = time.delta_seconds();
+    let solid = |x: i32, y: i32, z: i32| voxels.is_solid(IVec3::new(x, y, z));
+
+    for (entity, body, mut position, mut velocity, mut grounded) in query.iter_mut() {
+        let mut center = position.current;
+        grounded.0 = move_body(&solid, body, &mut center, &mut velocity.0, dt);
+        position.current = center;
+
+        if center.y < KILL_DEPTH {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Advances one body by `dt`: gravity, substepped per-axis collision with
+/// bounce, and ground friction. Returns whether it ended up on the ground.
+pub fn move_body(
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    body: &VoxelBody,
+    center: &mut Vec3,
+    velocity: &mut Vec3,
+    dt: f32,
+) -> bool {
+    velocity.y += GRAVITY * body.gravity_scale * dt;
+
+    let travel = velocity.abs().max_element() * dt;
+    let substeps = ((travel / MAX_SUBSTEP).ceil() as u32).clamp(1, MAX_SUBSTEPS);
+    let sub_dt = dt / substeps as f32;
+    let half = body.half_extents;
+
+    let mut grounded = false;
+    for _ in 0..substeps {
+        // Y first so a body sliding along the ground is not caught on it
+        for axis in [1, 0, 2] {
+            let speed = velocity[axis];
+            if speed == 0.0 {
+                continue;
+            }
+            let mut moved = *center;
+            moved[axis] += speed * sub_dt;
+            if !box_overlaps_solid(solid, moved - half, moved + half) {
+                *center = moved;
+                continue;
+            }
+
+            // Rest flush against the face of the block that was hit
+            let snapped = if speed > 0.0 {
+                (moved[axis] + half[axis] - 0.001).floor() - half[axis]
+            } else {
+                (moved[axis] - half[axis] + 0.001).floor() + 1.0 + half[axis]
+            };
+            let mut flush = *center;
+            flush[axis] = snapped;
+            if !box_overlaps_solid(solid, flush - half, flush + half) {
+                *center = flush;
+            }
+
+            if axis == 1 && speed < 0.0 {
+                grounded = true;
+            }
+            let bounce = -speed * body.bounciness;
+            velocity[axis] = if bounce.abs() < MIN_BOUNCE_SPEED { 0.0 } else { bounce };
+        }
+    }
+
+    if grounded {
+        let keep = (1.0 - body.friction * dt).max(0.0);
+        velocity.x *= keep;
+        velocity.z *= keep;
+    }
+    grounded
+}
+
+/// Places rendered transforms between the last two physics ticks, so motion
+/// stays smooth whatever the frame rate.
+fn interpolate_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&PhysicsPosition, &mut Transform)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (position, mut transform) in query.iter_mut() {
+        transform.translation = position.previous.lerp(position.current, alpha);
     }
 }
 
 fn handle_jump(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&mut Velocity, &Grounded), With<Player>>,
+    actions: Res<ActionState>,
+    mut query: Query<(&Player, &mut Velocity, &Grounded)>,
 ) {
-    for (mut velocity, grounded) in query.iter_mut() {
-        if keyboard.just_pressed(KeyCode::Space) && grounded.0 {
+    for (player, mut velocity, grounded) in query.iter_mut() {
+        if actions.just_pressed(Action::Jump) && grounded.0 && player.mode == MovementMode::Survival {
             velocity.0.y = JUMP_VELOCITY;
         }
     }
 }
 
-fn resolve_y(
-    world: &GameWorld,
-    chunks: &Query<&crate::chunk::Chunk>,
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_y(
+    solid: &impl Fn(i32, i32, i32) -> bool,
     x: f32,
     new_feet_y: f32,
     z: f32,
+    height: f32,
     vel_y: &mut f32,
     grounded: &mut Grounded,
+    impact: &mut f32,
 ) -> f32 {
     if *vel_y <= 0.0 {
         // Moving down — check all corners at foot level
         let foot_block_y = (new_feet_y - 0.001).floor() as i32;
-        let min_x = (x - PLAYER_WIDTH + 0.001).floor() as i32;
-        let max_x = (x + PLAYER_WIDTH - 0.001).floor() as i32;
-        let min_z = (z - PLAYER_WIDTH + 0.001).floor() as i32;
-        let max_z = (z + PLAYER_WIDTH - 0.001).floor() as i32;
-
-        let mut hit = false;
-        for bx in min_x..=max_x {
-            for bz in min_z..=max_z {
-                if is_solid_at(world, chunks, bx, foot_block_y, bz) {
-                    hit = true;
-                    break;
-                }
-            }
-            if hit { break; }
-        }
-
-        if hit {
+        if has_support(solid, x, new_feet_y, z) {
+            *impact = impact.max(-*vel_y);
             grounded.0 = true;
             *vel_y = 0.0;
             return foot_block_y as f32 + 1.0;
@@ -170,7 +497,7 @@ fn resolve_y(
         grounded.0 = false;
     } else {
         // Moving up — check all corners at head level
-        let head_block_y = (new_feet_y + PLAYER_HEIGHT - 0.001).floor() as i32;
+        let head_block_y = (new_feet_y + height - 0.001).floor() as i32;
         let min_x = (x - PLAYER_WIDTH + 0.001).floor() as i32;
         let max_x = (x + PLAYER_WIDTH - 0.001).floor() as i32;
         let min_z = (z - PLAYER_WIDTH + 0.001).floor() as i32;
@@ -179,7 +506,7 @@ fn resolve_y(
         let mut hit = false;
         for bx in min_x..=max_x {
             for bz in min_z..=max_z {
-                if is_solid_at(world, chunks, bx, head_block_y, bz) {
+                if solid(bx, head_block_y, bz) {
                     hit = true;
                     break;
                 }
@@ -189,7 +516,7 @@ fn resolve_y(
 
         if hit {
             *vel_y = 0.0;
-            return head_block_y as f32 - PLAYER_HEIGHT;
+            return head_block_y as f32 - height;
         }
         grounded.0 = false;
     }
@@ -197,26 +524,44 @@ fn resolve_y(
     new_feet_y
 }
 
-/// Generic axis resolver — tries the desired position, falls back to
-/// stepping back 1 unit at a time until no overlap.
-fn resolve_axis(
-    world: &GameWorld,
-    chunks: &Query<&crate::chunk::Chunk>,
-    // desired full position
-    desired_x: f32, desired_feet_y: f32, desired_z: f32,
-    // safe fallback position (before this axis moved)
-    safe_x: f32, _safe_feet_y: f32, safe_z: f32,
+/// Moves along one horizontal axis from `current` (x, feet_y, z) to
+/// `desired`. Returns the new axis value and feet height: blocked moves stop
+/// and zero the velocity, low ledges are stepped onto, and sneaking refuses
+/// to walk off an edge.
+pub fn resolve_axis(
+    solid: &impl Fn(i32, i32, i32) -> bool,
+    current: Vec3,
+    desired: f32,
     is_x: bool,
+    rules: MoveRules,
     vel: &mut f32,
-) -> f32 {
-    // If desired position has no overlap — accept it
-    if !aabb_overlaps_solid(world, chunks, desired_x, desired_feet_y, desired_z) {
-        return if is_x { desired_x } else { desired_z };
+) -> (f32, f32) {
+    let (x, z) = if is_x { (desired, current.z) } else { (current.x, desired) };
+    let safe = if is_x { current.x } else { current.z };
+    let feet_y = current.y;
+
+    if !aabb_overlaps_solid(solid, x, feet_y, z, rules.height) {
+        if rules.sneak && !has_support(solid, x, feet_y, z) {
+            *vel = 0.0;
+            return (safe, feet_y);
+        }
+        return (desired, feet_y);
+    }
+
+    // Step up onto the top of the block in the way if it is low enough and
+    // there is headroom both here and at the destination
+    let step_top = feet_y.floor() + 1.0;
+    let rise = step_top - feet_y;
+    if rise <= rules.step_height
+        && !aabb_overlaps_solid(solid, current.x, step_top, current.z, rules.height)
+        && !aabb_overlaps_solid(solid, x, step_top, z, rules.height)
+    {
+        return (desired, step_top);
     }
 
     // Collision — stop velocity and return safe position
     *vel = 0.0;
-    if is_x { safe_x } else { safe_z }
+    (safe, feet_y)
 }
 
 fn _player_corners(x: f32, z: f32) -> [(i32, i32); 4] {
@@ -226,4 +571,157 @@ fn _player_corners(x: f32, z: f32) -> [(i32, i32); 4] {
         ((x - PLAYER_WIDTH).floor() as i32, (z + PLAYER_WIDTH).floor() as i32),
         ((x + PLAYER_WIDTH).floor() as i32, (z + PLAYER_WIDTH).floor() as i32),
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const DT: f32 = 1.0 / PHYSICS_HZ as f32;
+
+    /// Flat ground with its top at y = 0, plus `blocks`.
+    fn world(blocks: &[IVec3]) -> impl Fn(i32, i32, i32) -> bool {
+        let blocks: HashSet<IVec3> = blocks.iter().copied().collect();
+        move |x, y, z| y < 0 || blocks.contains(&IVec3::new(x, y, z))
+    }
+
+    struct Run {
+        feet: Vec3,
+        velocity: Vec3,
+        grounded: bool,
+        impact: f32,
+        highest_feet: f32,
+    }
+
+    /// Steps a player standing with its feet at `feet`, holding the
+    /// horizontal part of `velocity` every tick the way movement input does.
+    fn run_player(
+        solid: &impl Fn(i32, i32, i32) -> bool,
+        player: &mut Player,
+        feet: Vec3,
+        velocity: Vec3,
+        crouch: bool,
+        ticks: u32,
+    ) -> Run {
+        let mut position = feet + Vec3::Y * player.height();
+        let mut vel = velocity;
+        let mut grounded = Grounded(false);
+        let mut impact = 0.0;
+        let mut highest_feet = feet.y;
+        for _ in 0..ticks {
+            vel.x = velocity.x;
+            vel.z = velocity.z;
+            step_player(solid, player, &mut position, &mut vel, &mut grounded, &mut impact, crouch, DT);
+            highest_feet = highest_feet.max(position.y - player.height());
+        }
+        Run {
+            feet: position - Vec3::Y * player.height(),
+            velocity: vel,
+            grounded: grounded.0,
+            impact,
+            highest_feet,
+        }
+    }
+
+    #[test]
+    fn player_lands_on_the_ground() {
+        let solid = world(&[]);
+        let run = run_player(&solid, &mut Player::default(), Vec3::new(0.5, 5.0, 0.5), Vec3::ZERO, false, 120);
+        assert_eq!(run.feet.y, 0.0);
+        assert!(run.grounded);
+        assert_eq!(run.velocity.y, 0.0);
+        assert!(run.impact > 10.0, "impact {}", run.impact);
+    }
+
+    #[test]
+    fn player_slides_along_a_wall() {
+        let wall: Vec<IVec3> = (-2..10)
+            .flat_map(|z| (0..3).map(move |y| IVec3::new(2, y, z)))
+            .collect();
+        let solid = world(&wall);
+        let run = run_player(&solid, &mut Player::default(), Vec3::new(0.5, 0.0, 0.5), Vec3::new(3.0, 0.0, 3.0), false, 60);
+        assert!(run.feet.x <= 2.0 - PLAYER_WIDTH, "went into the wall at x {}", run.feet.x);
+        assert!(run.feet.x > 1.5, "stopped short of the wall at x {}", run.feet.x);
+        assert!((run.feet.z - 3.5).abs() < 0.1, "slid to z {}", run.feet.z);
+        assert_eq!(run.feet.y, 0.0);
+    }
+
+    #[test]
+    fn step_assist_climbs_a_full_block() {
+        let ledge: Vec<IVec3> = (2..6).map(|x| IVec3::new(x, 0, 0)).collect();
+        let solid = world(&ledge);
+        let start = Vec3::new(0.5, 0.0, 0.5);
+
+        let run = run_player(&solid, &mut Player::default(), start, Vec3::new(3.0, 0.0, 0.0), false, 60);
+        assert_eq!(run.feet.y, 0.0, "climbed without step assist");
+        assert!(run.feet.x < 2.0);
+
+        let mut player = Player {
+            step_assist: true,
+            ..default()
+        };
+        let run = run_player(&solid, &mut player, start, Vec3::new(3.0, 0.0, 0.0), false, 60);
+        assert_eq!(run.feet.y, 1.0);
+        assert!(run.feet.x > 3.0, "stuck at x {}", run.feet.x);
+    }
+
+    #[test]
+    fn jumping_into_a_ceiling_stops_the_rise() {
+        let ceiling: Vec<IVec3> = (-1..2)
+            .flat_map(|x| (-1..2).map(move |z| IVec3::new(x, 3, z)))
+            .collect();
+        let solid = world(&ceiling);
+        let run = run_player(&solid, &mut Player::default(), Vec3::new(0.5, 0.0, 0.5), Vec3::Y * JUMP_VELOCITY, false, 60);
+        assert!(run.highest_feet <= 3.0 - PLAYER_HEIGHT + 0.001, "head went to {}", run.highest_feet + PLAYER_HEIGHT);
+        assert_eq!(run.feet.y, 0.0);
+        assert!(run.grounded);
+    }
+
+    #[test]
+    fn crouching_stops_at_the_edge() {
+        // A platform ending at x = 2 above a long drop
+        let solid = |x: i32, y: i32, _z: i32| y < 0 && x < 2 && y > -10;
+        let mut player = Player::default();
+        let run = run_player(&solid, &mut player, Vec3::new(0.5, 0.0, 0.5), Vec3::new(1.5, 0.0, 0.0), true, 180);
+        assert!(player.crouching);
+        assert_eq!(run.feet.y, 0.0);
+        assert!(run.feet.x < 2.0 + PLAYER_WIDTH, "walked off at x {}", run.feet.x);
+        assert!(run.feet.x > 1.9, "stopped short at x {}", run.feet.x);
+
+        let run = run_player(&solid, &mut Player::default(), Vec3::new(0.5, 0.0, 0.5), Vec3::new(1.5, 0.0, 0.0), false, 180);
+        assert!(run.feet.y < -1.0, "standing player did not fall");
+    }
+
+    #[test]
+    fn body_lands_and_friction_stops_it() {
+        let solid = world(&[]);
+        let body = VoxelBody::new(Vec3::splat(0.25));
+        let mut center = Vec3::new(0.5, 3.0, 0.5);
+        let mut velocity = Vec3::new(2.0, 0.0, 0.0);
+        let mut grounded = false;
+        for _ in 0..240 {
+            grounded = move_body(&solid, &body, &mut center, &mut velocity, DT);
+        }
+        assert!(grounded);
+        assert!((center.y - 0.25).abs() < 0.01, "rests at y {}", center.y);
+        assert!(velocity.length() < 0.01, "still moving at {}", velocity);
+    }
+
+    #[test]
+    fn body_slides_along_a_wall() {
+        let wall: Vec<IVec3> = (-2..10)
+            .flat_map(|z| (0..3).map(move |y| IVec3::new(2, y, z)))
+            .collect();
+        let solid = world(&wall);
+        let body = VoxelBody::new(Vec3::splat(0.25)).with_friction(0.0);
+        let mut center = Vec3::new(1.5, 0.25, 0.5);
+        let mut velocity = Vec3::new(4.0, 0.0, 2.0);
+        for _ in 0..60 {
+            move_body(&solid, &body, &mut center, &mut velocity, DT);
+        }
+        assert!(center.x <= 1.75 + 0.001, "went into the wall at x {}", center.x);
+        assert_eq!(velocity.x, 0.0);
+        assert!((center.z - 2.5).abs() < 0.05, "slid to z {}", center.z);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::camera::{MovementMode, Player};
+use crate::chunk::{Chunk, ChunkMap};
+use crate::chunk_codec::{decode_chunk, encode_chunk};
+use crate::health::Health;
+use crate::hunger::{FoodBag, Hunger, Stamina};
+use crate::journal::EditJournal;
+use crate::physics::{PhysicsPosition, Velocity};
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Everything that outlives a session is kept under this directory
+pub const SAVE_DIR: &str = "saves/world";
+const SEED_FILE: &str = "seed.txt";
+const PLAYER_FILE: &str = "player.txt";
+const JOURNAL_FILE: &str = "edits.dat";
+const CHUNK_DIR: &str = "chunks";
+
+const AUTOSAVE_INTERVAL: f32 = 30.0;
+
+pub fn save_path(file: &str) -> PathBuf {
+    Path::new(SAVE_DIR).join(file)
+}
+
+/// Where edited chunks are saved whole, one file per chunk.
+pub fn chunk_dir() -> PathBuf {
+    save_path(CHUNK_DIR)
+}
+
+fn chunk_file(dir: &Path, chunk_pos: IVec3) -> PathBuf {
+    dir.join(format!("{}_{}.chunk", chunk_pos.x, chunk_pos.z))
+}
+
+/// Writes `chunk` into `dir` with [`encode_chunk`], replacing any earlier
+/// copy.
+pub fn save_chunk(dir: &Path, chunk: &Chunk) {
+    let path = chunk_file(dir, chunk.position);
+    if let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, encode_chunk(chunk))) {
+        warn!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Reads back a chunk written by [`save_chunk`], or `None` if it was never
+/// saved or the file is unreadable.
+pub fn load_chunk(dir: &Path, chunk_pos: IVec3) -> Option<Chunk> {
+    let path = chunk_file(dir, chunk_pos);
+    let bytes = fs::read(&path).ok()?;
+    match decode_chunk(&bytes) {
+        Ok(chunk) if chunk.position == chunk_pos => Some(chunk),
+        Ok(_) => {
+            warn!("Ignoring {}: it holds another chunk", path.display());
+            None
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Reads the world seed from the save directory, or picks a new one and
+/// writes it there so the same terrain comes back next time.
+pub fn load_or_create_seed() -> u32 {
+    let path = save_path(SEED_FILE);
+    if let Ok(text) = fs::read_to_string(&path)
+        && let Ok(seed) = text.trim().parse()
+    {
+        return seed;
+    }
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| (d.as_millis() & 0xFFFFFFFF) as u32)
+        .unwrap_or(42);
+    if let Err(e) = fs::create_dir_all(SAVE_DIR).and_then(|_| fs::write(&path, format!("{}\n", seed))) {
+        warn!("Failed to save {}: {}", path.display(), e);
+    }
+    seed
+}
+
+/// The player's state as stored in `player.txt`, one `key = value` per line.
+pub struct PlayerData {
+    pub position: Vec3,
+    pub mode: MovementMode,
+    pub health: f32,
+    pub air: f32,
+    pub hunger: f32,
+    pub exhaustion: f32,
+    pub stamina: f32,
+    pub apples: u32,
+}
+
+impl PlayerData {
+    pub fn to_text(&self) -> String {
+        let p = self.position;
+        format!(
+            "# Player data\n\
+             position = {} {} {}\n\
+             mode = {:?}\n\
+             health = {}\n\
+             air = {}\n\
+             hunger = {}\n\
+             exhaustion = {}\n\
+             stamina = {}\n\
+             apples = {}\n",
+            p.x, p.y, p.z, self.mode, self.health, self.air, self.hunger, self.exhaustion, self.stamina, self.apples
+        )
+    }
+
+    /// Returns `None` unless every field is present and readable, so a
+    /// damaged file falls back to a fresh player instead of a half-loaded one.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let values: HashMap<&str, &str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+
+        let coords: Vec<f32> = values
+            .get("position")?
+            .split_whitespace()
+            .map(|v| v.parse().ok())
+            .collect::<Option<_>>()?;
+        let [x, y, z] = coords[..] else { return None };
+        let mode = match *values.get("mode")? {
+            "Survival" => MovementMode::Survival,
+            "Creative" => MovementMode::Creative,
+            "Spectator" => MovementMode::Spectator,
+            _ => return None,
+        };
+
+        Some(Self {
+            position: Vec3::new(x, y, z),
+            mode,
+            health: values.get("health")?.parse().ok()?,
+            air: values.get("air")?.parse().ok()?,
+            hunger: values.get("hunger")?.parse().ok()?,
+            exhaustion: values.get("exhaustion")?.parse().ok()?,
+            stamina: values.get("stamina")?.parse().ok()?,
+            apples: values.get("apples")?.parse().ok()?,
+        })
+    }
+}
+
+#[derive(Resource)]
+struct AutosaveTimer(Timer);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AutosaveTimer(Timer::from_seconds(AUTOSAVE_INTERVAL, TimerMode::Repeating)))
+            // The player is spawned during Startup
+            .add_systems(Startup, load_journal)
+            .add_systems(PostStartup, load_player)
+            .add_systems(Update, autosave)
+            .add_systems(Last, save_on_exit);
+    }
+}
+
+type PlayerSaveQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Player,
+        &'static mut PhysicsPosition,
+        &'static mut Health,
+        &'static mut Hunger,
+        &'static mut Stamina,
+        &'static mut FoodBag,
+    ),
+>;
+
+fn load_player(mut query: PlayerSaveQuery, mut velocities: Query<&mut Velocity, With<Player>>) {
+    let path = save_path(PLAYER_FILE);
+    let Ok(text) = fs::read_to_string(&path) else { return };
+    let Some(data) = PlayerData::from_text(&text) else {
+        warn!("Ignoring unreadable {}", path.display());
+        return;
+    };
+    let Ok((mut player, mut position, mut health, mut hunger, mut stamina, mut bag)) = query.get_single_mut() else {
+        return;
+    };
+
+    position.teleport(data.position);
+    if let Ok(mut velocity) = velocities.get_single_mut() {
+        velocity.0 = Vec3::ZERO;
+    }
+    player.mode = data.mode;
+    // Never load straight into the death screen
+    health.current = data.health.clamp(1.0, health.max);
+    health.air = data.air;
+    hunger.level = data.hunger;
+    hunger.exhaustion = data.exhaustion;
+    stamina.current = data.stamina;
+    bag.apples = data.apples;
+    info!("Loaded player from {}", path.display());
+}
+
+fn save_player(query: &PlayerSaveQuery) {
+    let Ok((player, position, health, hunger, stamina, bag)) = query.get_single() else { return };
+    let data = PlayerData {
+        position: position.current,
+        mode: player.mode,
+        health: health.current,
+        air: health.air,
+        hunger: hunger.level,
+        exhaustion: hunger.exhaustion,
+        stamina: stamina.current,
+        apples: bag.apples,
+    };
+
+    let path = save_path(PLAYER_FILE);
+    if let Err(e) = fs::create_dir_all(SAVE_DIR).and_then(|_| fs::write(&path, data.to_text())) {
+        warn!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Block edits are kept as a journal over the generated terrain; chunks
+/// replay it as they are generated.
+fn load_journal(mut journal: ResMut<EditJournal>) {
+    let path = save_path(JOURNAL_FILE);
+    let Ok(bytes) = fs::read(&path) else { return };
+    match EditJournal::from_bytes(&bytes) {
+        Ok(loaded) => {
+            info!("Loaded {} block edits from {}", loaded.len(), path.display());
+            *journal = loaded;
+        }
+        Err(e) => warn!("Ignoring unreadable {}: {}", path.display(), e),
+    }
+}
+
+fn save_journal(journal: &mut EditJournal) {
+    if !journal.take_unsaved() {
+        return;
+    }
+    journal.compact();
+    let path = save_path(JOURNAL_FILE);
+    if let Err(e) = fs::create_dir_all(SAVE_DIR).and_then(|_| fs::write(&path, journal.to_bytes())) {
+        warn!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Saves the loaded chunks edited since the last save. Ones that unloaded
+/// in between keep their older file, and the journal catches it up.
+fn save_edited_chunks(map: &ChunkMap, journal: &mut EditJournal) {
+    for chunk_pos in journal.take_unsaved_chunks() {
+        if let Some(chunk) = map.get(chunk_pos) {
+            save_chunk(&chunk_dir(), chunk);
+        }
+    }
+}
+
+fn autosave(
+    time: Res<Time>,
+    mut timer: ResMut<AutosaveTimer>,
+    query: PlayerSaveQuery,
+    mut journal: ResMut<EditJournal>,
+    map: Res<ChunkMap>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        save_player(&query);
+        save_edited_chunks(&map, &mut journal);
+        save_journal(&mut journal);
+    }
+}
+
+fn save_on_exit(
+    mut exits: EventReader<AppExit>,
+    query: PlayerSaveQuery,
+    mut journal: ResMut<EditJournal>,
+    map: Res<ChunkMap>,
+) {
+    if exits.read().next().is_some() {
+        save_player(&query);
+        save_edited_chunks(&map, &mut journal);
+        save_journal(&mut journal);
+        info!("Saved player to {}", save_path(PLAYER_FILE).display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockType;
+
+    #[test]
+    fn chunk_round_trips_through_a_file() {
+        let dir = std::env::temp_dir().join(format!("voxelverse-chunks-{}", std::process::id()));
+        let mut chunk = Chunk::new(IVec3::new(-2, 0, 5));
+        chunk.set_block(3, 10, 15, BlockType::Wood);
+        chunk.set_block(0, 0, 0, BlockType::Stone);
+
+        save_chunk(&dir, &chunk);
+        let loaded = load_chunk(&dir, chunk.position).unwrap();
+        assert_eq!(loaded.position, chunk.position);
+        assert_eq!(loaded.get_block(3, 10, 15), BlockType::Wood);
+        assert_eq!(loaded.get_block(0, 0, 0), BlockType::Stone);
+        assert_eq!(loaded.get_block(1, 1, 1), BlockType::Air);
+        assert!(load_chunk(&dir, IVec3::new(7, 0, 7)).is_none());
+
+        // A damaged file, or one renamed to another chunk, is not loaded
+        fs::write(chunk_file(&dir, IVec3::new(7, 0, 7)), encode_chunk(&chunk)).unwrap();
+        assert!(load_chunk(&dir, IVec3::new(7, 0, 7)).is_none());
+        fs::write(chunk_file(&dir, chunk.position), [1, 2, 3]).unwrap();
+        assert!(load_chunk(&dir, chunk.position).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,561 @@
+use crate::block::BlockType;
+use crate::camera::{MovementMode, Player};
+use crate::chunk::{CHUNK_SIZE, Chunk, ChunkMap, chunk_of};
+use crate::erosion::{ErosionCache, REGION_SIZE};
+use crate::journal::EditJournal;
+use crate::net::{
+    ClientMessage, Connection, InputFrame, PROTOCOL_VERSION, ServerMessage, apply_input,
+};
+use crate::physics::{Grounded, PHYSICS_HZ, PLAYER_HEIGHT, aabb_overlaps_solid, step_player};
+use crate::world::{
+    BlockChanged, MAX_RENDER_DISTANCE, World as GameWorld, WorldSettings, generate_chunk_blocks, get_spawn_height,
+};
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Longer chat lines are cut short
+const MAX_CHAT_LEN: usize = 256;
+// Chunks generated around each player so their physics has ground to stand on
+const PHYSICS_RADIUS: i32 = 1;
+// Each client may have one more input simulated every tick, saved up to
+// INPUT_BURST so inputs held up by the network catch up. A client sending
+// faster than it ticks only grows its queue, and a backlog past
+// MAX_QUEUED_INPUTS is dropped
+const INPUT_BURST: u32 = 8;
+const MAX_QUEUED_INPUTS: usize = 64;
+// Chunks further than this from a player on either axis are neither sent
+// to them nor kept for them: the largest render distance, the client's
+// unload margin of 2, and 1 for the camera running ahead of the server
+const VIEW_RANGE: i32 = MAX_RENDER_DISTANCE + 3;
+// Edits further than this from the player's eye are refused. Longer than
+// the client's reach, since felling a tree also clears the leaves above it
+const MAX_EDIT_REACH: f32 = 12.0;
+
+/// Chunk data owned by the server. It lives outside the ECS: the server
+/// has no chunk entities or visuals, only blocks.
+#[derive(Resource, Default)]
+pub struct ServerWorld {
+    pub chunks: ChunkMap,
+    /// Every accepted edit, replayed onto chunks generated again after
+    /// they were unloaded
+    journal: EditJournal,
+}
+
+impl ServerWorld {
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        self.chunks.get_block(IVec3::new(x, y, z)).is_some_and(|b| b.is_collidable())
+    }
+
+    fn ensure_chunk(
+        &mut self,
+        chunk_pos: IVec3,
+        world: &GameWorld,
+        settings: &WorldSettings,
+        erosion: &mut ErosionCache,
+    ) -> &Arc<Chunk> {
+        let journal = &self.journal;
+        self.chunks.get_or_insert_with(chunk_pos, || {
+            let mut chunk = generate_chunk_blocks(chunk_pos, world, settings, erosion).0;
+            journal.replay(&mut chunk);
+            chunk
+        })
+    }
+
+    fn set_block(&mut self, pos: IVec3, block: BlockType) {
+        if let Some(old) = self.chunks.set_block(pos, block) {
+            self.journal.log_edits([BlockChanged { pos, old, new: block }]);
+        }
+    }
+}
+
+/// The server's copy of one connected player.
+struct RemoteClient {
+    connection: Connection,
+    player: Player,
+    position: Vec3,
+    velocity: Vec3,
+    grounded: Grounded,
+    impact: f32,
+    inputs: VecDeque<InputFrame>,
+    /// Inputs that may be simulated before the next refill
+    input_budget: u32,
+    /// Last input applied, echoed back for reconciliation
+    last_seq: Option<u32>,
+    greeted: bool,
+}
+
+impl RemoteClient {
+    /// Whether `chunk_pos` is close enough to this player to be sent to
+    /// them and kept loaded.
+    fn in_view(&self, chunk_pos: IVec3) -> bool {
+        let offset = (chunk_pos - chunk_of(self.position.floor().as_ivec3())).abs();
+        chunk_pos.y == 0 && offset.x.max(offset.z) <= VIEW_RANGE
+    }
+}
+
+#[derive(Resource)]
+pub struct Server {
+    listener: TcpListener,
+    clients: HashMap<u32, RemoteClient>,
+    next_id: u32,
+}
+
+/// Runs a headless authoritative server on `addr` until the process is
+/// stopped.
+pub fn run(addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!("Server listening on {}", listener.local_addr()?);
+    build_app(listener, crate::save::load_or_create_seed())?.run();
+    Ok(())
+}
+
+/// A headless app serving the world of `seed` on `listener`; split from
+/// `run` so a test can drive a server on localhost with `App::update`.
+pub fn build_app(listener: TcpListener, seed: u32) -> io::Result<App> {
+    listener.set_nonblocking(true)?;
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / PHYSICS_HZ))),
+        LogPlugin::default(),
+    ))
+    .insert_resource(GameWorld::new(seed))
+    .insert_resource(Server {
+        listener,
+        clients: HashMap::new(),
+        next_id: 1,
+    })
+    .add_plugins(ServerPlugin);
+    Ok(app)
+}
+
+pub struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldSettings>()
+            .init_resource::<ErosionCache>()
+            .init_resource::<ServerWorld>()
+            .add_systems(
+                Update,
+                (accept_clients, handle_messages, broadcast_players, unload_far_chunks, flush_clients).chain(),
+            );
+    }
+}
+
+fn accept_clients(mut server: ResMut<Server>, world: Res<GameWorld>) {
+    loop {
+        let (stream, addr) = match server.listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("Accept failed: {}", e);
+                return;
+            }
+        };
+        let connection = match Connection::new(stream) {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Dropping {}: {}", addr, e);
+                continue;
+            }
+        };
+
+        let id = server.next_id;
+        server.next_id += 1;
+        let spawn = Vec3::new(0.0, get_spawn_height(&world.noise) + PLAYER_HEIGHT, 0.0);
+        server.clients.insert(
+            id,
+            RemoteClient {
+                connection,
+                player: Player::default(),
+                position: spawn,
+                velocity: Vec3::ZERO,
+                grounded: Grounded(false),
+                impact: 0.0,
+                inputs: VecDeque::new(),
+                input_budget: INPUT_BURST,
+                last_seq: None,
+                greeted: false,
+            },
+        );
+        info!("Player {} connected from {}", id, addr);
+    }
+}
+
+fn handle_messages(
+    mut server: ResMut<Server>,
+    mut server_world: ResMut<ServerWorld>,
+    world: Res<GameWorld>,
+    settings: Res<WorldSettings>,
+    mut erosion: ResMut<ErosionCache>,
+) {
+    let dt = 1.0 / PHYSICS_HZ as f32;
+    let mut changed_blocks = Vec::new();
+    let mut chat = Vec::new();
+    // Where every player stands, for edits that would place a block on one
+    let bodies: Vec<(Vec3, f32)> = server.clients.values().map(|c| (c.position, c.player.height())).collect();
+
+    for (&id, client) in server.clients.iter_mut() {
+        for message in client.connection.receive::<ClientMessage>() {
+            match message {
+                ClientMessage::Hello { version } => {
+                    if version != PROTOCOL_VERSION {
+                        warn!("Player {} speaks protocol {}, expected {}", id, version, PROTOCOL_VERSION);
+                        client.connection.closed = true;
+                        break;
+                    }
+                    client.greeted = true;
+                    client.connection.send(&ServerMessage::Welcome {
+                        id,
+                        seed: world.seed,
+                        spawn: client.position,
+                    });
+                }
+                _ if !client.greeted => {
+                    client.connection.closed = true;
+                    break;
+                }
+                ClientMessage::RequestChunk(pos) => {
+                    if !client.in_view(pos) {
+                        warn!("Refused chunk {} to player {}: out of view", pos, id);
+                        continue;
+                    }
+                    let chunk = server_world.ensure_chunk(pos, &world, &settings, &mut erosion);
+                    client.connection.send(&ServerMessage::ChunkData(chunk.clone()));
+                }
+                ClientMessage::Input(input) => {
+                    if client.inputs.len() >= MAX_QUEUED_INPUTS {
+                        client.inputs.pop_front();
+                    }
+                    client.inputs.push_back(input);
+                }
+                ClientMessage::SetBlock { pos, block } => {
+                    // Outside the view there is nothing the client could
+                    // have seen, so nothing to correct either
+                    if !client.in_view(chunk_of(pos)) {
+                        warn!("Refused edit at {} from player {}: out of view", pos, id);
+                        continue;
+                    }
+                    server_world.ensure_chunk(chunk_of(pos), &world, &settings, &mut erosion);
+                    let Some(old) = server_world.chunks.get_block(pos) else { continue };
+                    match check_edit(&client.player, client.position, &bodies, pos, old, block) {
+                        Ok(()) if old == block => {}
+                        Ok(()) => {
+                            server_world.set_block(pos, block);
+                            changed_blocks.push((pos, block));
+                        }
+                        Err(reason) => {
+                            warn!("Refused edit at {} from player {}: {}", pos, id, reason);
+                            client.connection.send(&ServerMessage::BlockChanged { pos, block: old });
+                        }
+                    }
+                }
+                ClientMessage::Chat(text) => {
+                    let text: String = text.chars().filter(|c| !c.is_control()).take(MAX_CHAT_LEN).collect();
+                    info!("Player {}: {}", id, text);
+                    chat.push((id, text));
+                }
+            }
+        }
+
+        client.input_budget = (client.input_budget + 1).min(INPUT_BURST);
+        if client.inputs.is_empty() {
+            continue;
+        }
+        // Make sure there is terrain under the player before simulating
+        let center = IVec3::new(
+            (client.position.x.floor() as i32).div_euclid(CHUNK_SIZE as i32),
+            0,
+            (client.position.z.floor() as i32).div_euclid(CHUNK_SIZE as i32),
+        );
+        for dx in -PHYSICS_RADIUS..=PHYSICS_RADIUS {
+            for dz in -PHYSICS_RADIUS..=PHYSICS_RADIUS {
+                server_world.ensure_chunk(center + IVec3::new(dx, 0, dz), &world, &settings, &mut erosion);
+            }
+        }
+
+        let solid = |x: i32, y: i32, z: i32| server_world.is_solid(x, y, z);
+        let count = client.inputs.len().min(client.input_budget as usize);
+        client.input_budget -= count as u32;
+        for input in client.inputs.drain(..count) {
+            apply_input(&client.player, &mut client.velocity, &client.grounded, &input);
+            step_player(
+                &solid,
+                &mut client.player,
+                &mut client.position,
+                &mut client.velocity,
+                &mut client.grounded,
+                &mut client.impact,
+                input.crouch,
+                dt,
+            );
+            client.last_seq = Some(input.seq);
+        }
+        // Fall damage is worked out on the client from its own prediction
+        client.impact = 0.0;
+
+        if let Some(seq) = client.last_seq {
+            client.connection.send(&ServerMessage::PlayerState {
+                seq,
+                position: client.position,
+                velocity: client.velocity,
+                grounded: client.grounded.0,
+                mode: client.player.mode,
+            });
+        }
+    }
+
+    for (pos, block) in changed_blocks {
+        for client in server.clients.values_mut() {
+            client.connection.send(&ServerMessage::BlockChanged { pos, block });
+        }
+    }
+    for (id, text) in chat {
+        for client in server.clients.values_mut().filter(|c| c.greeted) {
+            client.connection.send(&ServerMessage::Chat { id, text: text.clone() });
+        }
+    }
+}
+
+/// Checks a client's edit against what its player could do: spectators
+/// can't edit, the block has to be within reach, and a new block may only
+/// go into air or water that no player is standing in.
+fn check_edit(
+    player: &Player,
+    eye: Vec3,
+    bodies: &[(Vec3, f32)],
+    pos: IVec3,
+    old: BlockType,
+    new: BlockType,
+) -> Result<(), &'static str> {
+    if player.mode == MovementMode::Spectator {
+        return Err("spectators can't edit");
+    }
+    if eye.distance(pos.as_vec3() + Vec3::splat(0.5)) > MAX_EDIT_REACH {
+        return Err("out of reach");
+    }
+    if new != BlockType::Air {
+        if !matches!(old, BlockType::Air | BlockType::Water) {
+            return Err("the space is taken");
+        }
+        let placed = |x: i32, y: i32, z: i32| IVec3::new(x, y, z) == pos;
+        let blocks_player = |&(eye, height): &(Vec3, f32)| aabb_overlaps_solid(&placed, eye.x, eye.y - height, eye.z, height);
+        if new.is_collidable() && bodies.iter().any(blocks_player) {
+            return Err("a player is in the way");
+        }
+    }
+    Ok(())
+}
+
+fn broadcast_players(mut server: ResMut<Server>) {
+    let players: Vec<(u32, Vec3, bool)> = server
+        .clients
+        .iter()
+        .filter(|(_, c)| c.greeted)
+        .map(|(&id, c)| (id, c.position, c.player.crouching))
+        .collect();
+
+    for (&id, client) in server.clients.iter_mut() {
+        for &(other, position, crouching) in &players {
+            if other != id {
+                client.connection.send(&ServerMessage::PlayerMoved {
+                    id: other,
+                    position,
+                    crouching,
+                });
+            }
+        }
+    }
+}
+
+/// Drops chunks and erosion regions no player is near any more. Their
+/// edits are kept in the journal and replayed if they are generated again.
+fn unload_far_chunks(
+    server: Res<Server>,
+    mut server_world: ResMut<ServerWorld>,
+    mut erosion: ResMut<ErosionCache>,
+) {
+    server_world
+        .chunks
+        .retain(|chunk_pos| server.clients.values().any(|c| c.in_view(chunk_pos)));
+
+    // Chunk generation near a region border reads the regions next to it
+    let region_range = (VIEW_RANGE * CHUNK_SIZE as i32 + REGION_SIZE - 1) / REGION_SIZE + 1;
+    let centers: Vec<IVec2> = server
+        .clients
+        .values()
+        .map(|c| ErosionCache::region_of(c.position.x.floor() as i32, c.position.z.floor() as i32))
+        .collect();
+    erosion.retain(|region| centers.iter().any(|&c| (region - c).abs().max_element() <= region_range));
+}
+
+fn flush_clients(mut server: ResMut<Server>) {
+    let mut left = Vec::new();
+    for (&id, client) in server.clients.iter_mut() {
+        client.connection.flush();
+        if client.connection.closed {
+            left.push(id);
+        }
+    }
+
+    for id in left {
+        server.clients.remove(&id);
+        info!("Player {} disconnected", id);
+        for client in server.clients.values_mut() {
+            client.connection.send(&ServerMessage::PlayerLeft { id });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_codec::encode_chunk;
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+
+    fn connect(app: &mut App, addr: std::net::SocketAddr) -> (Connection, Vec3) {
+        let mut connection = Connection::new(TcpStream::connect(addr).unwrap()).unwrap();
+        connection.send(&ClientMessage::Hello { version: PROTOCOL_VERSION });
+        let welcome = pump_until(app, &mut connection, |m| matches!(m, ServerMessage::Welcome { .. }));
+        let ServerMessage::Welcome { seed, spawn, .. } = welcome.last().unwrap() else { unreachable!() };
+        assert_eq!(*seed, 42);
+        (connection, *spawn)
+    }
+
+    /// Runs the server until `connection` receives a message `done` holds
+    /// for, and returns everything it received up to and including it.
+    fn pump_until(
+        app: &mut App,
+        connection: &mut Connection,
+        mut done: impl FnMut(&ServerMessage) -> bool,
+    ) -> Vec<ServerMessage> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut received = Vec::new();
+        while Instant::now() < deadline {
+            connection.flush();
+            app.update();
+            std::thread::sleep(Duration::from_millis(5));
+            for message in connection.receive::<ServerMessage>() {
+                let found = done(&message);
+                received.push(message);
+                if found {
+                    return received;
+                }
+            }
+        }
+        panic!("timed out waiting for the server");
+    }
+
+    fn server_block(app: &App, pos: IVec3) -> BlockType {
+        app.world().resource::<ServerWorld>().chunks.get_block(pos).unwrap()
+    }
+
+    #[test]
+    fn serves_a_client_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut app = build_app(listener, 42).unwrap();
+
+        let (mut a, spawn) = connect(&mut app, addr);
+        let (mut b, _) = connect(&mut app, addr);
+
+        // A chunk out of view is never sent; the one after it is, intact
+        let home = chunk_of(spawn.floor().as_ivec3());
+        let far = home + IVec3::new(VIEW_RANGE + 1, 0, 0);
+        a.send(&ClientMessage::RequestChunk(far));
+        a.send(&ClientMessage::RequestChunk(home));
+        let received = pump_until(&mut app, &mut a, |m| matches!(m, ServerMessage::ChunkData(_)));
+        let ServerMessage::ChunkData(chunk) = received.last().unwrap() else { unreachable!() };
+        assert_eq!(chunk.position, home);
+        let served = app.world().resource::<ServerWorld>().chunks.get(home).unwrap().clone();
+        assert_eq!(encode_chunk(chunk), encode_chunk(&served));
+
+        // The server caps speed at its own idea of the player's mode
+        a.send(&ClientMessage::Input(InputFrame {
+            seq: 0,
+            velocity: Vec3::new(100.0, 0.0, 0.0),
+            crouch: false,
+        }));
+        let received = pump_until(&mut app, &mut a, |m| matches!(m, ServerMessage::PlayerState { .. }));
+        let ServerMessage::PlayerState { position: start, velocity, mode, .. } = *received.last().unwrap() else {
+            unreachable!()
+        };
+        let player = Player::default();
+        let max_speed = player.speed * player.sprint_multiplier;
+        assert_eq!(mode, MovementMode::Survival);
+        assert!(velocity.xz().length() <= max_speed + 1e-3);
+
+        // A flood of inputs is simulated one per tick, after a short burst
+        const TICKS: u32 = 20;
+        for seq in 1..=60 {
+            a.send(&ClientMessage::Input(InputFrame {
+                seq,
+                velocity: Vec3::new(max_speed, 0.0, 0.0),
+                crouch: false,
+            }));
+        }
+        a.flush();
+        std::thread::sleep(Duration::from_millis(100));
+        for _ in 0..TICKS {
+            app.update();
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        let Some(ServerMessage::PlayerState { seq, position, .. }) = a
+            .receive::<ServerMessage>()
+            .into_iter()
+            .rfind(|m| matches!(m, ServerMessage::PlayerState { .. }))
+        else {
+            panic!("no player state after the flood");
+        };
+        // The whole burst on the first tick, then one per tick
+        let simulated = INPUT_BURST + TICKS - 1;
+        assert_eq!(seq, simulated);
+        let moved = (position - start).xz().length();
+        assert!(moved <= simulated as f32 * max_speed / PHYSICS_HZ as f32 + 1e-3, "moved {}", moved);
+
+        // Refused edits are answered with the server's block, to A alone
+        let feet = (spawn - Vec3::Y * PLAYER_HEIGHT).floor().as_ivec3();
+        let ground = (0..=feet.y)
+            .rev()
+            .map(|y| IVec3::new(feet.x, y, feet.z))
+            .find(|&pos| server_block(&app, pos) != BlockType::Air)
+            .unwrap();
+        let out_of_reach = ground + IVec3::new(0, 0, 40);
+        let in_body = (spawn - Vec3::Y).floor().as_ivec3();
+        a.send(&ClientMessage::SetBlock { pos: out_of_reach, block: BlockType::Air });
+        a.send(&ClientMessage::SetBlock { pos: in_body, block: BlockType::Stone });
+        a.send(&ClientMessage::SetBlock { pos: ground, block: BlockType::Air });
+
+        let received = pump_until(&mut app, &mut a, |m| matches!(m, ServerMessage::BlockChanged { pos, .. } if *pos == ground));
+        let corrections: Vec<_> = received
+            .iter()
+            .filter_map(|m| match m {
+                ServerMessage::BlockChanged { pos, block } => Some((*pos, *block)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            corrections,
+            [
+                (out_of_reach, server_block(&app, out_of_reach)),
+                (in_body, server_block(&app, in_body)),
+                (ground, BlockType::Air),
+            ]
+        );
+        assert_eq!(server_block(&app, in_body), BlockType::Air);
+        assert_eq!(server_block(&app, ground), BlockType::Air);
+
+        let received = pump_until(&mut app, &mut b, |m| matches!(m, ServerMessage::BlockChanged { .. }));
+        assert!(matches!(
+            received.last().unwrap(),
+            ServerMessage::BlockChanged { pos, block: BlockType::Air } if *pos == ground
+        ));
+    }
+}
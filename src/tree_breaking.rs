@@ -1,8 +1,10 @@
 use bevy::prelude::*;
+use crate::actions::{Action, ActionState};
 use crate::camera::MainCamera;
-use crate::world::World;
-use crate::chunk::{Chunk, CHUNK_SIZE, CHUNK_HEIGHT};
+use crate::journal::BlockEditor;
 use crate::block::BlockType;
+use crate::hunger::LeavesBroken;
+use crate::physics::{VoxelBody, VoxelBodyBundle};
 
 const TREE_REACH: f32 = 6.0;
 // Calibrates base mathematical hardness (Total time = total components (wood+leaves) * THIS multiplier natively evaluating bounding mapping successfully!)
@@ -27,14 +29,12 @@ pub struct TreeCrackOverlay {
 
 #[derive(Component)]
 pub struct WoodParticle {
-    pub velocity: Vec3,
     pub age: f32,
     pub lifetime: f32,
 }
 
 #[derive(Component)]
 pub struct TreeDrop {
-    pub origin_y: f32,
     pub age: f32,
 }
 
@@ -127,10 +127,11 @@ fn raycast_tree_target(
 }
 
 /// Overlaps checks physically matching structures bounds flawlessly checking loops arrays bounds checks appropriately mappings evaluating dynamically checking components gracefully matching structurally bounds efficiently checks limits cleanly 
+#[allow(clippy::too_many_arguments)]
 fn handle_tree_breaking(
     mut commands: Commands,
     time: Res<Time>,
-    mouse: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
     mut state: ResMut<TreeBreakingState>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -138,12 +139,12 @@ fn handle_tree_breaking(
     tree_transform_query: Query<&GlobalTransform, With<TreePart>>,
     children_query: Query<&Children>,
     root_query: Query<&TreeRoot>,
-    mut chunks: Query<&mut Chunk>,
-    world: Res<World>,
+    mut editor: BlockEditor,
+    mut leaves_broken: EventWriter<LeavesBroken>,
 ) {
     let Some(target_root) = state.root_entity else { return; };
 
-    if !mouse.pressed(MouseButton::Left) {
+    if !actions.pressed(Action::Break) {
         state.progress_time = 0.0;
         for e in state.crack_entities.drain(..) { commands.entity(e).despawn_recursive(); }
         return;
@@ -182,21 +183,12 @@ fn handle_tree_breaking(
     // Shattering limits cleanly
     if state.progress_time >= state.total_break_duration && state.total_break_duration > 0.0 {
         if let Ok(tree_root) = root_query.get(target_root) {
-            for pos in &tree_root.blocks {
-                let chunk_pos = IVec3::new(pos.x.div_euclid(CHUNK_SIZE as i32), 0, pos.z.div_euclid(CHUNK_SIZE as i32));
-                if let Some(&chunk_ent) = world.chunks.get(&chunk_pos) {
-                    if let Ok(mut chunk) = chunks.get_mut(chunk_ent) {
-                        let lx = pos.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-                        let lz = pos.z.rem_euclid(CHUNK_SIZE as i32) as usize;
-                        if pos.y >= 0 && pos.y < CHUNK_HEIGHT as i32 {
-                            chunk.set_block(lx, pos.y as usize, lz, BlockType::Air);
-                        }
-                    }
-                }
-            }
+            // The whole tree is one undo step
+            editor.apply(tree_root.blocks.iter().map(|&pos| (pos, BlockType::Air)));
 
             // Explosion exactly overlaps limits 
             spawn_tree_drops(&mut commands, &asset_server, state.hit_point_origin, tree_root.wood_count, tree_root.leaves_count);
+            leaves_broken.send(LeavesBroken { position: state.hit_point_origin, count: tree_root.leaves_count });
             spawn_wood_particles(&mut commands, &mut meshes, &mut materials, state.hit_point_origin);
 
             commands.entity(target_root).despawn_recursive();
@@ -271,7 +263,12 @@ fn spawn_wood_particles(
                 transform: Transform::from_translation(center + Vec3::new(ox, oy, oz)).with_scale(Vec3::splat(0.2)),
                 ..default()
             },
-            WoodParticle { velocity: Vec3::new(vx, vy, vz), age: 0.0, lifetime: 1.2 },
+            WoodParticle { age: 0.0, lifetime: 1.2 },
+            VoxelBodyBundle::new(
+                VoxelBody::new(Vec3::splat(0.1)).with_gravity_scale(0.5).with_bounciness(0.3),
+                center + Vec3::new(ox, oy, oz),
+                Vec3::new(vx, vy, vz),
+            ),
         ));
     }
 }
@@ -285,9 +282,7 @@ fn update_wood_particles(
         if particle.age >= particle.lifetime { commands.entity(entity).despawn(); continue; }
 
         let t = particle.age / particle.lifetime;
-        transform.translation += particle.velocity * dt;
-        particle.velocity.y -= 14.0 * dt;
-        
+
         transform.scale = Vec3::splat((1.0 - t * 0.8).max(0.02) * 0.2);
         transform.rotate_x(dt * 6.0);
         transform.rotate_z(dt * 4.0);
@@ -311,7 +306,8 @@ fn spawn_tree_drops(
                     transform: Transform::from_translation(spawn_pos).with_scale(Vec3::splat(drop_scale)),
                     ..default()
                 },
-                TreeDrop { origin_y: spawn_pos.y, age: 0.0 },
+                TreeDrop { age: 0.0 },
+                VoxelBodyBundle::new(VoxelBody::new(Vec3::splat(drop_scale * 0.5)), spawn_pos, Vec3::ZERO),
             ));
         }
     };
@@ -328,7 +324,6 @@ fn update_tree_drops(
 
     for (entity, mut transform, mut drop) in query.iter_mut() {
         drop.age += dt;
-        transform.translation.y = drop.origin_y + (drop.age * 2.0).sin() * 0.12;
         transform.rotate_y(dt * 1.5);
 
         if (transform.translation - player_pos).length() < 1.8 {
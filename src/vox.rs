@@ -0,0 +1,551 @@
+use crate::actions::{Action, ActionState};
+use crate::block::BlockType;
+use crate::block_breaking::BreakingState;
+use crate::chunk::CHUNK_HEIGHT;
+use crate::hud::minimap_color;
+use crate::journal::BlockEditor;
+use crate::voxel_world::VoxelWorld;
+use bevy::prelude::*;
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STRUCTURES_DIR: &str = "assets/structures";
+const EXPORT_DIR: &str = "exports";
+const VOX_VERSION: u32 = 150;
+// MagicaVoxel models are limited to 256 voxels per axis
+const MAX_MODEL_SIZE: i32 = 256;
+
+const PALETTE_BLOCKS: [BlockType; 7] = [
+    BlockType::Grass,
+    BlockType::Dirt,
+    BlockType::Stone,
+    BlockType::Sand,
+    BlockType::Wood,
+    BlockType::Leaves,
+    BlockType::Water,
+];
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(std::io::Error),
+    Format(&'static str),
+}
+
+impl fmt::Display for VoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoxError::Io(e) => write!(f, "io error: {}", e),
+            VoxError::Format(msg) => write!(f, "invalid .vox file: {}", msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for VoxError {
+    fn from(e: std::io::Error) -> Self {
+        VoxError::Io(e)
+    }
+}
+
+/// A single model from a `.vox` file. Coordinates are MagicaVoxel's (Z up),
+/// colors are palette indices in 1..=255.
+pub struct VoxModel {
+    pub size: UVec3,
+    pub voxels: Vec<(UVec3, u8)>,
+}
+
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// RGBA per color index; index 0 is unused (empty voxel)
+    pub palette: [[u8; 4]; 256],
+}
+
+/// Structure ready to be placed into the world, in world axes (Y up).
+pub struct VoxStructure {
+    pub name: String,
+    pub size: IVec3,
+    pub blocks: Vec<(IVec3, BlockType)>,
+}
+
+#[derive(Resource, Default)]
+pub struct VoxLibrary {
+    pub structures: Vec<VoxStructure>,
+    pub selected: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct VoxSelection {
+    pub corner_a: Option<IVec3>,
+    pub corner_b: Option<IVec3>,
+}
+
+pub struct VoxPlugin;
+
+impl Plugin for VoxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxLibrary>()
+            .init_resource::<VoxSelection>()
+            .add_systems(Startup, load_structures)
+            .add_systems(
+                Update,
+                (
+                    cycle_structure,
+                    place_structure,
+                    select_export_corners,
+                    export_selection,
+                ),
+            );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Reading
+// ---------------------------------------------------------------------------
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], VoxError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(VoxError::Format("unexpected end of file"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn id(&mut self) -> Result<[u8; 4], VoxError> {
+        let b = self.take(4)?;
+        Ok([b[0], b[1], b[2], b[3]])
+    }
+}
+
+pub fn read_vox(bytes: &[u8]) -> Result<VoxFile, VoxError> {
+    let mut r = Reader { bytes, pos: 0 };
+    if &r.id()? != b"VOX " {
+        return Err(VoxError::Format("missing VOX header"));
+    }
+    let _version = r.u32()?;
+
+    if &r.id()? != b"MAIN" {
+        return Err(VoxError::Format("missing MAIN chunk"));
+    }
+    let main_content = r.u32()? as usize;
+    let _main_children = r.u32()?;
+    r.take(main_content)?;
+
+    let mut models = Vec::new();
+    let mut pending_size: Option<UVec3> = None;
+    let mut palette = default_palette();
+
+    while r.pos < bytes.len() {
+        let id = r.id()?;
+        let content_len = r.u32()? as usize;
+        let children_len = r.u32()? as usize;
+        let content = r.take(content_len)?;
+        let mut c = Reader { bytes: content, pos: 0 };
+
+        match &id {
+            b"SIZE" => {
+                pending_size = Some(UVec3::new(c.u32()?, c.u32()?, c.u32()?));
+            }
+            b"XYZI" => {
+                let size = pending_size
+                    .take()
+                    .ok_or(VoxError::Format("XYZI chunk without SIZE"))?;
+                let count = c.u32()? as usize;
+                let mut voxels = Vec::with_capacity(count.min(content_len / 4));
+                for _ in 0..count {
+                    let v = c.take(4)?;
+                    if v[3] == 0 {
+                        continue;
+                    }
+                    voxels.push((UVec3::new(v[0] as u32, v[1] as u32, v[2] as u32), v[3]));
+                }
+                models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                // Palette entry i describes color index i + 1
+                for i in 0..255 {
+                    let rgba = c.take(4)?;
+                    palette[i + 1] = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                }
+            }
+            // Scene graph, materials, layers etc. are not needed for block import
+            _ => {}
+        }
+
+        r.take(children_len)?;
+    }
+
+    if models.is_empty() {
+        return Err(VoxError::Format("file contains no models"));
+    }
+
+    Ok(VoxFile { models, palette })
+}
+
+/// MagicaVoxel's built-in palette, used when a file has no RGBA chunk.
+/// A 6×6×6 color cube (minus black) followed by red, green, blue and gray ramps.
+fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = [[0u8; 4]; 256];
+    let mut i = 1;
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                if r == 0 && g == 0 && b == 0 {
+                    continue;
+                }
+                palette[i] = [r, g, b, 0xff];
+                i += 1;
+            }
+        }
+    }
+    for channel in 0..4 {
+        for v in RAMP {
+            palette[i] = match channel {
+                0 => [v, 0, 0, 0xff],
+                1 => [0, v, 0, 0xff],
+                2 => [0, 0, v, 0xff],
+                _ => [v, v, v, 0xff],
+            };
+            i += 1;
+        }
+    }
+    palette
+}
+
+/// Maps a palette color onto the block whose minimap color is closest.
+pub fn nearest_block(rgba: [u8; 4]) -> BlockType {
+    let color = Vec3::new(rgba[0] as f32, rgba[1] as f32, rgba[2] as f32) / 255.0;
+
+    PALETTE_BLOCKS
+        .iter()
+        .copied()
+        .min_by(|&a, &b| {
+            let da = color.distance_squared(block_rgb(a));
+            let db = color.distance_squared(block_rgb(b));
+            da.total_cmp(&db)
+        })
+        .unwrap_or(BlockType::Stone)
+}
+
+fn block_rgb(block: BlockType) -> Vec3 {
+    let c = minimap_color(block).to_srgba();
+    Vec3::new(c.red, c.green, c.blue)
+}
+
+impl VoxStructure {
+    pub fn from_model(name: String, model: &VoxModel, palette: &[[u8; 4]; 256]) -> Self {
+        // Resolve each palette index once instead of per voxel
+        let mut lookup = [BlockType::Air; 256];
+        for (i, rgba) in palette.iter().enumerate().skip(1) {
+            lookup[i] = nearest_block(*rgba);
+        }
+
+        let blocks = model
+            .voxels
+            .iter()
+            .map(|&(p, index)| {
+                // MagicaVoxel is Z-up; swap into Bevy's Y-up world axes
+                (IVec3::new(p.x as i32, p.z as i32, p.y as i32), lookup[index as usize])
+            })
+            .collect();
+
+        Self {
+            name,
+            size: IVec3::new(model.size.x as i32, model.size.z as i32, model.size.y as i32),
+            blocks,
+        }
+    }
+}
+
+pub fn load_vox_structure(path: &Path) -> Result<VoxStructure, VoxError> {
+    let bytes = std::fs::read(path)?;
+    let file = read_vox(&bytes)?;
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(VoxStructure::from_model(name, &file.models[0], &file.palette))
+}
+
+// ---------------------------------------------------------------------------
+// Writing
+// ---------------------------------------------------------------------------
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+/// Encodes a block region as a single-model `.vox` file. `blocks` are in
+/// world axes relative to the region minimum; air entries are skipped.
+pub fn write_vox(size: IVec3, blocks: &[(IVec3, BlockType)]) -> Result<Vec<u8>, VoxError> {
+    if size.min_element() <= 0 || size.max_element() > MAX_MODEL_SIZE {
+        return Err(VoxError::Format("region must be 1..=256 blocks on every axis"));
+    }
+
+    let mut size_chunk = Vec::with_capacity(12);
+    for v in [size.x, size.z, size.y] {
+        size_chunk.extend_from_slice(&(v as u32).to_le_bytes());
+    }
+
+    let solid: Vec<_> = blocks.iter().filter(|(_, b)| *b != BlockType::Air).collect();
+    let mut xyzi = Vec::with_capacity(4 + solid.len() * 4);
+    xyzi.extend_from_slice(&(solid.len() as u32).to_le_bytes());
+    for &&(p, block) in &solid {
+        xyzi.extend_from_slice(&[p.x as u8, p.z as u8, p.y as u8, palette_index(block)]);
+    }
+
+    let mut rgba = vec![0u8; 256 * 4];
+    for (i, block) in PALETTE_BLOCKS.iter().enumerate() {
+        let c = minimap_color(*block).to_srgba();
+        for (j, v) in [c.red, c.green, c.blue, c.alpha].into_iter().enumerate() {
+            rgba[i * 4 + j] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+
+    let mut children = Vec::new();
+    write_chunk(&mut children, b"SIZE", &size_chunk);
+    write_chunk(&mut children, b"XYZI", &xyzi);
+    write_chunk(&mut children, b"RGBA", &rgba);
+
+    let mut out = Vec::with_capacity(20 + children.len());
+    out.extend_from_slice(b"VOX ");
+    out.extend_from_slice(&VOX_VERSION.to_le_bytes());
+    out.extend_from_slice(b"MAIN");
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    out.extend_from_slice(&children);
+    Ok(out)
+}
+
+fn palette_index(block: BlockType) -> u8 {
+    PALETTE_BLOCKS
+        .iter()
+        .position(|&b| b == block)
+        .map(|i| i as u8 + 1)
+        .unwrap_or(1)
+}
+
+// ---------------------------------------------------------------------------
+// Systems
+// ---------------------------------------------------------------------------
+
+fn load_structures(mut library: ResMut<VoxLibrary>) {
+    let Ok(entries) = std::fs::read_dir(STRUCTURES_DIR) else {
+        return;
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vox")))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match load_vox_structure(&path) {
+            Ok(structure) => {
+                info!(
+                    "Loaded structure '{}' ({} blocks)",
+                    structure.name,
+                    structure.blocks.len()
+                );
+                library.structures.push(structure);
+            }
+            Err(e) => warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn cycle_structure(actions: Res<ActionState>, mut library: ResMut<VoxLibrary>) {
+    if !actions.just_pressed(Action::CycleStructure) || library.structures.is_empty() {
+        return;
+    }
+    library.selected = (library.selected + 1) % library.structures.len();
+    info!("Selected structure '{}'", library.structures[library.selected].name);
+}
+
+fn place_structure(
+    actions: Res<ActionState>,
+    library: Res<VoxLibrary>,
+    state: Res<BreakingState>,
+    mut editor: BlockEditor,
+) {
+    if !actions.just_pressed(Action::Place) {
+        return;
+    }
+    let Some(structure) = library.structures.get(library.selected) else {
+        return;
+    };
+    let Some(target) = state.target else { return };
+    if let Err(e) = editor.allow_bulk_edits() {
+        warn!("{}", e);
+        return;
+    }
+
+    // Centre the footprint on the targeted block and rest it on top
+    let origin = target + IVec3::new(-structure.size.x / 2, 1, -structure.size.z / 2);
+
+    // Only fill empty space, as one undo step
+    let edits: Vec<(IVec3, BlockType)> = structure
+        .blocks
+        .iter()
+        .map(|&(offset, block)| (origin + offset, block))
+        .filter(|&(pos, _)| editor.get_block(pos) == Some(BlockType::Air))
+        .collect();
+    let placed = editor.apply(edits).len();
+
+    info!("Placed structure '{}' ({} blocks)", structure.name, placed);
+}
+
+fn select_export_corners(
+    actions: Res<ActionState>,
+    state: Res<BreakingState>,
+    mut selection: ResMut<VoxSelection>,
+) {
+    let Some(target) = state.target else { return };
+
+    if actions.just_pressed(Action::ExportCornerA) {
+        selection.corner_a = Some(target);
+        info!("Export corner A: {}", target);
+    }
+    if actions.just_pressed(Action::ExportCornerB) {
+        selection.corner_b = Some(target);
+        info!("Export corner B: {}", target);
+    }
+}
+
+fn export_selection(
+    actions: Res<ActionState>,
+    selection: Res<VoxSelection>,
+    voxels: VoxelWorld,
+) {
+    if !actions.just_pressed(Action::ExportSelection) {
+        return;
+    }
+    let (Some(a), Some(b)) = (selection.corner_a, selection.corner_b) else {
+        warn!("Select both export corners (Z / X) before exporting");
+        return;
+    };
+
+    let min = a.min(b).max(IVec3::new(i32::MIN, 0, i32::MIN));
+    let max = a.max(b).min(IVec3::new(i32::MAX, CHUNK_HEIGHT as i32 - 1, i32::MAX));
+    let size = max - min + IVec3::ONE;
+    if size.max_element() > MAX_MODEL_SIZE {
+        warn!("Selection {} exceeds the .vox size limit of {}", size, MAX_MODEL_SIZE);
+        return;
+    }
+
+    let blocks: Vec<(IVec3, BlockType)> = voxels
+        .region(min, max)
+        .filter(|&(_, block)| block != BlockType::Air)
+        .map(|(pos, block)| (pos - min, block))
+        .collect();
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = Path::new(EXPORT_DIR).join(format!("region_{}.vox", stamp));
+
+    let result = write_vox(size, &blocks).and_then(|bytes| {
+        std::fs::create_dir_all(EXPORT_DIR)?;
+        std::fs::write(&path, bytes)?;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => info!("Exported {} blocks to {}", blocks.len(), path.display()),
+        Err(e) => warn!("Export failed: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One voxel of every palette block, plus an air entry that is skipped.
+    fn sample_blocks() -> Vec<(IVec3, BlockType)> {
+        let mut blocks: Vec<_> = PALETTE_BLOCKS
+            .iter()
+            .enumerate()
+            .map(|(i, &block)| (IVec3::new(i as i32 % 3, i as i32, i as i32 % 2), block))
+            .collect();
+        blocks.push((IVec3::new(2, 0, 1), BlockType::Air));
+        blocks
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let size = IVec3::new(3, PALETTE_BLOCKS.len() as i32, 2);
+        let blocks = sample_blocks();
+        let file = read_vox(&write_vox(size, &blocks).unwrap()).unwrap();
+
+        // Stored Z up: the world's height is the model's third axis
+        let model = &file.models[0];
+        assert_eq!(model.size, UVec3::new(3, 2, PALETTE_BLOCKS.len() as u32));
+        assert!(model.voxels.iter().any(|&(p, _)| p == UVec3::new(1, 0, 4)));
+
+        let structure = VoxStructure::from_model("sample".into(), model, &file.palette);
+        assert_eq!(structure.size, size);
+        let solid: Vec<_> = blocks.into_iter().filter(|&(_, b)| b != BlockType::Air).collect();
+        assert_eq!(structure.blocks, solid);
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let bytes = write_vox(IVec3::new(3, 7, 2), &sample_blocks()).unwrap();
+        // Ending right before the RGBA chunk is still a whole file
+        let without_palette = bytes.len() - (12 + 256 * 4);
+        for len in 0..bytes.len() {
+            match read_vox(&bytes[..len]) {
+                Err(VoxError::Format(_)) => {}
+                Ok(_) => assert_eq!(len, without_palette),
+                Err(e) => panic!("read {} of {} bytes: {}", len, bytes.len(), e),
+            }
+        }
+    }
+
+    #[test]
+    fn bad_header_is_an_error() {
+        let bytes = write_vox(IVec3::ONE, &[(IVec3::ZERO, BlockType::Stone)]).unwrap();
+        let corrupt = |at: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + value.len()].copy_from_slice(value);
+            read_vox(&bytes)
+        };
+        assert!(matches!(corrupt(0, b"RIFF"), Err(VoxError::Format(_))));
+        assert!(matches!(corrupt(8, b"NIAM"), Err(VoxError::Format(_))));
+        // A voxel count far beyond the chunk's content
+        let xyzi_count = 20 + 12 + 12 + 12;
+        assert!(matches!(corrupt(xyzi_count, &u32::MAX.to_le_bytes()), Err(VoxError::Format(_))));
+        // A SIZE chunk claiming more content than the file has
+        assert!(matches!(corrupt(24, &u32::MAX.to_le_bytes()), Err(VoxError::Format(_))));
+    }
+
+    #[test]
+    fn size_is_limited_to_256() {
+        let stone = [(IVec3::ZERO, BlockType::Stone)];
+        assert!(write_vox(IVec3::new(256, 1, 256), &stone).is_ok());
+        for size in [IVec3::new(257, 1, 1), IVec3::new(1, 257, 1), IVec3::new(1, 1, 0)] {
+            assert!(matches!(write_vox(size, &stone), Err(VoxError::Format(_))), "size {}", size);
+        }
+    }
+}
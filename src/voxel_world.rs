@@ -0,0 +1,138 @@
+use crate::block::BlockType;
+use crate::chunk::{CHUNK_HEIGHT, ChunkMap, chunk_of, local_of};
+use crate::client::NetClient;
+use crate::world::{BlockChanged, World as GameWorld, write_blocks};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// The six blocks sharing a face with a block
+pub const NEIGHBOURS: [IVec3; 6] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+
+/// Access to blocks by world position, wherever they are stored. Writes
+/// go through [`write_blocks`], so every change is sent as a
+/// [`BlockChanged`]; player edits that should be undoable go through
+/// [`crate::journal::BlockEditor`] instead.
+#[derive(SystemParam)]
+pub struct VoxelWorld<'w> {
+    world: Res<'w, GameWorld>,
+    map: ResMut<'w, ChunkMap>,
+    events: EventWriter<'w, BlockChanged>,
+    net: Option<ResMut<'w, NetClient>>,
+}
+
+impl VoxelWorld<'_> {
+    /// The block at `pos`, or `None` above or below the world and in
+    /// chunks that are not loaded.
+    pub fn get_block(&self, pos: IVec3) -> Option<BlockType> {
+        self.map.get_block(pos)
+    }
+
+    /// Sets the block at `pos`. Returns the change, if the block is loaded
+    /// and was different.
+    pub fn set_block(&mut self, pos: IVec3, block: BlockType) -> Option<BlockChanged> {
+        self.set_blocks([(pos, block)]).pop()
+    }
+
+    /// Sets many blocks at once, sending the changes as one batch. Returns
+    /// the changes made.
+    pub fn set_blocks(&mut self, edits: impl IntoIterator<Item = (IVec3, BlockType)>) -> Vec<BlockChanged> {
+        write_blocks(edits, &mut self.map, &mut self.events, &mut self.net)
+    }
+
+    /// Whether bodies collide with the block at `pos`. Unloaded space is
+    /// open.
+    pub fn is_solid(&self, pos: IVec3) -> bool {
+        self.get_block(pos).is_some_and(|b| b.is_collidable())
+    }
+
+    /// Whether the chunk column holding `pos` is loaded, at any height.
+    pub fn is_loaded(&self, pos: IVec3) -> bool {
+        self.map.contains(chunk_of(pos))
+    }
+
+    /// The entity of the loaded chunk holding `pos`.
+    pub fn chunk_entity(&self, pos: IVec3) -> Option<Entity> {
+        self.world.chunks.get(&chunk_of(pos)).copied()
+    }
+
+    /// The six face neighbours of `pos` and their blocks, `None` where
+    /// not loaded.
+    pub fn neighbours(&self, pos: IVec3) -> impl Iterator<Item = (IVec3, Option<BlockType>)> + '_ {
+        NEIGHBOURS.iter().map(move |&dir| (pos + dir, self.get_block(pos + dir)))
+    }
+
+    /// Every loaded block in the box from `min` to `max`, inclusive.
+    pub fn region(&self, min: IVec3, max: IVec3) -> impl Iterator<Item = (IVec3, BlockType)> + '_ {
+        let (min_y, max_y) = (min.y.max(0), max.y.min(CHUNK_HEIGHT as i32 - 1));
+        (min.x..=max.x).flat_map(move |x| {
+            (min_y..=max_y).flat_map(move |y| {
+                (min.z..=max.z).filter_map(move |z| {
+                    let pos = IVec3::new(x, y, z);
+                    Some((pos, self.get_block(pos)?))
+                })
+            })
+        })
+    }
+
+    /// The highest block in a column that is not air, with its height.
+    pub fn top_block(&self, x: i32, z: i32) -> Option<(i32, BlockType)> {
+        let pos = IVec3::new(x, 0, z);
+        let chunk = self.map.get(chunk_of(pos))?;
+        let (lx, _, lz) = local_of(pos);
+        (0..CHUNK_HEIGHT).rev().find_map(|y| {
+            let block = chunk.get_block(lx, y, lz);
+            (block != BlockType::Air).then_some((y as i32, block))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::SystemState;
+
+    #[test]
+    fn negative_positions_land_in_the_right_chunk() {
+        assert_eq!(chunk_of(IVec3::new(-1, 5, -1)), IVec3::new(-1, 0, -1));
+        assert_eq!(local_of(IVec3::new(-1, 5, -1)), (15, 5, 15));
+        assert_eq!(chunk_of(IVec3::new(-16, 5, -17)), IVec3::new(-1, 0, -2));
+        assert_eq!(local_of(IVec3::new(-16, 5, -17)), (0, 5, 15));
+        assert_eq!(chunk_of(IVec3::new(-17, 5, 16)), IVec3::new(-2, 0, 1));
+        assert_eq!(local_of(IVec3::new(-17, 5, 16)), (15, 5, 0));
+    }
+
+    #[test]
+    fn set_block_at_negative_positions() {
+        let mut world = World::new();
+        world.insert_resource(GameWorld::new(1));
+        world.init_resource::<Events<BlockChanged>>();
+        let mut map = ChunkMap::default();
+        map.insert(Chunk::new(IVec3::new(-1, 0, -1)));
+        map.insert(Chunk::new(IVec3::new(-1, 0, -2)));
+        world.insert_resource(map);
+
+        let mut state: SystemState<VoxelWorld> = SystemState::new(&mut world);
+        let mut voxels = state.get_mut(&mut world);
+        let a = IVec3::new(-1, 5, -1);
+        let b = IVec3::new(-16, 5, -17);
+        assert!(voxels.set_block(a, BlockType::Stone).is_some());
+        assert!(voxels.set_block(b, BlockType::Dirt).is_some());
+        // Unloaded, and already that block
+        assert!(voxels.set_block(IVec3::new(0, 5, 0), BlockType::Stone).is_none());
+        assert!(voxels.set_block(a, BlockType::Stone).is_none());
+        assert_eq!(voxels.get_block(a), Some(BlockType::Stone));
+        assert_eq!(voxels.get_block(b), Some(BlockType::Dirt));
+        state.apply(&mut world);
+
+        let map = world.resource::<ChunkMap>();
+        assert_eq!(map.get(IVec3::new(-1, 0, -1)).unwrap().get_block(15, 5, 15), BlockType::Stone);
+        assert_eq!(map.get(IVec3::new(-1, 0, -2)).unwrap().get_block(0, 5, 15), BlockType::Dirt);
+        assert_eq!(map.get(IVec3::new(-1, 0, -1)).unwrap().get_block(0, 5, 0), BlockType::Air);
+
+        let events = world.resource::<Events<BlockChanged>>();
+        let sent: Vec<_> = events.get_reader().read(events).map(|e| (e.pos, e.new)).collect();
+        assert_eq!(sent, vec![(a, BlockType::Stone), (b, BlockType::Dirt)]);
+    }
+}
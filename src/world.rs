@@ -1,60 +1,220 @@
 use crate::block::BlockType;
 use crate::block_registry::BlockRegistry;
-use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk};
+use crate::camera::{MainCamera, Player};
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE, Chunk, ChunkMap, chunk_of};
+use crate::chunk_cache::ChunkCache;
+use crate::client::NetClient;
+use crate::console::{CommandResult, ConsoleCommandsExt, parse_arg, parse_coord};
+use crate::erosion::{ErodedArea, ErosionCache, ErosionSettings};
+use crate::journal::{BlockEditor, EditJournal};
+use crate::physics::Velocity;
 use crate::tree_breaking::{TreePart, TreeRoot};
+use crate::voxel_world::{NEIGHBOURS, VoxelWorld};
+use bevy::math::Affine3A;
 use bevy::prelude::*;
+use bevy::render::primitives::{Aabb, Frustum};
 use noise::{NoiseFn, Perlin};
-use std::collections::{HashMap, HashSet};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-#[derive(Event)]
-pub struct RenderBlockAndNeighborsEvent {
-    pub world_pos: IVec3,
+/// How distance to the camera is measured when loading chunks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LoadShape {
+    Circle,
+    Square,
 }
 
 #[derive(Resource)]
 pub struct World {
     pub chunks: HashMap<IVec3, Entity>,
     pub noise: Perlin,
+    /// Chunks this close to the camera are loaded
     pub render_distance: i32,
+    /// Loaded chunks stay until this many chunks further out, so walking
+    /// along a border does not load and unload the same chunks over and over
+    pub unload_margin: i32,
+    pub load_shape: LoadShape,
     pub seed: u32,
 }
 
-impl Default for World {
-    fn default() -> Self {
-        let seed = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| (d.as_millis() & 0xFFFFFFFF) as u32)
-            .unwrap_or(42);
+impl World {
+    pub fn new(seed: u32) -> Self {
         info!("World seed: {}", seed);
         Self {
             chunks: HashMap::new(),
             noise: Perlin::new(seed),
             render_distance: 4,
+            unload_margin: 2,
+            load_shape: LoadShape::Circle,
             seed,
         }
     }
+
+    /// Distance in chunks between two chunk positions, in the load shape.
+    fn chunk_distance(&self, a: IVec3, b: IVec3) -> f32 {
+        let d = (a - b).abs();
+        match self.load_shape {
+            LoadShape::Circle => (d.x as f32).hypot(d.z as f32),
+            LoadShape::Square => d.x.max(d.z) as f32,
+        }
+    }
+
+    // Half a chunk of slack rounds the circle out, so it does not end in a
+    // single chunk on each axis
+    pub fn in_load_range(&self, center: IVec3, chunk_pos: IVec3) -> bool {
+        self.chunk_distance(center, chunk_pos) <= self.render_distance as f32 + 0.5
+    }
+
+    pub fn in_unload_range(&self, center: IVec3, chunk_pos: IVec3) -> bool {
+        self.chunk_distance(center, chunk_pos) > (self.render_distance + self.unload_margin) as f32 + 0.5
+    }
+}
+
+/// Where a chunk is on its way in and out of the world.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChunkState {
+    /// In load range, waiting for its turn
+    Queued,
+    /// Blocks being generated, or requested from the server
+    Generating,
+    /// Blocks are in the [`ChunkMap`]; visuals not spawned yet
+    Meshing,
+    Ready,
+    /// Out of range; dropped at the end of the loading systems
+    Unloading,
+}
+
+/// The state of every chunk being loaded, loaded or unloaded, and the
+/// order queued chunks are loaded in.
+#[derive(Resource, Default)]
+pub struct ChunkStates {
+    states: HashMap<IVec3, ChunkState>,
+    /// Queued chunks, most urgent first; sorted again every frame
+    queue: Vec<IVec3>,
+    meshing: VecDeque<IVec3>,
+    /// Generated trees of chunks waiting to be meshed
+    trees: HashMap<IVec3, Vec<Vec<TreeBlock>>>,
+}
+
+impl ChunkStates {
+    pub fn get(&self, chunk_pos: IVec3) -> Option<ChunkState> {
+        self.states.get(&chunk_pos).copied()
+    }
+
+    pub fn count(&self, state: ChunkState) -> usize {
+        self.states.values().filter(|&&s| s == state).count()
+    }
+
+    /// Takes up to `n` queued chunks, most urgent first, and marks them
+    /// as generating.
+    pub fn start_generating(&mut self, n: usize) -> Vec<IVec3> {
+        let taken: Vec<IVec3> = self.queue.drain(..n.min(self.queue.len())).collect();
+        for pos in &taken {
+            self.states.insert(*pos, ChunkState::Generating);
+        }
+        taken
+    }
+
+    /// Marks a chunk whose blocks are now in the [`ChunkMap`] as ready for
+    /// its visuals.
+    pub fn finish_generating(&mut self, chunk_pos: IVec3, trees: Option<Vec<Vec<TreeBlock>>>) {
+        self.states.insert(chunk_pos, ChunkState::Meshing);
+        self.meshing.push_back(chunk_pos);
+        if let Some(trees) = trees {
+            self.trees.insert(chunk_pos, trees);
+        }
+    }
+
+    /// The next chunk to spawn visuals for, with its trees, marked ready.
+    fn next_to_mesh(&mut self) -> Option<(IVec3, Option<Vec<Vec<TreeBlock>>>)> {
+        while let Some(pos) = self.meshing.pop_front() {
+            if self.get(pos) == Some(ChunkState::Meshing) {
+                self.states.insert(pos, ChunkState::Ready);
+                return Some((pos, self.trees.remove(&pos)));
+            }
+        }
+        None
+    }
+
+    fn take_unloading(&mut self) -> Vec<IVec3> {
+        let unloading: Vec<IVec3> = self
+            .states
+            .iter()
+            .filter(|(_, state)| **state == ChunkState::Unloading)
+            .map(|(pos, _)| *pos)
+            .collect();
+        for pos in &unloading {
+            self.states.remove(pos);
+            self.trees.remove(pos);
+        }
+        unloading
+    }
+}
+
+/// Generation options; changing them only affects chunks generated afterwards.
+#[derive(Resource)]
+pub struct WorldSettings {
+    pub erosion_enabled: bool,
+    pub erosion: ErosionSettings,
+    /// Far-terrain LOD meshes are drawn out to this many chunks
+    pub lod_distance: i32,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            erosion_enabled: true,
+            erosion: ErosionSettings::default(),
+            lod_distance: 32,
+        }
+    }
+}
+
+/// Present when a server owns the world; chunks then arrive over the
+/// network instead of being generated locally.
+#[derive(Resource)]
+pub struct RemoteWorld;
+
+/// The entity of a loaded chunk. Its blocks are in [`ChunkMap`]; the
+/// entity only parents their visuals.
+#[derive(Component)]
+pub struct ChunkRoot {
+    pub position: IVec3,
 }
 
 #[derive(Component)]
 pub struct BlockVisual {
     pub world_pos: IVec3,
+    pub block: BlockType,
 }
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<World>()
-            .add_event::<RenderBlockAndNeighborsEvent>()
+        // A client joining a server has already inserted the server's world
+        if !app.world().contains_resource::<World>() {
+            app.insert_resource(World::new(crate::save::load_or_create_seed()));
+        }
+        app.init_resource::<WorldSettings>()
+            .init_resource::<ErosionCache>()
+            .init_resource::<StaleVisuals>()
+            .add_event::<BlockChanged>()
+            .init_resource::<ChunkStates>()
+            .init_resource::<ChunkCache>()
             .add_systems(
                 Update,
-                (
-                    generate_chunks,
-                    sync_block_visuals,
-                    render_block_and_neighbors,
-                ),
-            );
+                (queue_chunks, generate_chunks, mesh_chunks, unload_chunks, refresh_block_visuals).chain(),
+            )
+            .add_console_command("seed", "", "Show the world seed", seed_command)
+            .add_console_command("setblock", "<x> <y> <z> <block>", "Place one block", setblock_command)
+            .add_console_command(
+                "fill",
+                "<x1> <y1> <z1> <x2> <y2> <z2> <block>",
+                "Fill a box with one block (up to 64×64×64)",
+                fill_command,
+            )
+            .add_console_command("render_distance", "[chunks]", "Show or set the render distance", render_distance_command)
+            .add_console_command("load_shape", "[circle|square]", "Show or set the shape chunks load in", load_shape_command);
     }
 }
 
@@ -74,29 +234,39 @@ enum TreeSize {
     Large,
 }
 
-struct TreeBlock {
+pub(crate) struct TreeBlock {
     wx: i32,
     wy: i32,
     wz: i32,
     is_leaves: bool,
 }
 
-struct TreeRng(u32);
+impl TreeBlock {
+    fn pos(&self) -> IVec3 {
+        IVec3::new(self.wx, self.wy, self.wz)
+    }
+
+    fn block(&self) -> BlockType {
+        if self.is_leaves { BlockType::Leaves } else { BlockType::Wood }
+    }
+}
+
+pub(crate) struct TreeRng(u32);
 
 impl TreeRng {
-    fn new(seed: u32) -> Self {
+    pub(crate) fn new(seed: u32) -> Self {
         Self(if seed == 0 { 0x1337 } else { seed })
     }
-    fn next(&mut self) -> u32 {
+    pub(crate) fn next(&mut self) -> u32 {
         self.0 ^= self.0 << 13;
         self.0 ^= self.0 >> 17;
         self.0 ^= self.0 << 5;
         self.0
     }
-    fn f32(&mut self) -> f32 {
+    pub(crate) fn f32(&mut self) -> f32 {
         (self.next() & 0xFFFFFF) as f32 / 16777216.0
     }
-    fn range(&mut self, min: i32, max: i32) -> i32 {
+    pub(crate) fn range(&mut self, min: i32, max: i32) -> i32 {
         if max <= min {
             min
         } else {
@@ -126,13 +296,63 @@ fn fbm_noise(
     total / max_value
 }
 
+pub const SEA_LEVEL: usize = 28;
+
+const RIVER_SCALE: f64 = 0.0035;
+const RIVER_WARP: f64 = 0.9;
+// Ridge value (1.0 on the river's centre line) where the bank starts / the bed is reached
+const RIVER_BANK_START: f64 = 0.975;
+const RIVER_BED_START: f64 = 0.992;
+const RIVER_BED: f64 = SEA_LEVEL as f64 - 2.0;
+
+const LAKE_CELL: i32 = 128;
+const LAKE_CHANCE: f64 = 0.4;
+const LAKE_RIM: f32 = 3.0;
+
+/// Surface height plus the y up to which air above it is filled with water.
+#[derive(Clone, Copy)]
+pub struct TerrainColumn {
+    pub height: usize,
+    pub water_level: usize,
+}
+
+impl TerrainColumn {
+    /// Beaches sit just above the water line, whichever water it is
+    pub fn is_shore(&self) -> bool {
+        self.height <= self.water_level + 1
+    }
+
+    pub fn surface_block(&self) -> BlockType {
+        if self.is_shore() {
+            BlockType::Sand
+        } else if self.height > 55 {
+            BlockType::Stone
+        } else {
+            BlockType::Grass
+        }
+    }
+}
+
+struct Lake {
+    center: Vec2,
+    radius: f32,
+    level: usize,
+}
+
 fn get_height(noise: &Perlin, x: i32, z: i32) -> usize {
+    sample_column(noise, x, z).height
+}
+
+pub fn sample_column(noise: &Perlin, x: i32, z: i32) -> TerrainColumn {
     const CITY_RADIUS: f32 = 48.0;
     const CITY_HEIGHT: usize = 35;
 
     let dist_from_origin = ((x as f32).powi(2) + (z as f32).powi(2)).sqrt();
     if dist_from_origin < CITY_RADIUS {
-        return CITY_HEIGHT;
+        return TerrainColumn {
+            height: CITY_HEIGHT,
+            water_level: SEA_LEVEL,
+        };
     }
 
     const BLEND_RADIUS: f32 = 80.0;
@@ -143,6 +363,38 @@ fn get_height(noise: &Perlin, x: i32, z: i32) -> usize {
         1.0
     };
 
+    let natural_height = natural_height(noise, x, z);
+    let mut height = CITY_HEIGHT as f32 * (1.0 - blend_t) + natural_height as f32 * blend_t;
+
+    // Rivers fade out towards the city so the spawn area stays flat
+    let channel = river_channel(noise, x, z) as f32 * blend_t;
+    if channel > 0.0 && height > RIVER_BED as f32 {
+        height += (RIVER_BED as f32 - height) * channel;
+    }
+
+    let mut water_level = SEA_LEVEL;
+    if let Some(lake) = lake_near(noise, x, z) {
+        let d = Vec2::new(x as f32, z as f32).distance(lake.center);
+        let level = lake.level as f32;
+        if d < lake.radius {
+            // Bowl-shaped basin, deepest in the middle
+            let f = 1.0 - d / lake.radius;
+            let depth = 2.0 + lake.radius / 4.0;
+            height = height.min(level - 1.0 - f.sqrt() * depth);
+            water_level = lake.level;
+        } else if d < lake.radius + LAKE_RIM {
+            // Rim keeps the water from spilling out of the basin
+            height = height.max(level + 1.0);
+        }
+    }
+
+    TerrainColumn {
+        height: (height.round() as usize).clamp(1, CHUNK_HEIGHT - 1),
+        water_level,
+    }
+}
+
+fn natural_height(noise: &Perlin, x: i32, z: i32) -> usize {
     let x_f = x as f64 * TERRAIN_SCALE;
     let z_f = z as f64 * TERRAIN_SCALE;
     let dist = ((x as f64).powi(2) + (z as f64).powi(2)).sqrt();
@@ -158,9 +410,84 @@ fn get_height(noise: &Perlin, x: i32, z: i32) -> usize {
     let height_variation =
         detail_noise * (15.0 * mountain_blend + mountain_intensity * 40.0) * DAMPENING;
 
-    let natural_height = (30.0 + height_variation).clamp(1.0, CHUNK_HEIGHT as f64 - 1.0) as usize;
-    let blended = CITY_HEIGHT as f32 * (1.0 - blend_t) + natural_height as f32 * blend_t;
-    blended.round() as usize
+    (30.0 + height_variation).clamp(1.0, CHUNK_HEIGHT as f64 - 1.0) as usize
+}
+
+/// 0.0 away from rivers, rising across the bank to 1.0 on the river bed.
+/// Rivers follow the zero crossings of a domain-warped noise field, which
+/// gives long connected channels instead of isolated pits.
+fn river_channel(noise: &Perlin, x: i32, z: i32) -> f64 {
+    let x_f = x as f64 * RIVER_SCALE;
+    let z_f = z as f64 * RIVER_SCALE;
+
+    let warp_x = fbm_noise(noise, x_f * 2.0 + 310.7, z_f * 2.0 + 120.3, 2, 0.5, 2.0);
+    let warp_z = fbm_noise(noise, x_f * 2.0 + 710.1, z_f * 2.0 + 530.9, 2, 0.5, 2.0);
+    let n = fbm_noise(
+        noise,
+        x_f + warp_x * RIVER_WARP + 900.5,
+        z_f + warp_z * RIVER_WARP + 900.5,
+        3,
+        0.45,
+        2.0,
+    );
+
+    let ridge = 1.0 - n.abs();
+    let t = ((ridge - RIVER_BANK_START) / (RIVER_BED_START - RIVER_BANK_START)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Lakes sit one per `LAKE_CELL` grid cell at most, well inside the cell so
+/// a column only ever has to look at its own cell. The water surface is the
+/// lowest point of the surrounding rim, i.e. where the basin would spill.
+fn lake_near(noise: &Perlin, x: i32, z: i32) -> Option<Lake> {
+    let cell_x = x.div_euclid(LAKE_CELL);
+    let cell_z = z.div_euclid(LAKE_CELL);
+
+    // Sampling the noise ties lake placement to the world seed
+    let roll = noise.get([cell_x as f64 * 0.731 + 0.37, cell_z as f64 * 0.593 + 0.11]);
+    let seed_mix = (cell_x as u32)
+        .wrapping_mul(83492791)
+        .wrapping_add((cell_z as u32).wrapping_mul(29765723))
+        ^ ((roll + 1.0) * 1_000_000.0) as u32;
+    let mut rng = TreeRng::new(seed_mix);
+    if rng.f32() as f64 >= LAKE_CHANCE {
+        return None;
+    }
+    let quarter = LAKE_CELL / 4;
+    let center = Vec2::new(
+        (cell_x * LAKE_CELL + rng.range(quarter, LAKE_CELL - quarter)) as f32,
+        (cell_z * LAKE_CELL + rng.range(quarter, LAKE_CELL - quarter)) as f32,
+    );
+    let radius = rng.range(8, 20) as f32;
+
+    if Vec2::new(x as f32, z as f32).distance(center) >= radius + LAKE_RIM {
+        return None;
+    }
+    // Keep the spawn city dry
+    if center.length() < 80.0 + radius + LAKE_RIM {
+        return None;
+    }
+
+    let level = (0..8)
+        .map(|i| {
+            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+            let p = center + Vec2::new(angle.cos(), angle.sin()) * (radius + LAKE_RIM);
+            natural_height(noise, p.x.round() as i32, p.y.round() as i32)
+        })
+        .min()
+        .unwrap_or(0);
+
+    // Anything at or below the sea is just part of the ocean; very high
+    // basins would leave no room for the bowl below the surface
+    if level <= SEA_LEVEL + 2 || level + 4 >= CHUNK_HEIGHT {
+        return None;
+    }
+
+    Some(Lake {
+        center,
+        radius,
+        level,
+    })
 }
 
 fn add_leaf_clump(
@@ -347,17 +674,27 @@ fn tree_size_at(wx: i32, wz: i32, noise: &Perlin) -> TreeSize {
     }
 }
 
-fn generate_terrain(chunk: &mut Chunk, noise: &Perlin) -> Vec<(i32, i32, i32, TreeSize)> {
+fn generate_terrain(
+    chunk: &mut Chunk,
+    noise: &Perlin,
+    eroded: Option<&ErodedArea>,
+) -> Vec<(i32, i32, i32, TreeSize)> {
     let mut tree_positions = Vec::new();
     for x in 0..CHUNK_SIZE {
         for z in 0..CHUNK_SIZE {
             let world_x = chunk.position.x * CHUNK_SIZE as i32 + x as i32;
             let world_z = chunk.position.z * CHUNK_SIZE as i32 + z as i32;
-            let height = get_height(noise, world_x, world_z);
+            let mut column = sample_column(noise, world_x, world_z);
+            if let Some(area) = eroded {
+                let eroded_height = column.height as f32 + area.delta(world_x, world_z);
+                column.height = (eroded_height.round() as usize).clamp(1, CHUNK_HEIGHT - 1);
+            }
+            let height = column.height;
+            let shore = column.is_shore();
 
             for y in 0..CHUNK_HEIGHT {
                 let block = if y > height {
-                    if y <= 28 {
+                    if y <= column.water_level {
                         BlockType::Water
                     } else {
                         BlockType::Air
@@ -366,15 +703,9 @@ fn generate_terrain(chunk: &mut Chunk, noise: &Perlin) -> Vec<(i32, i32, i32, Tr
                     let depth_from_surface = height.saturating_sub(y);
 
                     if depth_from_surface == 0 {
-                        if height <= 29 {
-                            BlockType::Sand
-                        } else if height > 55 {
-                            BlockType::Stone
-                        } else {
-                            BlockType::Grass
-                        }
+                        column.surface_block()
                     } else if depth_from_surface <= 3 {
-                        if height <= 29 {
+                        if shore {
                             BlockType::Sand
                         } else {
                             BlockType::Dirt
@@ -387,7 +718,7 @@ fn generate_terrain(chunk: &mut Chunk, noise: &Perlin) -> Vec<(i32, i32, i32, Tr
             }
 
             let dist_from_origin = ((world_x as f32).powi(2) + (world_z as f32).powi(2)).sqrt();
-            if height > 32 && height < 52 && dist_from_origin > 40.0 {
+            if height > 32 && height < 52 && !shore && dist_from_origin > 40.0 {
                 let grid_x = world_x.div_euclid(TREE_SPACING) * TREE_SPACING;
                 let grid_z = world_z.div_euclid(TREE_SPACING) * TREE_SPACING;
 
@@ -415,267 +746,312 @@ fn generate_terrain(chunk: &mut Chunk, noise: &Perlin) -> Vec<(i32, i32, i32, Tr
     tree_positions
 }
 
-fn generate_chunks(
-    mut commands: Commands,
-    mut world: ResMut<World>,
-    asset_server: Res<AssetServer>,
-    registry: Res<BlockRegistry>,
-    camera_query: Query<&Transform, With<Camera>>,
-) {
-    if !registry.loaded {
-        return;
-    }
-    let camera_pos = if let Ok(t) = camera_query.get_single() {
-        t.translation
-    } else {
-        return;
-    };
-
-    let camera_chunk = IVec3::new(
-        (camera_pos.x / CHUNK_SIZE as f32).floor() as i32,
-        0,
-        (camera_pos.z / CHUNK_SIZE as f32).floor() as i32,
-    );
-    let render_distance = world.render_distance;
-
-    let mut pending: Vec<IVec3> = (-render_distance..=render_distance)
-        .flat_map(|cx| {
-            (-render_distance..=render_distance)
-                .map(move |cz| IVec3::new(camera_chunk.x + cx, 0, camera_chunk.z + cz))
-        })
-        .filter(|pos| !world.chunks.contains_key(pos))
-        .collect();
-
-    pending.sort_by_key(|pos| {
-        let dx = pos.x - camera_chunk.x;
-        let dz = pos.z - camera_chunk.z;
-        dx * dx + dz * dz
+/// Builds the blocks of one chunk, trees included. Also returns the trees
+/// rooted in it so they can be spawned as breakable units.
+pub(crate) fn generate_chunk_blocks(
+    chunk_pos: IVec3,
+    world: &World,
+    settings: &WorldSettings,
+    erosion: &mut ErosionCache,
+) -> (Chunk, Vec<Vec<TreeBlock>>) {
+    let mut chunk = Chunk::new(chunk_pos);
+    let eroded = settings.erosion_enabled.then(|| {
+        let min = IVec2::new(chunk_pos.x, chunk_pos.z) * CHUNK_SIZE as i32;
+        let max = min + IVec2::splat(CHUNK_SIZE as i32 - 1);
+        erosion.get_or_erode_area(min, max, &world.noise, world.seed, &settings.erosion)
     });
-
-    for chunk_pos in pending.iter().take(2) {
-        let mut chunk = Chunk::new(*chunk_pos);
-        let tree_positions = generate_terrain(&mut chunk, &world.noise);
-        let surface_blocks = chunk.get_surface_blocks();
-
-        let mut rendered_trees = Vec::new();
-        for (wx, wy, wz, size) in tree_positions {
-            let tree_blocks = build_tree_blocks(wx, wy, wz, size);
-
-            for tb in &tree_blocks {
-                let lx = tb.wx - (chunk_pos.x * CHUNK_SIZE as i32);
-                let lz = tb.wz - (chunk_pos.z * CHUNK_SIZE as i32);
-                if lx >= 0
-                    && lx < CHUNK_SIZE as i32
-                    && lz >= 0
-                    && lz < CHUNK_SIZE as i32
-                    && tb.wy >= 0
-                    && tb.wy < CHUNK_HEIGHT as i32
-                {
-                    chunk.set_block(
-                        lx as usize,
-                        tb.wy as usize,
-                        lz as usize,
-                        if tb.is_leaves {
-                            BlockType::Leaves
-                        } else {
-                            BlockType::Wood
-                        },
-                    );
-                }
+    let tree_positions = generate_terrain(&mut chunk, &world.noise, eroded.as_ref());
+
+    let mut rendered_trees = Vec::new();
+    for (wx, wy, wz, size) in tree_positions {
+        let tree_blocks = build_tree_blocks(wx, wy, wz, size);
+
+        for tb in &tree_blocks {
+            let lx = tb.wx - (chunk_pos.x * CHUNK_SIZE as i32);
+            let lz = tb.wz - (chunk_pos.z * CHUNK_SIZE as i32);
+            if lx >= 0
+                && lx < CHUNK_SIZE as i32
+                && lz >= 0
+                && lz < CHUNK_SIZE as i32
+                && tb.wy >= 0
+                && tb.wy < CHUNK_HEIGHT as i32
+            {
+                chunk.set_block(
+                    lx as usize,
+                    tb.wy as usize,
+                    lz as usize,
+                    if tb.is_leaves {
+                        BlockType::Leaves
+                    } else {
+                        BlockType::Wood
+                    },
+                );
             }
-            rendered_trees.push(tree_blocks);
         }
+        rendered_trees.push(tree_blocks);
+    }
+    (chunk, rendered_trees)
+}
 
-        let chunk_entity = commands
-            .spawn((SpatialBundle::default(), chunk))
-            .with_children(|parent| {
-                for (lx, ly, lz, block_type) in surface_blocks {
-                    if matches!(block_type, BlockType::Wood | BlockType::Leaves) {
-                        continue;
-                    }
-                    let wx = chunk_pos.x * CHUNK_SIZE as i32 + lx as i32;
-                    let wz = chunk_pos.z * CHUNK_SIZE as i32 + lz as i32;
-                    let (scene_path, y_offset) = block_visual(block_type);
-
-                    parent.spawn((
-                        SceneBundle {
-                            scene: asset_server.load(scene_path),
-                            transform: Transform::from_translation(Vec3::new(
-                                wx as f32 + 0.5,
-                                ly as f32 + 0.5 + y_offset,
-                                wz as f32 + 0.5,
-                            ))
-                            .with_scale(Vec3::splat(1.06)),
-                            ..default()
-                        },
-                        BlockVisual {
-                            world_pos: IVec3::new(wx, ly as i32, wz),
-                        },
-                    ));
+/// Spawns the entity and block visuals of a chunk already in the
+/// [`ChunkMap`] and registers it in `world.chunks`. Chunks received from a server come without tree
+/// information (`trees` is `None`); their wood and leaves are drawn as
+/// plain blocks.
+fn spawn_chunk(
+    commands: &mut Commands,
+    world: &mut World,
+    map: &ChunkMap,
+    asset_server: &AssetServer,
+    chunk_pos: IVec3,
+    trees: Option<Vec<Vec<TreeBlock>>>,
+) {
+    let Some(chunk) = map.get(chunk_pos) else { return };
+    let surface_blocks = chunk.get_surface_blocks();
+    let separate_trees = trees.is_some();
+    let rendered_trees = trees.unwrap_or_default();
+
+    let chunk_entity = commands
+        .spawn((SpatialBundle::default(), ChunkRoot { position: chunk_pos }))
+        .with_children(|parent| {
+            for (lx, ly, lz, block_type) in surface_blocks {
+                if separate_trees && matches!(block_type, BlockType::Wood | BlockType::Leaves) {
+                    continue;
                 }
+                let wx = chunk_pos.x * CHUNK_SIZE as i32 + lx as i32;
+                let wz = chunk_pos.z * CHUNK_SIZE as i32 + lz as i32;
+                let (scene_path, y_offset) = block_visual(block_type);
+
+                parent.spawn((
+                    SceneBundle {
+                        scene: asset_server.load(scene_path),
+                        transform: Transform::from_translation(Vec3::new(
+                            wx as f32 + 0.5,
+                            ly as f32 + 0.5 + y_offset,
+                            wz as f32 + 0.5,
+                        ))
+                        .with_scale(Vec3::splat(1.06)),
+                        ..default()
+                    },
+                    BlockVisual {
+                        world_pos: IVec3::new(wx, ly as i32, wz),
+                        block: block_type,
+                    },
+                ));
+            }
 
-                for tree_blocks in rendered_trees {
-                    let mut wood_count = 0;
-                    let mut leaves_count = 0;
-                    let mut pos_storage = Vec::with_capacity(tree_blocks.len());
+            for tree_blocks in rendered_trees {
+                let mut wood_count = 0;
+                let mut leaves_count = 0;
+                let mut pos_storage = Vec::with_capacity(tree_blocks.len());
 
-                    for tb in &tree_blocks {
-                        pos_storage.push(IVec3::new(tb.wx, tb.wy, tb.wz));
-                        if tb.is_leaves {
-                            leaves_count += 1;
-                        } else {
-                            wood_count += 1;
-                        }
+                for tb in &tree_blocks {
+                    pos_storage.push(IVec3::new(tb.wx, tb.wy, tb.wz));
+                    if tb.is_leaves {
+                        leaves_count += 1;
+                    } else {
+                        wood_count += 1;
                     }
-
-                    parent
-                        .spawn((
-                            SpatialBundle::default(),
-                            TreeRoot {
-                                wood_count,
-                                leaves_count,
-                                blocks: pos_storage,
-                            },
-                        ))
-                        .with_children(|tree_builder| {
-                            for tb in tree_blocks {
-                                let scene_path = if tb.is_leaves {
-                                    "leaves.glb#Scene0"
-                                } else {
-                                    "wood.glb#Scene0"
-                                };
-                                let center = Vec3::new(
-                                    tb.wx as f32 + 0.5,
-                                    tb.wy as f32 + 0.5,
-                                    tb.wz as f32 + 0.5,
-                                );
-
-                                tree_builder.spawn((
-                                    SceneBundle {
-                                        scene: asset_server.load(scene_path),
-                                        transform: Transform::from_translation(center)
-                                            .with_scale(Vec3::splat(1.0)),
-                                        ..default()
-                                    },
-                                    TreePart,
-                                ));
-                            }
-                        });
                 }
-            })
-            .id();
-
-        world.chunks.insert(*chunk_pos, chunk_entity);
-    }
 
-    let chunks_to_remove: Vec<IVec3> = world
-        .chunks
-        .keys()
-        .filter(|&&pos| {
-            (pos.x - camera_chunk.x).abs() > render_distance
-                || (pos.z - camera_chunk.z).abs() > render_distance
+                parent
+                    .spawn((
+                        SpatialBundle::default(),
+                        TreeRoot {
+                            wood_count,
+                            leaves_count,
+                            blocks: pos_storage,
+                        },
+                    ))
+                    .with_children(|tree_builder| {
+                        for tb in tree_blocks {
+                            let scene_path = if tb.is_leaves {
+                                "leaves.glb#Scene0"
+                            } else {
+                                "wood.glb#Scene0"
+                            };
+                            let center = Vec3::new(
+                                tb.wx as f32 + 0.5,
+                                tb.wy as f32 + 0.5,
+                                tb.wz as f32 + 0.5,
+                            );
+
+                            tree_builder.spawn((
+                                SceneBundle {
+                                    scene: asset_server.load(scene_path),
+                                    transform: Transform::from_translation(center)
+                                        .with_scale(Vec3::splat(1.0)),
+                                    ..default()
+                                },
+                                TreePart,
+                            ));
+                        }
+                    });
+            }
         })
-        .copied()
-        .collect();
+        .id();
 
-    for chunk_pos in chunks_to_remove {
-        if let Some(entity) = world.chunks.remove(&chunk_pos) {
-            commands.entity(entity).despawn_recursive();
-        }
+    world.chunks.insert(chunk_pos, chunk_entity);
+}
+
+/// Drops a loaded chunk's blocks and its entity with all its visuals.
+fn despawn_chunk(commands: &mut Commands, world: &mut World, map: &mut ChunkMap, chunk_pos: IVec3) {
+    map.remove(chunk_pos);
+    if let Some(entity) = world.chunks.remove(&chunk_pos) {
+        commands.entity(entity).despawn_recursive();
     }
 }
 
-fn sync_block_visuals(
-    mut commands: Commands,
+/// Queues chunks that came into load range, most urgent first, and marks
+/// loaded ones past the unload distance for unloading.
+fn queue_chunks(
     world: Res<World>,
-    chunks: Query<&Chunk>,
-    visuals: Query<(Entity, &BlockVisual)>,
-    asset_server: Res<AssetServer>,
+    mut states: ResMut<ChunkStates>,
+    registry: Res<BlockRegistry>,
+    camera_query: Query<(&Transform, &Frustum), With<MainCamera>>,
+    player_query: Query<&Velocity, With<Player>>,
 ) {
-    let mut broken_positions = Vec::new();
-
-    for (entity, visual) in visuals.iter() {
-        let chunk_pos = IVec3::new(
-            visual.world_pos.x.div_euclid(CHUNK_SIZE as i32),
-            0,
-            visual.world_pos.z.div_euclid(CHUNK_SIZE as i32),
-        );
+    if !registry.loaded {
+        return;
+    }
+    let Ok((camera, frustum)) = camera_query.get_single() else { return };
+    let camera_pos = camera.translation;
+    let center = chunk_of(camera_pos.floor().as_ivec3());
+    let velocity = player_query.get_single().map_or(Vec3::ZERO, |v| v.0);
+
+    let radius = world.render_distance;
+    for dx in -radius..=radius {
+        for dz in -radius..=radius {
+            let pos = center + IVec3::new(dx, 0, dz);
+            if world.in_load_range(center, pos) && !states.states.contains_key(&pos) {
+                states.states.insert(pos, ChunkState::Queued);
+            }
+        }
+    }
 
-        let Some(&chunk_entity) = world.chunks.get(&chunk_pos) else {
-            continue;
-        };
-        let Ok(chunk) = chunks.get(chunk_entity) else {
-            continue;
-        };
+    // Nothing is loaded yet for queued chunks or ones still on their way
+    // from the server, so they are dropped as soon as they leave the range
+    states.states.retain(|&pos, state| match state {
+        ChunkState::Queued => world.in_load_range(center, pos),
+        ChunkState::Generating => !world.in_unload_range(center, pos),
+        _ => true,
+    });
+    for (pos, state) in states.states.iter_mut() {
+        if matches!(state, ChunkState::Meshing | ChunkState::Ready) && world.in_unload_range(center, *pos) {
+            *state = ChunkState::Unloading;
+        }
+    }
 
-        let lx = visual.world_pos.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let lz = visual.world_pos.z.rem_euclid(CHUNK_SIZE as i32) as usize;
+    let mut queue: Vec<(IVec3, f32)> = states
+        .states
+        .iter()
+        .filter(|(_, state)| **state == ChunkState::Queued)
+        .map(|(&pos, _)| (pos, load_priority(pos, camera_pos, frustum, velocity)))
+        .collect();
+    queue.sort_by(|a, b| a.1.total_cmp(&b.1));
+    states.queue = queue.into_iter().map(|(pos, _)| pos).collect();
+}
 
-        if chunk.get_block(lx, visual.world_pos.y as usize, lz) == BlockType::Air {
-            commands.entity(entity).despawn_recursive();
-            broken_positions.push(visual.world_pos);
-        }
+/// How soon a chunk should load; lower is sooner. Starts from the squared
+/// distance to the camera, and is cut for chunks in view and ahead of
+/// where the player is going.
+fn load_priority(chunk_pos: IVec3, camera_pos: Vec3, frustum: &Frustum, velocity: Vec3) -> f32 {
+    let min = (chunk_pos * CHUNK_SIZE as i32).as_vec3();
+    let offset = (min + Vec3::new(0.5, 0.0, 0.5) * CHUNK_SIZE as f32 - camera_pos).with_y(0.0);
+    let mut priority = offset.length_squared();
+
+    let aabb = Aabb::from_min_max(min, min + Vec3::new(CHUNK_SIZE as f32, CHUNK_HEIGHT as f32, CHUNK_SIZE as f32));
+    if frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, true) {
+        priority *= IN_VIEW_PRIORITY;
     }
+    let heading = velocity.with_y(0.0);
+    if heading.length() > MIN_HEADING_SPEED && offset.normalize_or_zero().dot(heading.normalize()) > HEADING_COS {
+        priority *= AHEAD_PRIORITY;
+    }
+    priority
+}
 
-    if broken_positions.is_empty() {
+#[allow(clippy::too_many_arguments)]
+fn generate_chunks(
+    mut map: ResMut<ChunkMap>,
+    mut states: ResMut<ChunkStates>,
+    world: Res<World>,
+    settings: Res<WorldSettings>,
+    mut erosion: ResMut<ErosionCache>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    remote: Option<Res<RemoteWorld>>,
+    journal: Res<EditJournal>,
+    mut cache: ResMut<ChunkCache>,
+    mut stale: ResMut<StaleVisuals>,
+) {
+    let Ok(camera) = camera_query.get_single() else { return };
+    erosion.trim(ErosionCache::region_of(
+        camera.translation.x.floor() as i32,
+        camera.translation.z.floor() as i32,
+    ));
+
+    // Remote chunks are requested from the server instead
+    if remote.is_some() {
         return;
     }
-
-    let mut existing_visuals = HashSet::new();
-    for (_, visual) in visuals.iter() {
-        existing_visuals.insert(visual.world_pos);
+    for chunk_pos in states.start_generating(GENERATED_PER_FRAME) {
+        // A cached or saved chunk comes back as it was left, with its
+        // trees drawn block by block. A file saved earlier may predate
+        // later edits, which the journal replay catches up on
+        if journal.has_edits(chunk_pos)
+            && let Some(mut chunk) = cache.take(chunk_pos)
+        {
+            journal.replay(&mut chunk);
+            map.insert(chunk);
+            states.finish_generating(chunk_pos, None);
+            continue;
+        }
+        let (mut chunk, mut trees) = generate_chunk_blocks(chunk_pos, &world, &settings, &mut erosion);
+        journal.replay(&mut chunk);
+        // An edited tree is no longer the generated one; whatever is
+        // left of it is drawn block by block
+        trees.retain(|tree| {
+            let intact = tree.iter().all(|b| journal.edited_block(b.pos()).is_none_or(|e| e == b.block()));
+            if !intact {
+                stale.positions.extend(tree.iter().map(TreeBlock::pos));
+            }
+            intact
+        });
+        map.insert(chunk);
+        states.finish_generating(chunk_pos, Some(trees));
     }
+}
 
-    for pos in broken_positions {
-        let neighbors = [
-            pos + IVec3::X,
-            pos - IVec3::X,
-            pos + IVec3::Y,
-            pos - IVec3::Y,
-            pos + IVec3::Z,
-            pos - IVec3::Z,
-        ];
-
-        for n_pos in neighbors {
-            if existing_visuals.contains(&n_pos) {
-                continue;
-            }
+fn mesh_chunks(
+    mut commands: Commands,
+    mut world: ResMut<World>,
+    map: Res<ChunkMap>,
+    mut states: ResMut<ChunkStates>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in 0..MESHED_PER_FRAME {
+        let Some((chunk_pos, trees)) = states.next_to_mesh() else { break };
+        spawn_chunk(&mut commands, &mut world, &map, &asset_server, chunk_pos, trees);
+    }
+}
 
-            let c_pos = IVec3::new(
-                n_pos.x.div_euclid(CHUNK_SIZE as i32),
-                0,
-                n_pos.z.div_euclid(CHUNK_SIZE as i32),
-            );
-            if let Some(&chunk_entity) = world.chunks.get(&c_pos) {
-                if let Ok(chunk) = chunks.get(chunk_entity) {
-                    if n_pos.y >= 0 && n_pos.y < CHUNK_HEIGHT as i32 {
-                        let lx = n_pos.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-                        let lz = n_pos.z.rem_euclid(CHUNK_SIZE as i32) as usize;
-                        let b_type = chunk.get_block(lx, n_pos.y as usize, lz);
-
-                        if b_type != BlockType::Air {
-                            let (scene_path, y_offset) = block_visual(b_type);
-
-                            commands.entity(chunk_entity).with_children(|parent| {
-                                parent.spawn((
-                                    SceneBundle {
-                                        scene: asset_server.load(scene_path),
-                                        transform: Transform::from_translation(Vec3::new(
-                                            n_pos.x as f32 + 0.5,
-                                            n_pos.y as f32 + 0.5 + y_offset,
-                                            n_pos.z as f32 + 0.5,
-                                        ))
-                                        .with_scale(Vec3::splat(1.06)),
-                                        ..default()
-                                    },
-                                    BlockVisual { world_pos: n_pos },
-                                ));
-                            });
-                        }
-                    }
-                }
-            }
+fn unload_chunks(
+    mut commands: Commands,
+    mut world: ResMut<World>,
+    mut map: ResMut<ChunkMap>,
+    mut states: ResMut<ChunkStates>,
+    mut cache: ResMut<ChunkCache>,
+    journal: Res<EditJournal>,
+    remote: Option<Res<RemoteWorld>>,
+) {
+    for chunk_pos in states.take_unloading() {
+        // Edited chunks are kept as they are; a server keeps its own
+        if remote.is_none()
+            && journal.has_edits(chunk_pos)
+            && let Some(chunk) = map.get(chunk_pos)
+        {
+            cache.store(chunk);
         }
+        despawn_chunk(&mut commands, &mut world, &mut map, chunk_pos);
     }
 }
 
@@ -686,6 +1062,8 @@ fn block_visual(block: BlockType) -> (&'static str, f32) {
         BlockType::Stone => ("stone.glb#Scene0", 0.0),
         BlockType::Sand => ("sand.glb#Scene0", 0.0),
         BlockType::Water => ("water.glb#Scene0", 0.0),
+        BlockType::Wood => ("wood.glb#Scene0", 0.0),
+        BlockType::Leaves => ("leaves.glb#Scene0", 0.0),
         _ => ("grass.glb#Scene0", 0.0),
     }
 }
@@ -694,100 +1072,212 @@ pub fn get_spawn_height(noise: &Perlin) -> f32 {
     get_height(noise, 0, 0) as f32 + 2.0
 }
 
-pub fn render_block_and_neighbors(
-    mut events: EventReader<RenderBlockAndNeighborsEvent>,
-    world: Res<World>,
-    chunks: Query<&Chunk>,
-    visuals: Query<&BlockVisual>,
+// Chunks generated and given visuals per frame, to spread the work out
+const GENERATED_PER_FRAME: usize = 2;
+const MESHED_PER_FRAME: usize = 4;
+// Load priority factors: chunks in view load as if half as far away, and
+// chunks within about 45° of the player's heading a further √2 closer
+const IN_VIEW_PRIORITY: f32 = 0.25;
+const AHEAD_PRIORITY: f32 = 0.5;
+const HEADING_COS: f32 = 0.7;
+const MIN_HEADING_SPEED: f32 = 1.0;
+
+pub const MAX_EDIT_VOLUME: i64 = 64 * 64 * 64;
+pub const MAX_RENDER_DISTANCE: i32 = 16;
+
+/// Sent for every block [`write_blocks`] changed, whoever changed it:
+/// block visuals, the minimap, mob paths and the edit journal all follow
+/// the world through these.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BlockChanged {
+    pub pos: IVec3,
+    pub old: BlockType,
+    pub new: BlockType,
+}
+
+/// Blocks whose visuals need checking without having changed, such as
+/// what is left of an edited tree when its chunk is generated again.
+#[derive(Resource, Default)]
+struct StaleVisuals {
+    positions: Vec<IVec3>,
+}
+
+/// Writes a batch of blocks into loaded chunks, sending [`BlockChanged`]
+/// for each one that changed and forwarding it to the server when
+/// connected. Positions outside loaded chunks are skipped. Returns the
+/// changes in write order.
+///
+/// Gameplay edits should go through [`BlockEditor`] instead, which also
+/// makes them one undo step.
+pub fn write_blocks(
+    edits: impl IntoIterator<Item = (IVec3, BlockType)>,
+    map: &mut ChunkMap,
+    events: &mut EventWriter<BlockChanged>,
+    net: &mut Option<ResMut<NetClient>>,
+) -> Vec<BlockChanged> {
+    let mut changed = Vec::new();
+    for (pos, block) in edits {
+        let Some(old) = map.set_block(pos, block) else { continue };
+        if old == block {
+            continue;
+        }
+        if let Some(net) = net.as_mut() {
+            net.send_block(pos, block);
+        }
+        changed.push(BlockChanged { pos, old, new: block });
+    }
+    events.send_batch(changed.iter().copied());
+    changed
+}
+
+/// Brings block visuals in line with changed blocks: every changed block
+/// and its neighbours gets a model if it is exposed to air, and loses it if
+/// it was removed, replaced or buried. A batch of any size is handled in
+/// one pass.
+fn refresh_block_visuals(
     mut commands: Commands,
+    // VoxelWorld can send BlockChanged, so the two can't be borrowed at once
+    mut params: ParamSet<(EventReader<BlockChanged>, VoxelWorld)>,
+    mut stale: ResMut<StaleVisuals>,
+    visuals: Query<(Entity, &BlockVisual)>,
     asset_server: Res<AssetServer>,
 ) {
-    fn spawn_visual_if_needed(
-        world_pos: IVec3,
-        world: &World,
-        chunks: &Query<&Chunk>,
-        visuals: &Query<&BlockVisual>,
-        commands: &mut Commands,
-        asset_server: &AssetServer,
-    ) {
-        if visuals.iter().any(|v| v.world_pos == world_pos) {
-            return;
-        }
-
-        let chunk_coord = IVec3::new(
-            world_pos.x.div_euclid(CHUNK_SIZE as i32),
-            world_pos.y.div_euclid(CHUNK_HEIGHT as i32),
-            world_pos.z.div_euclid(CHUNK_SIZE as i32),
-        );
-        let Some(&chunk_entity) = world.chunks.get(&chunk_coord) else {
-            return;
-        };
-        let Ok(chunk) = chunks.get(chunk_entity) else {
-            return;
-        };
+    let changed: Vec<IVec3> = params.p0().read().map(|c| c.pos).collect();
+    if changed.is_empty() && stale.positions.is_empty() {
+        return;
+    }
+    let voxels = params.p1();
+    let shown: HashMap<IVec3, (Entity, BlockType)> = visuals.iter().map(|(e, v)| (v.world_pos, (e, v.block))).collect();
+    let mut candidates = HashSet::new();
+    for pos in changed.into_iter().chain(stale.positions.drain(..)) {
+        candidates.insert(pos);
+        candidates.extend(NEIGHBOURS.iter().map(|&dir| pos + dir));
+    }
 
-        let local_x = world_pos.x.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let local_y = world_pos.y as usize;
-        let local_z = world_pos.z.rem_euclid(CHUNK_SIZE as i32) as usize;
+    for pos in candidates {
+        let Some(block) = voxels.get_block(pos) else { continue };
+        // Unloaded neighbours count as open so chunk borders stay drawn
+        let exposed = voxels.neighbours(pos).any(|(_, b)| b.is_none_or(|b| b == BlockType::Air));
+        let wanted = block != BlockType::Air && exposed;
 
-        if local_x >= CHUNK_SIZE || local_y >= CHUNK_HEIGHT || local_z >= CHUNK_SIZE {
-            return;
+        match shown.get(&pos) {
+            Some(&(_, current)) if wanted && current == block => continue,
+            Some(&(entity, _)) => commands.entity(entity).despawn_recursive(),
+            None => {}
         }
-
-        let block = chunk.get_block(local_x, local_y, local_z);
-        if block == BlockType::Air || block.is_transparent() {
-            return;
+        if !wanted {
+            continue;
         }
-
+        let Some(chunk_entity) = voxels.chunk_entity(pos) else { continue };
         let (scene_path, y_offset) = block_visual(block);
         commands.entity(chunk_entity).with_children(|parent| {
             parent.spawn((
                 SceneBundle {
                     scene: asset_server.load(scene_path),
                     transform: Transform::from_translation(Vec3::new(
-                        world_pos.x as f32 + 0.5,
-                        world_pos.y as f32 + 0.5 + y_offset,
-                        world_pos.z as f32 + 0.5,
+                        pos.x as f32 + 0.5,
+                        pos.y as f32 + 0.5 + y_offset,
+                        pos.z as f32 + 0.5,
                     ))
-                    .with_scale(Vec3::splat(1.07)),
+                    .with_scale(Vec3::splat(1.06)),
                     ..default()
                 },
-                BlockVisual { world_pos },
+                BlockVisual { world_pos: pos, block },
             ));
         });
     }
+}
 
-    const DIRS: [IVec3; 6] = [
-        IVec3::X,
-        IVec3::NEG_X,
-        IVec3::Y,
-        IVec3::NEG_Y,
-        IVec3::Z,
-        IVec3::NEG_Z,
-    ];
-
-    for event in events.read() {
-        let center = event.world_pos;
-
-        spawn_visual_if_needed(
-            center,
-            &world,
-            &chunks,
-            &visuals,
-            &mut commands,
-            &asset_server,
-        );
+pub(crate) fn player_feet(players: &Query<(&Player, &Transform)>) -> Vec3 {
+    players
+        .get_single()
+        .map(|(player, transform)| transform.translation - Vec3::Y * player.height())
+        .unwrap_or(Vec3::ZERO)
+}
 
-        for dir in DIRS {
-            let neighbor = center + dir;
-            spawn_visual_if_needed(
-                neighbor,
-                &world,
-                &chunks,
-                &visuals,
-                &mut commands,
-                &asset_server,
-            );
-        }
+pub(crate) fn block_pos(args: &[String], first: usize, feet: Vec3) -> Result<IVec3, String> {
+    Ok(IVec3::new(
+        parse_coord(args, first, "x", feet.x)?.floor() as i32,
+        parse_coord(args, first + 1, "y", feet.y)?.floor() as i32,
+        parse_coord(args, first + 2, "z", feet.z)?.floor() as i32,
+    ))
+}
+
+pub(crate) fn block_arg(args: &[String], index: usize) -> Result<BlockType, String> {
+    let name: String = parse_arg(args, index, "block")?;
+    BlockType::from_name(&name).ok_or_else(|| format!("Unknown block '{}'", name))
+}
+
+fn seed_command(In(_args): In<Vec<String>>, world: Res<World>) -> CommandResult {
+    Ok(format!("Seed: {}", world.seed))
+}
+
+fn setblock_command(
+    In(args): In<Vec<String>>,
+    mut editor: BlockEditor,
+    players: Query<(&Player, &Transform)>,
+) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let pos = block_pos(&args, 0, player_feet(&players))?;
+    let block = block_arg(&args, 3)?;
+    if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+        return Err(format!("y must be 0 to {}", CHUNK_HEIGHT - 1));
     }
+    if editor.get_block(pos).is_none() {
+        return Err("That position is not loaded".to_string());
+    }
+    editor.set_block(pos, block);
+    Ok(format!("Set {} {} {} to {:?}", pos.x, pos.y, pos.z, block))
+}
+
+/// The blocks of the box between two corners, clamped to the world's
+/// height, or an error if there are more than `MAX_EDIT_VOLUME`.
+pub(crate) fn edit_region(a: IVec3, b: IVec3) -> Result<(IVec3, IVec3), String> {
+    let min = a.min(b).max(IVec3::new(i32::MIN, 0, i32::MIN));
+    let max = a.max(b).min(IVec3::new(i32::MAX, CHUNK_HEIGHT as i32 - 1, i32::MAX));
+    let size = (max - min + IVec3::ONE).as_i64vec3().max(bevy::math::I64Vec3::ZERO);
+    let volume = size.x * size.y * size.z;
+    if volume > MAX_EDIT_VOLUME {
+        return Err(format!("{} blocks is too many; edits are limited to {}", volume, MAX_EDIT_VOLUME));
+    }
+    Ok((min, max))
+}
+
+pub(crate) fn region_blocks(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.x..=max.x).flat_map(move |x| (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z))))
+}
+
+fn fill_command(
+    In(args): In<Vec<String>>,
+    mut editor: BlockEditor,
+    players: Query<(&Player, &Transform)>,
+) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let feet = player_feet(&players);
+    let (min, max) = edit_region(block_pos(&args, 0, feet)?, block_pos(&args, 3, feet)?)?;
+    let block = block_arg(&args, 6)?;
+    let changed = editor.apply(region_blocks(min, max).map(|pos| (pos, block)));
+    Ok(format!("Filled {} blocks with {:?}", changed.len(), block))
+}
+
+fn render_distance_command(In(args): In<Vec<String>>, mut world: ResMut<World>) -> CommandResult {
+    if args.is_empty() {
+        return Ok(format!("Render distance is {} chunks", world.render_distance));
+    }
+    let distance: i32 = parse_arg(&args, 0, "chunks")?;
+    if !(1..=MAX_RENDER_DISTANCE).contains(&distance) {
+        return Err(format!("Render distance must be 1 to {} chunks", MAX_RENDER_DISTANCE));
+    }
+    world.render_distance = distance;
+    Ok(format!("Render distance set to {} chunks", distance))
+}
+
+fn load_shape_command(In(args): In<Vec<String>>, mut world: ResMut<World>) -> CommandResult {
+    world.load_shape = match args.first().map(String::as_str) {
+        None => return Ok(format!("Chunks load in a {:?}", world.load_shape)),
+        Some("circle") => LoadShape::Circle,
+        Some("square") => LoadShape::Square,
+        Some(other) => return Err(format!("Unknown shape '{}'; use circle or square", other)),
+    };
+    Ok(format!("Chunks now load in a {:?}", world.load_shape))
 }
@@ -0,0 +1,147 @@
+use crate::block::BlockType;
+use crate::camera::Player;
+use crate::console::{CommandResult, ConsoleCommandsExt, parse_arg};
+use crate::journal::BlockEditor;
+use crate::vox::VoxSelection;
+use crate::voxel_world::VoxelWorld;
+use crate::world::{block_arg, edit_region, player_feet, region_blocks};
+use bevy::prelude::*;
+
+const SELECTION_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+
+/// Blocks copied with `/copy`, relative to the player's feet at the time,
+/// so `/paste` puts them back in the same place relative to the player.
+#[derive(Resource, Default)]
+struct Clipboard {
+    blocks: Vec<(IVec3, BlockType)>,
+}
+
+pub struct WorldEditPlugin;
+
+impl Plugin for WorldEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>()
+            .add_console_command("sel", "[clear]", "Show or clear the selection (set corners with Z / X)", sel_command)
+            .add_console_command("set", "<block>", "Fill the selection", set_command)
+            .add_console_command("replace", "<from> <to>", "Replace one block with another in the selection", replace_command)
+            .add_console_command("hollow", "<block>", "Make the selection a hollow box of <block>", hollow_command)
+            .add_console_command("copy", "", "Copy the selection, relative to where you stand", copy_command)
+            .add_console_command("paste", "[-a]", "Paste relative to where you stand; -a skips air", paste_command)
+            .add_console_command("rotate", "[90|180|270]", "Turn the clipboard clockwise around you", rotate_command)
+            .add_systems(Update, draw_selection);
+    }
+}
+
+fn draw_selection(selection: Res<VoxSelection>, mut gizmos: Gizmos) {
+    let (min, max) = match (selection.corner_a, selection.corner_b) {
+        (Some(a), Some(b)) => (a.min(b), a.max(b)),
+        (Some(corner), None) | (None, Some(corner)) => (corner, corner),
+        (None, None) => return,
+    };
+    // Slightly oversized so the lines are not hidden inside block faces
+    let size = (max - min + IVec3::ONE).as_vec3() + Vec3::splat(0.04);
+    let center = (min.as_vec3() + (max + IVec3::ONE).as_vec3()) / 2.0;
+    gizmos.cuboid(Transform::from_translation(center).with_scale(size), SELECTION_COLOR);
+}
+
+fn selected_region(selection: &VoxSelection) -> Result<(IVec3, IVec3), String> {
+    match (selection.corner_a, selection.corner_b) {
+        (Some(a), Some(b)) => edit_region(a, b),
+        _ => Err("Select both corners first (Z / X on a block)".to_string()),
+    }
+}
+
+fn feet_block(players: &Query<(&Player, &Transform)>) -> IVec3 {
+    player_feet(players).floor().as_ivec3()
+}
+
+fn sel_command(In(args): In<Vec<String>>, mut selection: ResMut<VoxSelection>) -> CommandResult {
+    if args.first().map(String::as_str) == Some("clear") {
+        *selection = VoxSelection::default();
+        return Ok("Selection cleared".to_string());
+    }
+    let (min, max) = selected_region(&selection)?;
+    let size = max - min + IVec3::ONE;
+    Ok(format!("Selected {} to {} ({}×{}×{})", min, max, size.x, size.y, size.z))
+}
+
+fn set_command(In(args): In<Vec<String>>, selection: Res<VoxSelection>, mut editor: BlockEditor) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let block = block_arg(&args, 0)?;
+    let (min, max) = selected_region(&selection)?;
+    let count = editor.apply(region_blocks(min, max).map(|pos| (pos, block))).len();
+    Ok(format!("Set {} blocks to {:?}", count, block))
+}
+
+fn replace_command(In(args): In<Vec<String>>, selection: Res<VoxSelection>, mut editor: BlockEditor) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let from = block_arg(&args, 0)?;
+    let to = block_arg(&args, 1)?;
+    let (min, max) = selected_region(&selection)?;
+    let edits: Vec<(IVec3, BlockType)> = region_blocks(min, max)
+        .filter(|&pos| editor.get_block(pos) == Some(from))
+        .map(|pos| (pos, to))
+        .collect();
+    let count = editor.apply(edits).len();
+    Ok(format!("Replaced {} {:?} with {:?}", count, from, to))
+}
+
+fn hollow_command(In(args): In<Vec<String>>, selection: Res<VoxSelection>, mut editor: BlockEditor) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    let block = block_arg(&args, 0)?;
+    let (min, max) = selected_region(&selection)?;
+    let edits = region_blocks(min, max).map(|pos| {
+        let on_shell = pos.cmpeq(min).any() || pos.cmpeq(max).any();
+        (pos, if on_shell { block } else { BlockType::Air })
+    });
+    let count = editor.apply(edits).len();
+    Ok(format!("Built a hollow {:?} box ({} blocks changed)", block, count))
+}
+
+fn copy_command(
+    In(_args): In<Vec<String>>,
+    selection: Res<VoxSelection>,
+    voxels: VoxelWorld,
+    players: Query<(&Player, &Transform)>,
+    mut clipboard: ResMut<Clipboard>,
+) -> CommandResult {
+    let (min, max) = selected_region(&selection)?;
+    let origin = feet_block(&players);
+    clipboard.blocks = voxels.region(min, max).map(|(pos, block)| (pos - origin, block)).collect();
+    Ok(format!("Copied {} blocks", clipboard.blocks.len()))
+}
+
+fn paste_command(
+    In(args): In<Vec<String>>,
+    mut editor: BlockEditor,
+    players: Query<(&Player, &Transform)>,
+    clipboard: Res<Clipboard>,
+) -> CommandResult {
+    editor.allow_bulk_edits()?;
+    if clipboard.blocks.is_empty() {
+        return Err("The clipboard is empty; /copy first".to_string());
+    }
+    let skip_air = args.iter().any(|a| a == "-a");
+    let origin = feet_block(&players);
+    let edits = clipboard
+        .blocks
+        .iter()
+        .filter(|(_, block)| !(skip_air && *block == BlockType::Air))
+        .map(|&(offset, block)| (origin + offset, block));
+    let count = editor.apply(edits).len();
+    Ok(format!("Pasted {} blocks", count))
+}
+
+fn rotate_command(In(args): In<Vec<String>>, mut clipboard: ResMut<Clipboard>) -> CommandResult {
+    let degrees: i32 = if args.is_empty() { 90 } else { parse_arg(&args, 0, "degrees")? };
+    if degrees.rem_euclid(90) != 0 {
+        return Err("Rotation must be a multiple of 90 degrees".to_string());
+    }
+    for _ in 0..degrees.rem_euclid(360) / 90 {
+        // Clockwise seen from above: +X turns to +Z
+        for (offset, _) in clipboard.blocks.iter_mut() {
+            *offset = IVec3::new(-offset.z, offset.y, offset.x);
+        }
+    }
+    Ok(format!("Rotated the clipboard {} degrees", degrees.rem_euclid(360)))
+}